@@ -0,0 +1,76 @@
+use super::Check;
+use crate::io::{error, ErrorKind, Read};
+
+/// Hashes all bytes read from `inner` and compares the result with the expected
+/// check value once `inner` reports the end of the stream.
+///
+/// A mismatch is reported as an [InvalidData](ErrorKind::InvalidData) error by the read
+/// that would otherwise have returned 0.
+///
+/// # Examples
+/// ```
+/// use std::io::Read;
+/// use lzma_rust::check::{Check, CheckReader, Crc32};
+/// use lzma_rust::LZMA2Reader;
+/// let compressed = [1, 0, 12, 72, 101, 108, 108, 111, 44, 32, 119, 111, 114, 108, 100, 33, 0];
+/// let lzma2 = LZMA2Reader::new(&compressed[..], 4096, None);
+/// let mut reader = CheckReader::<_, Crc32>::new(lzma2, Crc32::checksum(b"Hello, world!"));
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], b"Hello, world!");
+///
+/// let lzma2 = LZMA2Reader::new(&compressed[..], 4096, None);
+/// let mut reader = CheckReader::<_, Crc32>::new(lzma2, 0);
+/// assert!(reader.read_to_end(&mut Vec::new()).is_err());
+/// ```
+pub struct CheckReader<R, C: Check> {
+    inner: R,
+    check: C,
+    expected: C::Output,
+}
+
+impl<R, C: Check> CheckReader<R, C> {
+    pub fn new(inner: R, expected: C::Output) -> Self {
+        Self {
+            inner,
+            check: C::default(),
+            expected,
+        }
+    }
+
+    /// Check value of the bytes read so far.
+    pub fn value(&self) -> C::Output {
+        self.check.value()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl<R: Read, C: Check> embedded_io::ErrorType for CheckReader<R, C> {
+    type Error = <R as embedded_io::ErrorType>::Error;
+}
+
+impl<R: Read, C: Check> Read for CheckReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> crate::io::lzma_reader_result!(R, usize) {
+        let size = self.inner.read(buf)?;
+        self.check.update(&buf[..size]);
+        if size == 0 && !buf.is_empty() && self.check.value() != self.expected {
+            return error!(
+                crate::io::write_error_kind!(R, ErrorKind::InvalidData),
+                "Integrity check does not match"
+            );
+        }
+        Ok(size)
+    }
+}
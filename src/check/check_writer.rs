@@ -0,0 +1,86 @@
+use super::Check;
+use crate::io::{error, ErrorKind, Write};
+
+/// Hashes all bytes written to `inner`.
+///
+/// Writing an empty buffer finishes the stream, following the convention of
+/// [LZMA2Writer](crate::LZMA2Writer): the empty write is passed on to `inner` and,
+/// if an expected check value was given, a mismatch is reported as an
+/// [InvalidData](ErrorKind::InvalidData) error.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use lzma_rust::check::{Check, CheckWriter, Crc32};
+/// use lzma_rust::{CountingWriter, LZMA2Options, LZMA2Writer};
+/// let options = LZMA2Options::default();
+/// let lzma2 = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+/// let mut writer = CheckWriter::<_, Crc32>::new(lzma2);
+/// writer.write_all(b"Hello, world!").unwrap();
+/// writer.write(&[]).unwrap();
+/// assert_eq!(writer.value(), Crc32::checksum(b"Hello, world!"));
+/// ```
+pub struct CheckWriter<W, C: Check> {
+    inner: W,
+    check: C,
+    expected: Option<C::Output>,
+}
+
+impl<W, C: Check> CheckWriter<W, C> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            check: C::default(),
+            expected: None,
+        }
+    }
+
+    /// Creates a writer that verifies the written bytes against `expected` when finished.
+    pub fn new_expected(inner: W, expected: C::Output) -> Self {
+        Self {
+            inner,
+            check: C::default(),
+            expected: Some(expected),
+        }
+    }
+
+    /// Check value of the bytes written so far.
+    pub fn value(&self) -> C::Output {
+        self.check.value()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl<W: Write, C: Check> embedded_io::ErrorType for CheckWriter<W, C> {
+    type Error = <W as embedded_io::ErrorType>::Error;
+}
+
+impl<W: Write, C: Check> Write for CheckWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> crate::io::write_result!(W, usize) {
+        let size = self.inner.write(buf)?;
+        self.check.update(&buf[..size]);
+        if buf.is_empty() && matches!(self.expected, Some(e) if e != self.check.value()) {
+            return error!(
+                crate::io::write_error_kind!(W, ErrorKind::InvalidData),
+                "Integrity check does not match"
+            );
+        }
+        Ok(size)
+    }
+
+    fn flush(&mut self) -> crate::io::write_result!(W, ()) {
+        self.inner.flush()
+    }
+}
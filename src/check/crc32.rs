@@ -0,0 +1,62 @@
+use super::Check;
+
+/// Incremental CRC32 (IEEE 802.3) as used by .xz, .lz, .7z and .zip.
+///
+/// # Examples
+/// ```
+/// use lzma_rust::check::{Check, Crc32};
+/// let mut crc = Crc32::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.value(), 0xCBF43926);
+/// assert_eq!(Crc32::checksum(b"123456789"), 0xCBF43926);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc32 {
+    crc: u32,
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut r = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            r = if r & 1 != 0 {
+                (r >> 1) ^ 0xEDB88320
+            } else {
+                r >> 1
+            };
+            j += 1;
+        }
+        table[i] = r;
+        i += 1;
+    }
+    table
+}
+
+impl Crc32 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Check for Crc32 {
+    type Output = u32;
+
+    fn update(&mut self, buf: &[u8]) {
+        let mut crc = !self.crc;
+        for &b in buf {
+            crc = CRC32_TABLE[((crc ^ b as u32) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        self.crc = !crc;
+    }
+
+    #[inline]
+    fn value(&self) -> u32 {
+        self.crc
+    }
+}
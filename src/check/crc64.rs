@@ -0,0 +1,61 @@
+use super::Check;
+
+/// Incremental CRC64 (ECMA-182, reflected) as used by .xz.
+///
+/// # Examples
+/// ```
+/// use lzma_rust::check::{Check, Crc64};
+/// let mut crc = Crc64::new();
+/// crc.update(b"1234");
+/// crc.update(b"56789");
+/// assert_eq!(crc.value(), 0x995DC9BBDF1939FA);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc64 {
+    crc: u64,
+}
+
+const CRC64_TABLE: [u64; 256] = crc64_table();
+
+const fn crc64_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut r = i as u64;
+        let mut j = 0;
+        while j < 8 {
+            r = if r & 1 != 0 {
+                (r >> 1) ^ 0xC96C5795D7870F42
+            } else {
+                r >> 1
+            };
+            j += 1;
+        }
+        table[i] = r;
+        i += 1;
+    }
+    table
+}
+
+impl Crc64 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Check for Crc64 {
+    type Output = u64;
+
+    fn update(&mut self, buf: &[u8]) {
+        let mut crc = !self.crc;
+        for &b in buf {
+            crc = CRC64_TABLE[((crc ^ b as u64) & 0xFF) as usize] ^ (crc >> 8);
+        }
+        self.crc = !crc;
+    }
+
+    #[inline]
+    fn value(&self) -> u64 {
+        self.crc
+    }
+}
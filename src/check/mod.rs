@@ -0,0 +1,39 @@
+//! Integrity checks used by the container formats.
+//!
+//! All hashers are incremental and work without `alloc`. [CheckReader] and [CheckWriter]
+//! hash the uncompressed bytes flowing through a reader or writer, e.g. [LZMA2Reader](crate::LZMA2Reader),
+//! and report a mismatch with the expected value as [InvalidData](crate::io::ErrorKind::InvalidData).
+
+mod crc32;
+mod crc64;
+mod sha256;
+pub use crc32::*;
+pub use crc64::*;
+pub use sha256::*;
+
+#[cfg(feature = "alloc")]
+mod check_reader;
+#[cfg(feature = "alloc")]
+mod check_writer;
+#[cfg(feature = "alloc")]
+pub use check_reader::*;
+#[cfg(feature = "alloc")]
+pub use check_writer::*;
+
+/// An incremental hasher.
+pub trait Check: Default {
+    type Output: Copy + PartialEq + core::fmt::Debug;
+
+    /// Hashes `buf` in addition to all data passed before.
+    fn update(&mut self, buf: &[u8]);
+
+    /// Returns the check value of all data passed so far.
+    fn value(&self) -> Self::Output;
+
+    /// Computes the check value of `buf` in one go.
+    fn checksum(buf: &[u8]) -> Self::Output {
+        let mut check = Self::default();
+        check.update(buf);
+        check.value()
+    }
+}
@@ -86,7 +86,8 @@ impl<
     }
 
     pub fn end_marker_detected(&self) -> bool {
-        self.reps[0] == -1
+        // distances are 32 bits wide, the end marker is the distance 0xFFFFFFFF
+        self.reps[0] as i32 == -1
     }
 
     pub fn decode<R: RangeSource>(
@@ -0,0 +1,139 @@
+use crate::filter::{BCJFilter, FILTER_BUF_SIZE};
+use crate::io::{error, write_error_kind, ErrorKind, Write};
+
+/// Encodes data with a BCJ filter, usually before it is compressed by an
+/// [LZMA2Writer](crate::LZMA2Writer). See [BCJReader](crate::BCJReader) for an example.
+///
+/// The last few bytes can only be converted once it is known whether more data follows, so
+/// they are kept until [finish](Self::finish) is called. Writing an empty buffer finishes
+/// this writer and then the inner writer, like it does for `LZMA2Writer`.
+pub struct BCJWriter<W> {
+    inner: W,
+    filter: BCJFilter,
+    buf: [u8; FILTER_BUF_SIZE],
+    pos: usize,
+    unfiltered: usize,
+    finished: bool,
+}
+
+impl<W: Write> BCJWriter<W> {
+    pub fn new(inner: W, filter: BCJFilter) -> Self {
+        Self {
+            inner,
+            filter,
+            buf: [0; FILTER_BUF_SIZE],
+            pos: 0,
+            unfiltered: 0,
+            finished: false,
+        }
+    }
+
+    /// Creates a writer that encodes with the x86 BCJ filter.
+    /// - [start_pos] - the start offset of the filter, usually 0.
+    pub fn new_x86(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_x86(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the ARM BCJ filter.
+    pub fn new_arm(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_arm(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the ARM-Thumb BCJ filter.
+    pub fn new_arm_thumb(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_arm_thumb(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the ARM64 BCJ filter.
+    pub fn new_arm64(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_arm64(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the PowerPC BCJ filter.
+    pub fn new_ppc(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_ppc(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the SPARC BCJ filter.
+    pub fn new_sparc(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_sparc(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the IA-64 BCJ filter.
+    pub fn new_ia64(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_ia64(true, start_pos))
+    }
+
+    /// Creates a writer that encodes with the RISC-V BCJ filter.
+    pub fn new_riscv(inner: W, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_riscv(true, start_pos))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Writes the remaining unconverted bytes. The inner writer isn't finished.
+    pub fn finish(&mut self) -> crate::io::write_result!(W, ()) {
+        if !self.finished {
+            self.inner
+                .write_all(&self.buf[self.pos..self.pos + self.unfiltered])?;
+            self.finished = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl<W: Write> embedded_io::ErrorType for BCJWriter<W> {
+    type Error = <W as embedded_io::ErrorType>::Error;
+}
+
+impl<W: Write> Write for BCJWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> crate::io::write_result!(W, usize) {
+        if buf.is_empty() && !self.finished {
+            self.finish()?;
+            self.inner.write(buf)?;
+            return Ok(0);
+        }
+        if self.finished {
+            return error!(write_error_kind!(W, ErrorKind::Other), "BCJ finished");
+        }
+
+        let mut off = 0;
+        while off < buf.len() {
+            let end = self.pos + self.unfiltered;
+            let copy_size = (buf.len() - off).min(FILTER_BUF_SIZE - end);
+            self.buf[end..end + copy_size].copy_from_slice(&buf[off..off + copy_size]);
+            off += copy_size;
+            self.unfiltered += copy_size;
+
+            let filtered = self
+                .filter
+                .code(&mut self.buf[self.pos..self.pos + self.unfiltered]);
+            self.unfiltered -= filtered;
+            self.inner
+                .write_all(&self.buf[self.pos..self.pos + filtered])?;
+            self.pos += filtered;
+
+            if self.pos + self.unfiltered == FILTER_BUF_SIZE {
+                self.buf.copy_within(self.pos..FILTER_BUF_SIZE, 0);
+                self.pos = 0;
+            }
+        }
+        Ok(off)
+    }
+
+    /// Flushes the inner writer. The bytes that can't be converted yet are kept.
+    fn flush(&mut self) -> crate::io::write_result!(W, ()) {
+        self.inner.flush()
+    }
+}
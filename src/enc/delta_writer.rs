@@ -0,0 +1,61 @@
+use crate::filter::{DeltaFilter, FILTER_BUF_SIZE};
+use crate::io::Write;
+
+/// Encodes data with a Delta filter, usually before it is compressed by an
+/// [LZMA2Writer](crate::LZMA2Writer). See [DeltaReader](crate::DeltaReader) for an example.
+///
+/// Nothing is buffered between writes. Writing an empty buffer is passed on to the inner
+/// writer, which finishes an `LZMA2Writer`.
+pub struct DeltaWriter<W> {
+    inner: W,
+    filter: DeltaFilter,
+    buf: [u8; FILTER_BUF_SIZE],
+}
+
+impl<W: Write> DeltaWriter<W> {
+    /// Creates a writer that encodes with the Delta filter.
+    /// - [distance] - the distance in bytes, see [DeltaFilter::new].
+    pub fn new(inner: W, distance: usize) -> Self {
+        Self {
+            inner,
+            filter: DeltaFilter::new(distance),
+            buf: [0; FILTER_BUF_SIZE],
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl<W: Write> embedded_io::ErrorType for DeltaWriter<W> {
+    type Error = <W as embedded_io::ErrorType>::Error;
+}
+
+impl<W: Write> Write for DeltaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> crate::io::write_result!(W, usize) {
+        if buf.is_empty() {
+            return self.inner.write(buf);
+        }
+        for chunk in buf.chunks(FILTER_BUF_SIZE) {
+            let out = &mut self.buf[..chunk.len()];
+            out.copy_from_slice(chunk);
+            self.filter.encode(out);
+            self.inner.write_all(out)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> crate::io::write_result!(W, ()) {
+        self.inner.flush()
+    }
+}
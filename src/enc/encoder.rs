@@ -153,6 +153,14 @@ impl LZMAEncoder {
             )
         };
         let lz = match mf {
+            MFType::HC3 => LZEncoder::new_hc3(
+                dict_size,
+                extra_size_before,
+                extra_size_after,
+                nice_len as _,
+                MATCH_LEN_MAX as _,
+                depth_limit,
+            ),
             MFType::HC4 => LZEncoder::new_hc4(
                 dict_size,
                 extra_size_before,
@@ -161,6 +169,22 @@ impl LZMAEncoder {
                 MATCH_LEN_MAX as _,
                 depth_limit,
             ),
+            MFType::BT2 => LZEncoder::new_bt2(
+                dict_size,
+                extra_size_before,
+                extra_size_after,
+                nice_len as _,
+                MATCH_LEN_MAX as _,
+                depth_limit,
+            ),
+            MFType::BT3 => LZEncoder::new_bt3(
+                dict_size,
+                extra_size_before,
+                extra_size_after,
+                nice_len as _,
+                MATCH_LEN_MAX as _,
+                depth_limit,
+            ),
             MFType::BT4 => LZEncoder::new_bt4(
                 dict_size,
                 extra_size_before,
@@ -222,8 +246,7 @@ impl LZMAEncoder {
         rc: &mut RangeEncoder<W>,
         mode: &mut dyn LZMAEncoderTrait,
     ) -> crate::io::write_result!(W, ()) {
-        let encode_init = self.encode_init(rc)?;
-        if !self.lz.is_started() && !encode_init {
+        if !self.lz.is_started() && !self.encode_init(rc)? {
             return Ok(());
         }
         while self.encode_symbol(rc, mode)? {}
@@ -640,6 +663,36 @@ impl LZMAEncoder {
         }
         Ok(true)
     }
+
+    /// Encodes symbols until more input is needed or the last symbol made the compressed
+    /// size exceed `out_limit`. That symbol is taken back by restoring the range encoder, so
+    /// `uncompressed_size` counts only the bytes that fit, and the encoder can't be used
+    /// afterwards. Returns `true` if encoding stopped at the limit.
+    #[cfg(not(feature = "no_std"))]
+    pub fn encode_for_microlzma(
+        &mut self,
+        rc: &mut RangeEncoder<RangeEncoderBuffer>,
+        mode: &mut dyn LZMAEncoderTrait,
+        out_limit: u64,
+    ) -> crate::io::write_result!(RangeEncoderBuffer, bool) {
+        loop {
+            let state = rc.save();
+            let uncompressed_size = self.data.uncompressed_size;
+            let encoded = if self.lz.is_started() {
+                self.encode_symbol(rc, mode)?
+            } else {
+                self.encode_init(rc)?
+            };
+            if !encoded {
+                return Ok(false);
+            }
+            if rc.get_pending_size() > out_limit {
+                rc.restore(state);
+                self.data.uncompressed_size = uncompressed_size;
+                return Ok(true);
+            }
+        }
+    }
 }
 
 impl Deref for LZMAEncoder {
@@ -0,0 +1,196 @@
+use crate::check::{Check, Crc32};
+use crate::io::{error, ErrorKind, Result, Write};
+use crate::lzip::{
+    encode_dict_size, encode_header, Trailer, HEADER_SIZE, LC, LP, PB, TRAILER_SIZE,
+};
+
+use super::{CountingWriter, LZMA2Options, LZMAWriter};
+
+/// Options for [LzipWriter].
+#[derive(Debug, Clone)]
+pub struct LzipOptions {
+    /// `lc`, `lp` and `pb` must be 3, 0 and 2, the only values lzip supports.
+    pub lzma: LZMA2Options,
+    /// Maximum uncompressed size of a member. `None` puts all data into a single member.
+    pub member_data_size: Option<u64>,
+}
+
+impl Default for LzipOptions {
+    fn default() -> Self {
+        Self::with_preset(6)
+    }
+}
+
+impl LzipOptions {
+    /// preset: [0..9]
+    pub fn with_preset(preset: u64) -> Self {
+        Self {
+            lzma: LZMA2Options::with_preset(preset),
+            member_data_size: None,
+        }
+    }
+}
+
+/// Compresses into the lzip (.lz) file format.
+///
+/// The data is split into members of [LzipOptions::member_data_size], each compressed with
+/// [LZMAWriter] and followed by a trailer. The last member is finished by
+/// [finish](LzipWriter::finish) or by writing an empty buffer.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use lzma_rust::{LzipOptions, LzipWriter};
+/// let mut writer = LzipWriter::new(Vec::new(), &LzipOptions::with_preset(6)).unwrap();
+/// writer.write_all(b"Hello, world!").unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.into_inner();
+/// assert_eq!(&compressed[..5], b"LZIP\x01");
+/// ```
+pub struct LzipWriter<W: Write> {
+    inner: Option<W>,
+    member: Option<MemberWriter<W>>,
+    options: LzipOptions,
+    coded_dict_size: u8,
+    member_count: u64,
+    finished: bool,
+}
+
+impl<W: Write> LzipWriter<W> {
+    pub fn new(inner: W, options: &LzipOptions) -> Result<Self> {
+        let lzma = &options.lzma;
+        if (lzma.lc, lzma.lp, lzma.pb) != (LC, LP, PB) {
+            return error!(ErrorKind::InvalidInput, "lzip requires lc=3, lp=0 and pb=2");
+        }
+        if lzma.preset_dict.is_some() {
+            return error!(
+                ErrorKind::Unsupported,
+                "Preset dictionary is not supported by the lzip format"
+            );
+        }
+        if options.member_data_size == Some(0) {
+            return error!(ErrorKind::InvalidInput, "lzip member size must not be 0");
+        }
+        Ok(Self {
+            inner: Some(inner),
+            member: None,
+            coded_dict_size: encode_dict_size(lzma.dict_size)?,
+            options: options.clone(),
+            member_count: 0,
+            finished: false,
+        })
+    }
+
+    /// Returns the underlying writer. Call [finish](Self::finish) first to complete the file.
+    pub fn into_inner(self) -> W {
+        match self.member {
+            Some(member) => member.lzma.rc.inner.inner,
+            None => self.inner.unwrap(),
+        }
+    }
+
+    fn start_member(&mut self) -> Result<()> {
+        let mut inner = self.inner.take().unwrap();
+        inner.write_all(&encode_header(self.coded_dict_size))?;
+        let lzma = LZMAWriter::new_no_header(CountingWriter::new(inner), &self.options.lzma, true)?;
+        self.member = Some(MemberWriter {
+            lzma,
+            crc32: Crc32::new(),
+            data_size: 0,
+        });
+        self.member_count += 1;
+        Ok(())
+    }
+
+    fn end_member(&mut self) -> Result<()> {
+        if let Some(member) = self.member.take() {
+            self.inner = Some(member.finish()?);
+        }
+        Ok(())
+    }
+
+    /// Finishes the last member. An empty input is written as a single empty member.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        if self.member_count == 0 {
+            self.start_member()?;
+        }
+        self.end_member()?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for LzipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() && !self.finished {
+            self.finish()?;
+            return Ok(0);
+        }
+        if self.finished {
+            return error!(ErrorKind::Other, "lzip finished");
+        }
+        let mut off = 0;
+        while off < buf.len() {
+            if self.member.is_none() {
+                self.start_member()?;
+            }
+            let member = self.member.as_mut().unwrap();
+            let mut len = buf.len() - off;
+            if let Some(member_data_size) = self.options.member_data_size {
+                len = len.min((member_data_size - member.data_size) as usize);
+            }
+            member.write_all(&buf[off..off + len])?;
+            off += len;
+            if Some(member.data_size) == self.options.member_data_size {
+                self.end_member()?;
+            }
+        }
+        Ok(off)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self.member.as_mut() {
+            Some(member) => member.lzma.flush(),
+            None => self.inner.as_mut().unwrap().flush(),
+        }
+    }
+}
+
+/// Compresses the data of a single member and keeps track of its CRC32 and size.
+struct MemberWriter<W: Write> {
+    lzma: LZMAWriter<W>,
+    crc32: Crc32,
+    data_size: u64,
+}
+
+impl<W: Write> MemberWriter<W> {
+    /// Writes the end marker of the LZMA data and the trailer.
+    fn finish(mut self) -> Result<W> {
+        self.lzma.finish()?;
+        let compressed_size = self.lzma.rc.inner.written_bytes() as u64;
+        let mut inner = self.lzma.rc.inner.inner;
+        let trailer = Trailer {
+            crc32: self.crc32.value(),
+            data_size: self.data_size,
+            member_size: (HEADER_SIZE + TRAILER_SIZE) as u64 + compressed_size,
+        };
+        inner.write_all(&trailer.encode())?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for MemberWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.lzma.write_all(buf)?;
+        self.crc32.update(buf);
+        self.data_size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.lzma.flush()
+    }
+}
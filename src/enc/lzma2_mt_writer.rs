@@ -0,0 +1,315 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::io::{error, ErrorKind, Result, Write};
+
+use super::{CountingWriter, LZMA2Options, LZMA2Writer};
+
+/// Options for [LZMA2MTWriter].
+#[derive(Debug, Clone)]
+pub struct LZMA2MTOptions {
+    pub lzma2: LZMA2Options,
+    /// Number of compression threads.
+    pub threads: usize,
+    /// Uncompressed size of a block. Every block starts with a dictionary reset, so the blocks
+    /// can be compressed independently, at the cost of a slightly worse ratio.
+    pub block_size: u64,
+    /// Maximum number of blocks that are being compressed or waiting to be written. Writing
+    /// waits while this many blocks are in flight, which bounds the memory usage.
+    pub max_in_flight: usize,
+}
+
+impl Default for LZMA2MTOptions {
+    fn default() -> Self {
+        Self::with_preset(6)
+    }
+}
+
+impl LZMA2MTOptions {
+    /// Uses one thread per available CPU core.
+    ///
+    /// preset: [0..9]
+    pub fn with_preset(preset: u64) -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(LZMA2Options::with_preset(preset), threads)
+    }
+
+    /// Uses blocks of three times the dictionary size, but at least 1 MiB, like xz does, and
+    /// allows two blocks per thread to be in flight.
+    pub fn new(lzma2: LZMA2Options, threads: usize) -> Self {
+        let block_size = (lzma2.dict_size * 3).max(1 << 20);
+        Self {
+            lzma2,
+            threads,
+            block_size,
+            max_in_flight: threads * 2,
+        }
+    }
+}
+
+struct Job {
+    data: Vec<u8>,
+    use_preset_dict: bool,
+    result: Sender<Result<Vec<u8>>>,
+}
+
+/// Compresses into the LZMA2 format on multiple threads.
+///
+/// The input is split into blocks of [LZMA2MTOptions::block_size], which are compressed in
+/// parallel and written in order. The output is a regular LZMA2 stream that [LZMA2Reader]
+/// can decompress, and doesn't depend on the number of threads. The preset dictionary of the
+/// options is only used by the first block.
+///
+/// [LZMA2Reader]: crate::LZMA2Reader
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::{LZMA2MTOptions, LZMA2MTWriter, LZMA2Options, LZMA2Reader};
+/// let mut options = LZMA2MTOptions::new(LZMA2Options::with_preset(1), 4);
+/// options.block_size = 64 << 10;
+/// let data: Vec<u8> = (0..1000000u32).flat_map(|i| (i % 1000).to_le_bytes()).collect();
+///
+/// let mut writer = LZMA2MTWriter::new(Vec::new(), &options).unwrap();
+/// writer.write_all(&data).unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.into_inner();
+///
+/// let mut reader = LZMA2Reader::new(&compressed[..], options.lzma2.dict_size, None);
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, data);
+/// ```
+pub struct LZMA2MTWriter<W: Write> {
+    inner: W,
+    block_size: usize,
+    max_in_flight: usize,
+    buf: Vec<u8>,
+    pending: VecDeque<Receiver<Result<Vec<u8>>>>,
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    first_block: bool,
+    finished: bool,
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<W: Write> LZMA2MTWriter<W> {
+    /// Creates a new compressor and starts its threads.
+    pub fn new(inner: W, options: &LZMA2MTOptions) -> Result<Self> {
+        if options.threads == 0 {
+            return error!(ErrorKind::InvalidInput, "Thread count must not be 0");
+        }
+        if options.max_in_flight == 0 {
+            return error!(ErrorKind::InvalidInput, "Max in flight must not be 0");
+        }
+        let block_size = match usize::try_from(options.block_size) {
+            Ok(0) => return error!(ErrorKind::InvalidInput, "Block size must not be 0"),
+            Ok(size) => size,
+            Err(_) => return error!(ErrorKind::InvalidInput, "Block size is too large"),
+        };
+        options.lzma2.validate()?;
+
+        // A block can't refer further back than its own start, so a larger dictionary would
+        // only waste memory.
+        let mut lzma2 = options.lzma2.clone();
+        lzma2.dict_size = lzma2
+            .dict_size
+            .min(options.block_size.max(crate::DICT_SIZE_MIN));
+        let lzma2 = Arc::new(lzma2);
+
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let workers = (0..options.threads.min(options.max_in_flight))
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let lzma2 = Arc::clone(&lzma2);
+                thread::spawn(move || run_worker(&receiver, &lzma2))
+            })
+            .collect();
+
+        Ok(Self {
+            inner,
+            block_size,
+            max_in_flight: options.max_in_flight,
+            buf: Vec::with_capacity(block_size.min(1 << 20)),
+            pending: VecDeque::new(),
+            jobs: Some(jobs),
+            workers,
+            first_block: true,
+            finished: false,
+            error: None,
+        })
+    }
+
+    /// Returns the underlying writer. Call [finish](Self::finish) first to complete the stream.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    fn submit_block(&mut self) -> Result<()> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        while self.pending.len() >= self.max_in_flight {
+            self.write_block(true)?;
+        }
+        let data = std::mem::replace(
+            &mut self.buf,
+            Vec::with_capacity(self.block_size.min(1 << 20)),
+        );
+        let (result, receiver) = mpsc::channel();
+        let job = Job {
+            data,
+            use_preset_dict: self.first_block,
+            result,
+        };
+        self.first_block = false;
+        if self.jobs.as_ref().unwrap().send(job).is_err() {
+            return error!(ErrorKind::Other, "LZMA2 compression threads have stopped");
+        }
+        self.pending.push_back(receiver);
+        // Write the blocks that are done without waiting for the others.
+        while !self.pending.is_empty() && self.write_block(false)? {}
+        Ok(())
+    }
+
+    /// Writes the oldest block in flight. Returns false if `wait` is false and the block
+    /// isn't compressed yet.
+    fn write_block(&mut self, wait: bool) -> Result<bool> {
+        let receiver = &self.pending[0];
+        let result = if wait {
+            receiver.recv().ok()
+        } else {
+            match receiver.try_recv() {
+                Ok(result) => Some(result),
+                Err(TryRecvError::Empty) => return Ok(false),
+                Err(TryRecvError::Disconnected) => None,
+            }
+        };
+        self.pending.pop_front();
+        match result {
+            Some(compressed) => self.inner.write_all(&compressed?)?,
+            None => return error!(ErrorKind::Other, "LZMA2 compression thread panicked"),
+        }
+        Ok(true)
+    }
+
+    fn wait_all(&mut self) -> Result<()> {
+        while !self.pending.is_empty() {
+            self.write_block(true)?;
+        }
+        Ok(())
+    }
+
+    fn check_error<T>(&mut self, result: Result<T>) -> Result<T> {
+        if let Err(e) = &result {
+            self.error = Some((e.kind(), e.to_string()));
+        }
+        result
+    }
+
+    fn finish_inner(&mut self) -> Result<()> {
+        self.submit_block()?;
+        self.wait_all()?;
+        self.inner.write_all(&[0x00])?;
+        self.finished = true;
+        self.jobs = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+        Ok(())
+    }
+
+    /// Compresses the remaining data, writes the end marker and stops the threads.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some((kind, msg)) = &self.error {
+            return error!(*kind, msg.clone());
+        }
+        if self.finished {
+            return Ok(());
+        }
+        let result = self.finish_inner();
+        self.check_error(result)
+    }
+}
+
+fn run_worker(receiver: &Mutex<Receiver<Job>>, options: &LZMA2Options) {
+    let mut options_without_preset = options.clone();
+    options_without_preset.preset_dict = None;
+    loop {
+        let job = match receiver.lock().unwrap().recv() {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let options = if job.use_preset_dict {
+            options
+        } else {
+            &options_without_preset
+        };
+        // The writer has been dropped if nobody waits for the result.
+        let _ = job.result.send(compress_block(&job.data, options));
+    }
+}
+
+/// Compresses a block into LZMA2 chunks without the end marker. The first chunk resets the
+/// dictionary unless there is a preset dictionary.
+fn compress_block(data: &[u8], options: &LZMA2Options) -> Result<Vec<u8>> {
+    let output = CountingWriter::new(Vec::with_capacity(data.len() / 2));
+    let mut writer = LZMA2Writer::new(output, options)?;
+    writer.write_all(data)?;
+    writer.finish()?;
+    let mut compressed = writer.inner.inner;
+    compressed.pop();
+    Ok(compressed)
+}
+
+impl<W: Write> Write for LZMA2MTWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() && !self.finished {
+            self.finish()?;
+            self.inner.write(buf)?;
+            return Ok(0);
+        }
+        if let Some((kind, msg)) = &self.error {
+            return error!(*kind, msg.clone());
+        }
+        if self.finished {
+            return error!(ErrorKind::Other, "LZMA2 finished");
+        }
+
+        let mut off = 0;
+        while off < buf.len() {
+            let size = (buf.len() - off).min(self.block_size - self.buf.len());
+            self.buf.extend_from_slice(&buf[off..off + size]);
+            off += size;
+            if self.buf.len() == self.block_size {
+                let result = self.submit_block();
+                self.check_error(result)?;
+            }
+        }
+        Ok(off)
+    }
+
+    /// Compresses the buffered data as a shorter block and waits until all blocks are written.
+    fn flush(&mut self) -> Result<()> {
+        if let Some((kind, msg)) = &self.error {
+            return error!(*kind, msg.clone());
+        }
+        if self.finished {
+            return error!(ErrorKind::Other, "LZMA2 flush finished");
+        }
+        let result = self.submit_block().and_then(|_| self.wait_all());
+        self.check_error(result)?;
+        self.inner.flush()
+    }
+}
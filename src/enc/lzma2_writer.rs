@@ -1,3 +1,5 @@
+use core::{fmt, str::FromStr};
+
 use crate::io::{error, transmute_result_error_type, write_error_kind, ErrorKind, Write};
 
 use super::counting::CountingWriter;
@@ -34,6 +36,8 @@ impl LZMA2Options {
     pub const NICE_LEN_MAX: u64 = 273;
     pub const NICE_LEN_MIN: u64 = 8;
     pub const DICT_SIZE_DEFAULT: u64 = 8 << 20;
+    /// The largest dictionary the encoder supports, as its positions must fit into an `i32`.
+    pub const DICT_SIZE_MAX: u64 = i32::MAX as u64 & !15;
     const PRESET_TO_DICT_SIZE: &'static [u64] = &[
         1 << 18,
         1 << 20,
@@ -71,6 +75,11 @@ impl LZMA2Options {
         }
     }
 
+    /// Starts building options from preset 6, see [LZMA2OptionsBuilder].
+    pub fn builder() -> LZMA2OptionsBuilder {
+        LZMA2OptionsBuilder::default()
+    }
+
     /// preset: [0..9]
     #[inline]
     pub fn with_preset(preset: u64) -> Self {
@@ -117,6 +126,175 @@ impl LZMA2Options {
         }
     }
 
+    /// Like [with_preset](Self::with_preset), with the slower settings of xz's extreme presets
+    /// `-0e` to `-9e`, which compress a little better.
+    ///
+    /// preset: [0..9]
+    ///
+    /// # Examples
+    /// ```
+    /// use lzma_rust::{EncodeMode, LZMA2Options};
+    /// let options = LZMA2Options::with_preset_extreme(6);
+    /// assert_eq!(options.dict_size, LZMA2Options::with_preset(6).dict_size);
+    /// assert_eq!(options.mode, EncodeMode::Normal);
+    /// assert_eq!(options.nice_len, 273);
+    /// assert_eq!(options.depth_limit, 512);
+    /// ```
+    #[inline]
+    pub fn with_preset_extreme(preset: u64) -> Self {
+        let mut opt = Self::with_preset(preset);
+        opt.set_preset_extreme(preset);
+        opt
+    }
+
+    /// preset: [0..9]
+    pub fn set_preset_extreme(&mut self, preset: u64) {
+        if preset > 9 {
+            return;
+        }
+        self.set_preset(preset);
+        self.mode = EncodeMode::Normal;
+        self.mf = MFType::BT4;
+        if preset == 3 || preset == 5 {
+            self.nice_len = 192;
+            self.depth_limit = 0;
+        } else {
+            self.nice_len = Self::NICE_LEN_MAX;
+            self.depth_limit = 512;
+        }
+    }
+
+    /// Shrinks the dictionary to what `input_size` bytes of input and the preset dictionary
+    /// can use. The match finder tables are sized by the dictionary, so they shrink too, and
+    /// [get_memory_usage](Self::get_memory_usage) reports the smaller usage.
+    ///
+    /// The size is rounded up to `2^n` or `2^n + 2^(n-1)` bytes like xz does, and is never
+    /// below [DICT_SIZE_MIN](crate::DICT_SIZE_MIN).
+    ///
+    /// # Examples
+    /// ```
+    /// use lzma_rust::LZMA2Options;
+    /// let mut options = LZMA2Options::with_preset(9);
+    /// let memory_usage = options.get_memory_usage();
+    /// options.shrink_dict_size(10 << 10);
+    /// assert_eq!(options.dict_size, 12 << 10);
+    /// assert!(options.get_memory_usage() < memory_usage / 100);
+    /// ```
+    pub fn shrink_dict_size(&mut self, input_size: u64) {
+        self.dict_size = self.dict_size_for_input(input_size);
+    }
+
+    pub(crate) fn dict_size_for_input(&self, input_size: u64) -> u64 {
+        let preset_dict_size = self.preset_dict.as_ref().map_or(0, |d| d.len() as u64);
+        let needed = input_size
+            .saturating_add(preset_dict_size)
+            .max(crate::DICT_SIZE_MIN);
+        if needed >= self.dict_size {
+            return self.dict_size;
+        }
+        let mut prop = 0;
+        while dict_size_of_prop(prop) < needed {
+            prop += 1;
+        }
+        self.dict_size.min(dict_size_of_prop(prop))
+    }
+
+    /// Adjusts the options so that [get_memory_usage](Self::get_memory_usage) fits into
+    /// `limit_kib` KiB, like xz's `--memlimit-compress`.
+    ///
+    /// The dictionary is made smaller first. If even the smallest one doesn't fit, BT4 is
+    /// replaced by HC4, which needs less memory, and the dictionary is lowered again from its
+    /// original size. If nothing fits, the options are left unchanged and an error is returned.
+    ///
+    /// # Examples
+    /// ```
+    /// use lzma_rust::LZMA2Options;
+    /// let mut options = LZMA2Options::with_preset(9);
+    /// options.fit_memory_limit(200 << 10).unwrap();
+    /// assert_eq!(options.dict_size, 8 << 20);
+    /// assert!(options.get_memory_usage() <= 200 << 10);
+    /// assert!(options.fit_memory_limit(100).is_err());
+    /// ```
+    pub fn fit_memory_limit(&mut self, limit_kib: u64) -> crate::io::Result<()> {
+        let dict_size = self.dict_size;
+        let mf = self.mf;
+        while self.get_memory_usage() > limit_kib {
+            if self.dict_size > crate::DICT_SIZE_MIN {
+                // Steps through the sizes that the dictionary size property can represent.
+                let prop = self.dict_size_prop() as u64;
+                self.dict_size = dict_size_of_prop(prop - 1);
+            } else if self.mf == MFType::BT4 {
+                self.mf = MFType::HC4;
+                self.dict_size = dict_size;
+            } else {
+                self.dict_size = dict_size;
+                self.mf = mf;
+                return error!(
+                    ErrorKind::OutOfMemory,
+                    "Encoder options do not fit into the memory limit"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the options can be used for LZMA2. The writers check this when they are
+    /// created, so that invalid options don't panic while compressing.
+    ///
+    /// # Examples
+    /// ```
+    /// use lzma_rust::LZMA2Options;
+    /// let mut options = LZMA2Options::with_preset(6);
+    /// assert!(options.validate().is_ok());
+    /// options.lp = 2;
+    /// assert!(options.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> crate::io::Result<()> {
+        self.validate_lzma()?;
+        if self.lc + self.lp > 4 {
+            return error!(
+                ErrorKind::InvalidInput,
+                "lc + lp must not exceed 4 in LZMA2"
+            );
+        }
+        Ok(())
+    }
+
+    /// Like [validate](Self::validate), but allows `lc + lp > 4` as the .lzma format does.
+    pub(crate) fn validate_lzma(&self) -> crate::io::Result<()> {
+        if self.lc > 8 {
+            return error!(ErrorKind::InvalidInput, "lc must not exceed 8");
+        }
+        if self.lp > 4 {
+            return error!(ErrorKind::InvalidInput, "lp must not exceed 4");
+        }
+        if self.pb > 4 {
+            return error!(ErrorKind::InvalidInput, "pb must not exceed 4");
+        }
+        if !(Self::NICE_LEN_MIN..=Self::NICE_LEN_MAX).contains(&self.nice_len) {
+            return error!(ErrorKind::InvalidInput, "nice_len must be 8..=273");
+        }
+        if self.dict_size < crate::DICT_SIZE_MIN {
+            return error!(
+                ErrorKind::InvalidInput,
+                "Dictionary size must be at least 4 KiB"
+            );
+        }
+        if self.dict_size > Self::DICT_SIZE_MAX {
+            return error!(
+                ErrorKind::InvalidInput,
+                "Dictionary size must be below 2 GiB"
+            );
+        }
+        Ok(())
+    }
+
+    /// Estimated memory usage of the encoder in KiB.
+    ///
+    /// The usage follows [dict_size](Self::dict_size) as set. Only
+    /// [LZMAWriter](crate::LZMAWriter) shrinks the dictionary to a known input size by itself,
+    /// so call [shrink_dict_size](Self::shrink_dict_size) first to get the usage for a small
+    /// input.
     pub fn get_memory_usage(&self) -> u64 {
         let dict_size = self.dict_size;
         let extra_size_before = get_extra_size_before(dict_size);
@@ -127,7 +305,257 @@ impl LZMA2Options {
     pub fn get_props(&self) -> u8 {
         ((self.pb * 5 + self.lp) * 9 + self.lc) as u8
     }
+
+    /// The LZMA2 dictionary size property byte, as stored in .xz and .7z filter properties.
+    ///
+    /// The byte can only represent sizes of the form `2^n` or `2^n + 2^(n-1)`, so other
+    /// dictionary sizes are rounded up to the next one, which is what the decoder will allocate.
+    /// See [lzma2_dict_size_from_prop](crate::lzma2_dict_size_from_prop) for the reverse.
+    ///
+    /// # Examples
+    /// ```
+    /// use lzma_rust::{lzma2_dict_size_from_prop, LZMA2Options};
+    /// let mut options = LZMA2Options::with_preset(6);
+    /// assert_eq!(options.dict_size_prop(), 22);
+    /// options.dict_size = 5 << 20;
+    /// assert_eq!(lzma2_dict_size_from_prop(options.dict_size_prop()).unwrap(), 6 << 20);
+    /// ```
+    pub fn dict_size_prop(&self) -> u8 {
+        let mut prop = 0;
+        while prop < 40 && dict_size_of_prop(prop as u64) < self.dict_size {
+            prop += 1;
+        }
+        prop
+    }
+}
+
+/// Builder of [LZMA2Options] that checks them in [build](Self::build).
+///
+/// # Examples
+/// ```
+/// use lzma_rust::lz::MFType;
+/// use lzma_rust::{LZMA2Options, LZMA2OptionsBuilder};
+/// let options = LZMA2Options::builder().lc(1).lp(3).mf(MFType::BT2).build().unwrap();
+/// assert_eq!((options.lc, options.lp), (1, 3));
+///
+/// // Start from another preset.
+/// let builder = LZMA2OptionsBuilder::from(LZMA2Options::with_preset(9));
+/// assert!(builder.lc(4).lp(1).build().is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct LZMA2OptionsBuilder {
+    options: LZMA2Options,
+}
+
+impl From<LZMA2Options> for LZMA2OptionsBuilder {
+    fn from(options: LZMA2Options) -> Self {
+        Self { options }
+    }
 }
+
+impl LZMA2OptionsBuilder {
+    /// Dictionary size in bytes, [DICT_SIZE_MIN](crate::DICT_SIZE_MIN) to
+    /// [DICT_SIZE_MAX](LZMA2Options::DICT_SIZE_MAX).
+    pub fn dict_size(mut self, dict_size: u64) -> Self {
+        self.options.dict_size = dict_size;
+        self
+    }
+
+    /// Number of literal context bits, 0 to 4 together with `lp`.
+    pub fn lc(mut self, lc: u64) -> Self {
+        self.options.lc = lc;
+        self
+    }
+
+    /// Number of literal position bits, 0 to 4 together with `lc`.
+    pub fn lp(mut self, lp: u64) -> Self {
+        self.options.lp = lp;
+        self
+    }
+
+    /// Number of position bits, 0 to 4.
+    pub fn pb(mut self, pb: u64) -> Self {
+        self.options.pb = pb;
+        self
+    }
+
+    pub fn mode(mut self, mode: EncodeMode) -> Self {
+        self.options.mode = mode;
+        self
+    }
+
+    /// Match length at which the encoder stops looking for longer matches,
+    /// [NICE_LEN_MIN](LZMA2Options::NICE_LEN_MIN) to [NICE_LEN_MAX](LZMA2Options::NICE_LEN_MAX).
+    pub fn nice_len(mut self, nice_len: u64) -> Self {
+        self.options.nice_len = nice_len;
+        self
+    }
+
+    pub fn mf(mut self, mf: MFType) -> Self {
+        self.options.mf = mf;
+        self
+    }
+
+    /// Maximum number of match candidates to check, 0 picks a default from `nice_len`.
+    pub fn depth_limit(mut self, depth_limit: i64) -> Self {
+        self.options.depth_limit = depth_limit;
+        self
+    }
+
+    pub fn preset_dict(mut self, preset_dict: crate::Vec<u8>) -> Self {
+        self.options.preset_dict = Some(preset_dict);
+        self
+    }
+
+    /// Returns the options, or an error describing the first invalid one.
+    pub fn build(self) -> crate::io::Result<LZMA2Options> {
+        self.options.validate()?;
+        Ok(self.options)
+    }
+}
+
+const MODE_NAMES: [(EncodeMode, &str); 2] =
+    [(EncodeMode::Fast, "fast"), (EncodeMode::Normal, "normal")];
+const MF_NAMES: [(MFType, &str); 5] = [
+    (MFType::HC3, "hc3"),
+    (MFType::HC4, "hc4"),
+    (MFType::BT2, "bt2"),
+    (MFType::BT3, "bt3"),
+    (MFType::BT4, "bt4"),
+];
+
+/// Parses a decimal number with an optional `k`, `M` or `G` suffix, which may be followed by
+/// `i` and `B` like in `64MiB`.
+fn parse_number(value: &str) -> crate::io::Result<u64> {
+    let digits = value.bytes().take_while(u8::is_ascii_digit).count();
+    let (number, suffix) = value.split_at(digits);
+    let shift = match suffix.as_bytes() {
+        [] => 0,
+        [unit, b'i' | b'B'] | [unit, b'i', b'B'] | [unit] => match unit.to_ascii_lowercase() {
+            b'k' => 10,
+            b'm' => 20,
+            b'g' => 30,
+            _ => return error!(ErrorKind::InvalidInput, "Invalid multiplier suffix"),
+        },
+        _ => return error!(ErrorKind::InvalidInput, "Invalid multiplier suffix"),
+    };
+    match number.parse::<u64>().map(|n| n.checked_mul(1 << shift)) {
+        Ok(Some(n)) => Ok(n),
+        Ok(None) => error!(ErrorKind::InvalidInput, "Value is too large"),
+        Err(_) => error!(
+            ErrorKind::InvalidInput,
+            "Value is not a non-negative decimal integer"
+        ),
+    }
+}
+
+/// Parses the LZMA2 options syntax of xz, such as `preset=6e,dict=64MiB,lc=4,mf=bt4`.
+///
+/// The options are `preset` (`0` to `9`, with `e` for [extreme](LZMA2Options::with_preset_extreme)),
+/// `dict`, `lc`, `lp`, `pb`, `mode` (`fast` or `normal`), `nice`, `mf` (`hc3`, `hc4`, `bt2`,
+/// `bt3` or `bt4`) and `depth`. They start from preset 6 and are applied in order, so a
+/// `preset` resets the options before it. The result is [validated](LZMA2Options::validate).
+///
+/// # Examples
+/// ```
+/// use lzma_rust::{EncodeMode, LZMA2Options};
+/// let options: LZMA2Options = "preset=6e,dict=64MiB,lc=4,lp=0,pb=0".parse().unwrap();
+/// assert_eq!(options.dict_size, 64 << 20);
+/// assert_eq!((options.lc, options.pb), (4, 0));
+/// assert_eq!(options.mode, EncodeMode::Normal);
+/// assert_eq!(
+///     options.to_string(),
+///     "dict=64MiB,lc=4,lp=0,pb=0,mode=normal,nice=273,mf=bt4,depth=512"
+/// );
+/// ```
+impl FromStr for LZMA2Options {
+    #[cfg(not(feature = "no_std"))]
+    type Err = std::io::Error;
+    #[cfg(feature = "no_std")]
+    type Err = embedded_io::ErrorKind;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut options = Self::default();
+        for option in s.split(',').map(str::trim).filter(|o| !o.is_empty()) {
+            let (name, value) = match option.split_once('=') {
+                Some((name, value)) => (name.trim(), value.trim()),
+                None => {
+                    return error!(
+                        ErrorKind::InvalidInput,
+                        "Options must be name=value pairs separated with commas"
+                    )
+                }
+            };
+            match name {
+                "preset" => {
+                    let (level, extreme) = match value.strip_suffix('e') {
+                        Some(level) => (level, true),
+                        None => (value, false),
+                    };
+                    let preset = match level.as_bytes() {
+                        [digit @ b'0'..=b'9'] => (digit - b'0') as u64,
+                        _ => return error!(ErrorKind::InvalidInput, "Unsupported preset"),
+                    };
+                    if extreme {
+                        options.set_preset_extreme(preset);
+                    } else {
+                        options.set_preset(preset);
+                    }
+                }
+                "dict" => options.dict_size = parse_number(value)?,
+                "lc" => options.lc = parse_number(value)?,
+                "lp" => options.lp = parse_number(value)?,
+                "pb" => options.pb = parse_number(value)?,
+                "nice" => options.nice_len = parse_number(value)?,
+                "depth" => {
+                    options.depth_limit = match i64::try_from(parse_number(value)?) {
+                        Ok(depth) => depth,
+                        Err(_) => return error!(ErrorKind::InvalidInput, "Value is too large"),
+                    }
+                }
+                "mode" => match MODE_NAMES.iter().find(|(_, n)| *n == value) {
+                    Some((mode, _)) => options.mode = *mode,
+                    None => return error!(ErrorKind::InvalidInput, "Invalid mode"),
+                },
+                "mf" => match MF_NAMES.iter().find(|(_, n)| *n == value) {
+                    Some((mf, _)) => options.mf = *mf,
+                    None => return error!(ErrorKind::InvalidInput, "Invalid match finder"),
+                },
+                _ => return error!(ErrorKind::InvalidInput, "Invalid option name"),
+            }
+        }
+        options.validate()?;
+        Ok(options)
+    }
+}
+
+/// Prints the options like `xz -vv` does, in the syntax that [from_str](LZMA2Options::from_str)
+/// parses. The preset dictionary is left out.
+impl fmt::Display for LZMA2Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (dict_size, unit) = [(30, "GiB"), (20, "MiB"), (10, "KiB")]
+            .into_iter()
+            .find(|(shift, _)| self.dict_size != 0 && self.dict_size % (1 << shift) == 0)
+            .map_or((self.dict_size, ""), |(shift, unit)| {
+                (self.dict_size >> shift, unit)
+            });
+        let mode = MODE_NAMES.iter().find(|(m, _)| *m == self.mode).unwrap().1;
+        let mf = MF_NAMES.iter().find(|(m, _)| *m == self.mf).unwrap().1;
+        // Any depth limit <= 0 means automatic, which is spelled `depth=0`.
+        let depth = self.depth_limit.max(0);
+        write!(
+            f,
+            "dict={}{},lc={},lp={},pb={},mode={},nice={},mf={},depth={}",
+            dict_size, unit, self.lc, self.lp, self.pb, mode, self.nice_len, mf, depth
+        )
+    }
+}
+
+/// The dictionary size that the LZMA2 dictionary size property `prop` stands for.
+const fn dict_size_of_prop(prop: u64) -> u64 {
+    (2 | (prop & 1)) << (prop / 2 + 11)
+}
+
 const COMPRESSED_SIZE_MAX: u64 = 64 << 10;
 pub fn get_extra_size_before(dict_size: u64) -> u64 {
     if COMPRESSED_SIZE_MAX > dict_size {
@@ -137,15 +565,26 @@ pub fn get_extra_size_before(dict_size: u64) -> u64 {
     }
 }
 
+/// How [LZMA2Writer::flush_mode] ends the current chunk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlushMode {
+    /// Ends the current chunk and keeps the dictionary, like [flush](Write::flush).
+    Sync,
+    /// Also resets the dictionary and the state, so that decoding can start with the next
+    /// chunk.
+    Full,
+}
+
 /// LZMA2 format writer
 /// # Examples
 /// ```
 /// use std::io::Write;
-/// use lzma_rust::enc::lzma2_writer::{LZMA2Options, LZMA2Writer};
-/// let mut writer = LZMA2Writer::new(crate::Vec::new(), &LZMA2Options::default());
-///    writer.write_all(b"hello world").unwrap();
-///    let compressed = writer.finish().unwrap();
-///
+/// use lzma_rust::{CountingWriter, LZMA2Options, LZMA2Writer};
+/// let options = LZMA2Options::default();
+/// let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+/// writer.write_all(b"hello world").unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.inner.inner;
 /// ```
 pub struct LZMA2Writer<W: Write> {
     pub inner: CountingWriter<W>,
@@ -161,7 +600,22 @@ pub struct LZMA2Writer<W: Write> {
 }
 
 impl<W: Write> LZMA2Writer<W> {
-    pub fn new(inner: CountingWriter<W>, options: &LZMA2Options) -> Self {
+    /// Fails with [InvalidInput](ErrorKind::InvalidInput) if the options are not
+    /// [valid](LZMA2Options::validate).
+    ///
+    /// The dictionary is allocated at the full [dict_size](LZMA2Options::dict_size), also for
+    /// a small input. If the input size is known, call
+    /// [shrink_dict_size](LZMA2Options::shrink_dict_size) on the options first, and use the
+    /// same options for the dictionary size in the container, since LZMA2 doesn't store it.
+    pub fn new(
+        inner: CountingWriter<W>,
+        options: &LZMA2Options,
+    ) -> crate::io::write_result!(W, Self) {
+        // Not `?`, the error type differs with no_std.
+        #[allow(clippy::question_mark)]
+        if let Err(e) = options.validate() {
+            return Err(write_error_kind!(W, e));
+        }
         let dict_size = options.dict_size;
         let rc = RangeEncoder::new_buffer(COMPRESSED_SIZE_MAX as usize);
         let (mut lzma, mode) = LZMAEncoder::new(
@@ -181,7 +635,7 @@ impl<W: Write> LZMA2Writer<W> {
             lzma.lz.set_preset_dict(dict_size, preset_dict);
             dict_reset_needed = false;
         }
-        Self {
+        Ok(Self {
             inner,
             rc,
             lzma,
@@ -192,7 +646,7 @@ impl<W: Write> LZMA2Writer<W> {
             props_needed: true,
             pending_size: 0,
             finished: false,
-        }
+        })
     }
 
     fn write_lzma(
@@ -306,6 +760,57 @@ impl<W: Write> LZMA2Writer<W> {
         Ok(())
     }
 
+    /// Writes out all pending data and returns the compressed offset of the chunk boundary.
+    ///
+    /// After a [FlushMode::Full] flush, a new [LZMA2Reader](crate::LZMA2Reader) can decode
+    /// the stream starting at the returned offset.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::io::{Read, Write};
+    /// use lzma_rust::{CountingWriter, FlushMode, LZMA2Options, LZMA2Reader, LZMA2Writer};
+    /// let options = LZMA2Options::default();
+    /// let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    /// writer.write_all(b"first record").unwrap();
+    /// let offset = writer.flush_mode(FlushMode::Full).unwrap() as usize;
+    /// writer.write_all(b"second record").unwrap();
+    /// writer.finish().unwrap();
+    /// let compressed = writer.inner.inner;
+    ///
+    /// let mut reader = LZMA2Reader::new(&compressed[offset..], options.dict_size, None);
+    /// let mut output = Vec::new();
+    /// reader.read_to_end(&mut output).unwrap();
+    /// assert_eq!(output, b"second record");
+    /// ```
+    pub fn flush_mode(&mut self, mode: FlushMode) -> crate::io::write_result!(W, u64) {
+        if self.finished {
+            return error!(
+                write_error_kind!(W, ErrorKind::Other),
+                "LZMA2 flush finished"
+            );
+        }
+        self.lzma.lz.set_flushing();
+        while self.pending_size > 0 {
+            transmute_result_error_type!(
+                self.lzma.encode_for_lzma2(&mut self.rc, &mut self.mode),
+                bool,
+                RangeEncoderBuffer,
+                W
+            )?;
+            self.write_chunk()?;
+        }
+        if mode == FlushMode::Full {
+            self.lzma.lz.reset();
+            self.lzma.reset(&mut self.mode);
+            self.lzma.reset_uncompressed_size();
+            self.dict_reset_needed = true;
+            self.state_reset_needed = true;
+            self.props_needed = true;
+        }
+        self.inner.flush()?;
+        Ok(self.inner.written_bytes() as u64)
+    }
+
     pub fn finish(&mut self) -> crate::io::write_result!(W, ()) {
         if !self.finished {
             self.write_end_marker()?;
@@ -319,9 +824,6 @@ impl<W: Write> embedded_io::ErrorType for LZMA2Writer<W> {
     type Error = <W as embedded_io::ErrorType>::Error;
 }
 
-impl<W: Write> Drop for LZMA2Writer<W> {
-    fn drop(&mut self) {}
-}
 impl<W: Write> Write for LZMA2Writer<W> {
     fn write(&mut self, buf: &[u8]) -> crate::io::write_result!(W, usize) {
         let mut len = buf.len();
@@ -353,22 +855,7 @@ impl<W: Write> Write for LZMA2Writer<W> {
     }
 
     fn flush(&mut self) -> crate::io::write_result!(W, ()) {
-        if self.finished {
-            return error!(
-                write_error_kind!(W, ErrorKind::Other),
-                "LZMA2 flush finished"
-            );
-        }
-        self.lzma.lz.set_flushing();
-        while self.pending_size > 0 {
-            transmute_result_error_type!(
-                self.lzma.encode_for_lzma2(&mut self.rc, &mut self.mode),
-                bool,
-                RangeEncoderBuffer,
-                W
-            )?;
-            self.write_chunk()?;
-        }
-        self.inner.flush()
+        self.flush_mode(FlushMode::Sync)?;
+        Ok(())
     }
 }
@@ -1,6 +1,7 @@
 use crate::io::{error, write_error_kind, ErrorKind, Write};
 
 use super::{range_enc::RangeEncoder, CountingWriter, LZMA2Options};
+use crate::LZMAHeader;
 
 use super::encoder::{LZMAEncoder, LZMAEncoderModes};
 
@@ -8,19 +9,21 @@ use super::encoder::{LZMAEncoder, LZMAEncoderModes};
 ///
 /// # Examples
 /// ```
-/// use std::io::Write;
-/// use lzma_rust::{LZMA2Options, LZMAWriter};
+/// use std::io::{Read, Write};
+/// use lzma_rust::{CountingWriter, LZMA2Options, LZMAReader, LZMAWriter};
 /// let s = b"Hello, world!";
-/// let mut out = crate::Vec::new();
-/// let mut options = LZMA2Options::with_preset(6);
-/// options.dict_size = LZMA2Options::DICT_SIZE_DEFAULT;
-
-/// let mut w = LZMAWriter::new_no_header(CountingWriter::new(&mut out), &options, false).unwrap();
-/// w.write_all(&s).unwrap();
+/// let mut out = Vec::new();
+/// let options = LZMA2Options::with_preset(6);
+/// let mut w = LZMAWriter::new_use_header(CountingWriter::new(&mut out), &options, None).unwrap();
+/// w.write_all(s).unwrap();
 /// w.write(&[]).unwrap();
+/// drop(w);
 ///
+/// let mut r = LZMAReader::new_mem_limit(&out[..], u64::MAX, None).unwrap();
+/// let mut decompressed = Vec::new();
+/// r.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], s);
 /// ```
-///
 pub struct LZMAWriter<W: Write> {
     pub rc: RangeEncoder<CountingWriter<W>>,
     lzma: LZMAEncoder,
@@ -40,6 +43,16 @@ impl<W: Write> LZMAWriter<W> {
         use_end_marker: bool,
         expected_uncompressed_size: Option<u64>,
     ) -> crate::io::write_result!(CountingWriter<W>, LZMAWriter<W>) {
+        // Not `?`, the error type differs with no_std.
+        #[allow(clippy::question_mark)]
+        if let Err(e) = options.validate_lzma() {
+            return Err(write_error_kind!(CountingWriter<W>, e));
+        }
+        // With a known input size, a larger dictionary would only waste memory.
+        let dict_size = match expected_uncompressed_size {
+            Some(size) => options.dict_size_for_input(size),
+            None => options.dict_size,
+        };
         let (mut lzma, mode) = LZMAEncoder::new(
             options.mode,
             options.lc,
@@ -47,7 +60,7 @@ impl<W: Write> LZMAWriter<W> {
             options.pb,
             options.mf,
             options.depth_limit,
-            options.dict_size,
+            dict_size,
             options.nice_len as usize,
         );
         if let Some(preset_dict) = &options.preset_dict {
@@ -57,21 +70,25 @@ impl<W: Write> LZMAWriter<W> {
                     "Header is not supported with preset dict"
                 );
             }
-            lzma.lz.set_preset_dict(options.dict_size, preset_dict);
+            lzma.lz.set_preset_dict(dict_size, preset_dict);
         }
 
         let props = options.get_props();
         if use_header {
-            out.write_all(&[props])?;
-            let mut dict_size = options.dict_size;
-            for _i in 0..4 {
-                out.write_all(&[(dict_size & 0xFF) as u8])?;
-                dict_size >>= 8;
-            }
-            let expected_compressed_size = expected_uncompressed_size.unwrap_or(u64::MAX);
-            for i in 0..8 {
-                out.write_all(&[((expected_compressed_size >> (i * 8)) & 0xFF) as u8])?;
+            if dict_size > u32::MAX as u64 {
+                return error!(
+                    write_error_kind!(CountingWriter<W>, ErrorKind::InvalidInput),
+                    "Dictionary size does not fit into the .lzma header"
+                );
             }
+            let header = LZMAHeader {
+                lc: options.lc,
+                lp: options.lp,
+                pb: options.pb,
+                dict_size: dict_size as u32,
+                uncompressed_size: expected_uncompressed_size,
+            };
+            header.write_to(&mut out)?;
         }
 
         let rc = RangeEncoder::new(out);
@@ -87,6 +104,8 @@ impl<W: Write> LZMAWriter<W> {
         })
     }
 
+    /// Writes a .lzma header. If `input_size` is known, it is stored in the header and the
+    /// dictionary is shrunk to it, see [LZMA2Options::shrink_dict_size].
     #[inline]
     pub fn new_use_header(
         out: CountingWriter<W>,
@@ -0,0 +1,84 @@
+use crate::io::{error, ErrorKind, Result};
+
+use super::encoder::LZMAEncoder;
+use super::range_enc::RangeEncoder;
+use super::LZMA2Options;
+
+/// Room for the symbol that exceeds the output size, which is encoded before
+/// the size can be checked.
+const SYMBOL_SIZE_MAX: usize = 64;
+/// Size of an LZMA stream without any symbols.
+const EMPTY_SIZE: usize = 5;
+
+/// Compresses as much of `input` as fits into `output` as MicroLZMA, the format used by
+/// EROFS: an LZMA stream without an end marker, whose first byte (always 0x00) is replaced
+/// by the bitwise negation of the props byte. The dictionary size and both sizes are not
+/// stored and have to be known by the decoder, see [MicroLZMAReader](crate::MicroLZMAReader).
+///
+/// Returns the number of input bytes consumed and the number of bytes written to `output`,
+/// which may be a few bytes less than its length even if not all of the input fits.
+///
+/// Like liblzma, the input is compressed in a single pass: the symbol that doesn't fit is
+/// only known after encoding it, so it's taken back and the stream ends before it.
+///
+/// # Examples
+/// ```
+/// use std::io::Read;
+/// use lzma_rust::{microlzma_compress, LZMA2Options, MicroLZMAReader};
+/// let input: Vec<u8> = (0..100000u32).flat_map(|i| (i % 1000).to_le_bytes()).collect();
+/// let options = LZMA2Options::with_preset(6);
+/// let mut output = [0u8; 64];
+/// let (consumed, written) = microlzma_compress(&input, &mut output, &options).unwrap();
+/// assert!(consumed < input.len());
+/// assert!(written <= output.len());
+///
+/// let mut reader =
+///     MicroLZMAReader::new(&output[..], written as u64, consumed as u64, options.dict_size).unwrap();
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], &input[..consumed]);
+/// ```
+pub fn microlzma_compress(
+    input: &[u8],
+    output: &mut [u8],
+    options: &LZMA2Options,
+) -> Result<(usize, usize)> {
+    if output.len() < EMPTY_SIZE {
+        return error!(
+            ErrorKind::InvalidInput,
+            "MicroLZMA output must be at least 5 bytes"
+        );
+    }
+    if options.preset_dict.is_some() {
+        return error!(
+            ErrorKind::Unsupported,
+            "Preset dictionary is not supported by MicroLZMA"
+        );
+    }
+    let (mut lzma, mut mode) = LZMAEncoder::new(
+        options.mode,
+        options.lc,
+        options.lp,
+        options.pb,
+        options.mf,
+        options.depth_limit,
+        options.dict_size,
+        options.nice_len as usize,
+    );
+    let mut rc = RangeEncoder::new_buffer(output.len() + SYMBOL_SIZE_MAX);
+    let mut off = 0;
+    loop {
+        off += lzma.lz.fill_window(&input[off..]);
+        if off == input.len() {
+            lzma.lz.set_finishing();
+        }
+        let limited = lzma.encode_for_microlzma(&mut rc, &mut mode, output.len() as u64)?;
+        if limited || off == input.len() {
+            break;
+        }
+    }
+    let written = rc.finish_buffer()?.unwrap_or(0);
+    rc.write_to(&mut &mut output[..])?;
+    output[0] = !options.get_props();
+    Ok((lzma.data.uncompressed_size as usize, written))
+}
@@ -1,3 +1,5 @@
+mod bcj_writer;
+mod delta_writer;
 mod encoder;
 mod encoder_fast;
 mod encoder_normal;
@@ -5,8 +7,34 @@ mod lzma2_writer;
 mod lzma_writer;
 mod range_enc;
 use super::*;
+pub use bcj_writer::*;
+pub use delta_writer::*;
 pub use counting::*;
 pub use encoder::EncodeMode;
 pub use lzma2_writer::*;
 mod counting;
 pub use lzma_writer::*;
+#[cfg(not(feature = "no_std"))]
+mod lzip_writer;
+#[cfg(not(feature = "no_std"))]
+mod lzma2_mt_writer;
+#[cfg(not(feature = "no_std"))]
+mod microlzma;
+#[cfg(not(feature = "no_std"))]
+mod sevenz_writer;
+#[cfg(not(feature = "no_std"))]
+mod xz_writer;
+#[cfg(not(feature = "no_std"))]
+mod zip_lzma_writer;
+#[cfg(not(feature = "no_std"))]
+pub use lzip_writer::*;
+#[cfg(not(feature = "no_std"))]
+pub use lzma2_mt_writer::*;
+#[cfg(not(feature = "no_std"))]
+pub use microlzma::*;
+#[cfg(not(feature = "no_std"))]
+pub use sevenz_writer::*;
+#[cfg(not(feature = "no_std"))]
+pub use xz_writer::*;
+#[cfg(not(feature = "no_std"))]
+pub use zip_lzma_writer::*;
@@ -232,6 +232,36 @@ impl RangeEncoder<RangeEncoderBuffer> {
         let w = &self.inner;
         w.pos as u64 + self.cache_size + 5 - 1
     }
+
+    /// Saves the state before encoding a symbol, see [restore](Self::restore).
+    pub fn save(&self) -> RangeEncoderState {
+        RangeEncoderState {
+            low: self.low,
+            range: self.range,
+            cache_size: self.cache_size,
+            cache: self.cache,
+            pos: self.inner.pos,
+        }
+    }
+
+    /// Takes back the symbols encoded since `state` was saved. The buffer is only appended
+    /// to, so the bytes before the saved position are still valid.
+    pub fn restore(&mut self, state: RangeEncoderState) {
+        self.low = state.low;
+        self.range = state.range;
+        self.cache_size = state.cache_size;
+        self.cache = state.cache;
+        self.inner.pos = state.pos;
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RangeEncoderState {
+    low: u64,
+    range: u64,
+    cache_size: u64,
+    cache: u8,
+    pos: usize,
 }
 
 pub struct RangeEncoderBuffer {
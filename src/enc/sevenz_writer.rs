@@ -0,0 +1,429 @@
+use crate::check::{Check, Crc32};
+use crate::io::{error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use crate::sevenz::{
+    method_id, property_id as id, write_bits, write_number, SevenZEntry, SignatureHeader,
+    SIGNATURE_HEADER_SIZE,
+};
+
+use super::{CountingWriter, LZMA2Options, LZMA2Writer};
+
+/// Options for [SevenZWriter].
+#[derive(Debug, Clone)]
+pub struct SevenZOptions {
+    pub lzma2: LZMA2Options,
+    /// Compress all files as a single stream (one folder), which usually compresses better.
+    /// Otherwise every file is compressed separately.
+    pub solid: bool,
+}
+
+impl Default for SevenZOptions {
+    fn default() -> Self {
+        Self::with_preset(6)
+    }
+}
+
+impl SevenZOptions {
+    /// preset: [0..9]
+    pub fn with_preset(preset: u64) -> Self {
+        Self {
+            lzma2: LZMA2Options::with_preset(preset),
+            solid: true,
+        }
+    }
+}
+
+/// Sizes and CRCs of the files written to a folder.
+#[derive(Debug, Default)]
+struct FolderInfo {
+    unpack_size: u64,
+    sizes: crate::Vec<u64>,
+    crcs: crate::Vec<u32>,
+}
+
+/// Writes a 7z archive.
+///
+/// File data is compressed with [LZMA2Writer], either into one solid folder or into one folder
+/// per file, see [SevenZOptions::solid]. Entries without data, like empty files and
+/// directories, are only stored in the header. The header is written LZMA2 compressed by
+/// [finish](Self::finish), which then seeks back to fill in the signature header.
+///
+/// # Examples
+/// ```
+/// use std::io::{Cursor, Read};
+/// use lzma_rust::{SevenZEntry, SevenZOptions, SevenZReader, SevenZWriter};
+/// let mut writer = SevenZWriter::new(Cursor::new(Vec::new()), &SevenZOptions::default()).unwrap();
+/// let entry = SevenZEntry {
+///     name: "hello.txt".to_string(),
+///     ..Default::default()
+/// };
+/// writer.push_entry(entry, Some(&b"Hello, world!"[..])).unwrap();
+/// let dir = SevenZEntry {
+///     name: "dir".to_string(),
+///     is_directory: true,
+///     ..Default::default()
+/// };
+/// writer.push_entry(dir, None::<&[u8]>).unwrap();
+/// writer.finish().unwrap();
+///
+/// let mut reader = SevenZReader::new(writer.into_inner()).unwrap();
+/// assert_eq!(reader.entries().len(), 2);
+/// reader
+///     .for_each_entry(|entry, data| {
+///         let mut content = Vec::new();
+///         data.read_to_end(&mut content)?;
+///         if entry.name == "hello.txt" {
+///             assert_eq!(&content[..], b"Hello, world!");
+///         }
+///         Ok(true)
+///     })
+///     .unwrap();
+/// ```
+pub struct SevenZWriter<W: Write + Seek> {
+    inner: Option<W>,
+    folder: Option<LZMA2Writer<W>>,
+    options: SevenZOptions,
+    /// Position of the signature header in `inner`.
+    start: u64,
+    pack_sizes: crate::Vec<u64>,
+    folders: crate::Vec<FolderInfo>,
+    entries: crate::Vec<SevenZEntry>,
+    finished: bool,
+    /// Set when writing the data of an entry failed, which may leave part of it in a
+    /// folder without a matching entry.
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<W: Write + Seek> SevenZWriter<W> {
+    /// Creates a new 7z archive writer at the current position of `inner`, leaving
+    /// room for the signature header.
+    pub fn new(mut inner: W, options: &SevenZOptions) -> Result<Self> {
+        if options.lzma2.preset_dict.is_some() {
+            return error!(
+                ErrorKind::Unsupported,
+                "Preset dictionary is not supported by the 7z format"
+            );
+        }
+        options.lzma2.validate()?;
+        let start = inner.stream_position()?;
+        inner.write_all(&[0u8; SIGNATURE_HEADER_SIZE])?;
+        Ok(Self {
+            inner: Some(inner),
+            folder: None,
+            options: options.clone(),
+            start,
+            pack_sizes: crate::Vec::new(),
+            folders: crate::Vec::new(),
+            entries: crate::Vec::new(),
+            finished: false,
+            error: None,
+        })
+    }
+
+    /// Returns the underlying writer. Call [finish](Self::finish) first to complete the archive.
+    pub fn into_inner(self) -> W {
+        match self.folder {
+            Some(folder) => folder.inner.inner,
+            None => self.inner.unwrap(),
+        }
+    }
+
+    /// Adds an entry with the data read from `data` until its end.
+    ///
+    /// The name, [is_directory](SevenZEntry::is_directory), [mtime](SevenZEntry::mtime)
+    /// and [attributes](SevenZEntry::attributes) of `entry` are stored, the other fields
+    /// are set from the data. A file with no or empty data is stored as an empty file.
+    ///
+    /// If reading or compressing the data fails, the archive can't be completed: this and
+    /// every later call, including [finish](Self::finish), return the error.
+    pub fn push_entry<R: Read>(&mut self, mut entry: SevenZEntry, data: Option<R>) -> Result<()> {
+        if let Some(e) = &self.error {
+            return error!(e.0, e.1.clone());
+        }
+        if self.finished {
+            return error!(ErrorKind::Other, "7z archive finished");
+        }
+        entry.has_stream = false;
+        entry.is_anti_item = false;
+        entry.size = 0;
+        entry.crc = None;
+        if let Some(mut data) = data {
+            if entry.is_directory {
+                return error!(ErrorKind::InvalidInput, "7z directories cannot have data");
+            }
+            if let Err(e) = self.write_data(&mut data, &mut entry) {
+                self.error = Some((e.kind(), e.to_string()));
+                return Err(e);
+            }
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Compresses `data` into the current folder and sets the size and CRC32 of `entry`.
+    fn write_data<R: Read>(&mut self, data: &mut R, entry: &mut SevenZEntry) -> Result<()> {
+        let mut buf = vec![0u8; 64 << 10];
+        let mut crc32 = Crc32::new();
+        loop {
+            let size = match data.read(&mut buf) {
+                Ok(0) => break,
+                Ok(size) => size,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if self.folder.is_none() {
+                self.start_folder()?;
+            }
+            self.folder.as_mut().unwrap().write_all(&buf[..size])?;
+            crc32.update(&buf[..size]);
+            entry.size += size as u64;
+        }
+        if entry.size > 0 {
+            entry.has_stream = true;
+            entry.crc = Some(crc32.value());
+            let folder = self.folders.last_mut().unwrap();
+            folder.unpack_size += entry.size;
+            folder.sizes.push(entry.size);
+            folder.crcs.push(crc32.value());
+            if !self.options.solid {
+                self.end_folder()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn start_folder(&mut self) -> Result<()> {
+        let inner = CountingWriter::new(self.inner.take().unwrap());
+        self.folder = Some(LZMA2Writer::new(inner, &self.options.lzma2)?);
+        self.folders.push(FolderInfo::default());
+        Ok(())
+    }
+
+    fn end_folder(&mut self) -> Result<()> {
+        if let Some(mut folder) = self.folder.take() {
+            folder.finish()?;
+            self.pack_sizes.push(folder.inner.written_bytes() as u64);
+            self.inner = Some(folder.inner.inner);
+        }
+        Ok(())
+    }
+
+    /// Finishes the last folder and writes the header and the signature header.
+    pub fn finish(&mut self) -> Result<()> {
+        if let Some(e) = &self.error {
+            return error!(e.0, e.1.clone());
+        }
+        if self.finished {
+            return Ok(());
+        }
+        self.end_folder()?;
+        let mut inner = self.inner.take().unwrap();
+        let mut signature = SignatureHeader {
+            next_header_offset: 0,
+            next_header_size: 0,
+            next_header_crc: 0,
+        };
+        if !self.entries.is_empty() {
+            let header = self.encode_header();
+            let pack_pos: u64 = self.pack_sizes.iter().sum();
+            let (w, pack_size) = compress_header(inner, &header, &self.options.lzma2)?;
+            inner = w;
+            let encoded = encode_header_info(&header, pack_pos, pack_size, &self.options.lzma2);
+            inner.write_all(&encoded)?;
+            signature = SignatureHeader {
+                next_header_offset: pack_pos + pack_size,
+                next_header_size: encoded.len() as u64,
+                next_header_crc: Crc32::checksum(&encoded),
+            };
+        }
+        let end = inner.stream_position()?;
+        inner.seek(SeekFrom::Start(self.start))?;
+        inner.write_all(&signature.encode())?;
+        inner.seek(SeekFrom::Start(end))?;
+        self.inner = Some(inner);
+        self.finished = true;
+        Ok(())
+    }
+
+    fn encode_header(&self) -> crate::Vec<u8> {
+        let mut buf = vec![id::HEADER];
+        if !self.folders.is_empty() {
+            buf.push(id::MAIN_STREAMS_INFO);
+            write_pack_info(&mut buf, 0, &self.pack_sizes);
+            let unpack_sizes: crate::Vec<u64> =
+                self.folders.iter().map(|f| f.unpack_size).collect();
+            write_unpack_info(&mut buf, &self.options.lzma2, &unpack_sizes, &[]);
+
+            buf.push(id::SUBSTREAMS_INFO);
+            if self.folders.iter().any(|f| f.sizes.len() != 1) {
+                buf.push(id::NUM_UNPACK_STREAM);
+                for folder in self.folders.iter() {
+                    write_number(&mut buf, folder.sizes.len() as u64);
+                }
+            }
+            if self.folders.iter().any(|f| f.sizes.len() > 1) {
+                buf.push(id::SIZE);
+                for folder in self.folders.iter() {
+                    for &size in &folder.sizes[..folder.sizes.len() - 1] {
+                        write_number(&mut buf, size);
+                    }
+                }
+            }
+            buf.push(id::CRC);
+            buf.push(1);
+            for &crc in self.folders.iter().flat_map(|f| f.crcs.iter()) {
+                buf.extend_from_slice(&crc.to_le_bytes());
+            }
+            buf.push(id::END);
+            buf.push(id::END);
+        }
+
+        buf.push(id::FILES_INFO);
+        write_number(&mut buf, self.entries.len() as u64);
+        let empty_streams: crate::Vec<bool> = self.entries.iter().map(|e| !e.has_stream).collect();
+        if empty_streams.contains(&true) {
+            let mut bits = crate::Vec::new();
+            write_bits(&mut bits, &empty_streams);
+            write_property(&mut buf, id::EMPTY_STREAM, &bits);
+            let empty_files: crate::Vec<bool> = self
+                .entries
+                .iter()
+                .filter(|e| !e.has_stream)
+                .map(|e| !e.is_directory)
+                .collect();
+            if empty_files.contains(&true) {
+                let mut bits = crate::Vec::new();
+                write_bits(&mut bits, &empty_files);
+                write_property(&mut buf, id::EMPTY_FILE, &bits);
+            }
+        }
+
+        let mut names = vec![0];
+        for entry in self.entries.iter() {
+            for c in entry.name.encode_utf16().chain(Some(0)) {
+                names.extend_from_slice(&c.to_le_bytes());
+            }
+        }
+        write_property(&mut buf, id::NAME, &names);
+
+        let mtimes: crate::Vec<_> = self.entries.iter().map(|e| e.mtime).collect();
+        if let Some(data) = encode_optional(&mtimes, |t| t.to_le_bytes().to_vec()) {
+            write_property(&mut buf, id::MTIME, &data);
+        }
+        let attributes: crate::Vec<_> = self.entries.iter().map(|e| e.attributes).collect();
+        if let Some(data) = encode_optional(&attributes, |a| a.to_le_bytes().to_vec()) {
+            write_property(&mut buf, id::WIN_ATTRIBUTES, &data);
+        }
+        buf.push(id::END);
+        buf.push(id::END);
+        buf
+    }
+}
+
+fn write_property(buf: &mut crate::Vec<u8>, property: u8, data: &[u8]) {
+    buf.push(property);
+    write_number(buf, data.len() as u64);
+    buf.extend_from_slice(data);
+}
+
+fn write_pack_info(buf: &mut crate::Vec<u8>, pack_pos: u64, pack_sizes: &[u64]) {
+    buf.push(id::PACK_INFO);
+    write_number(buf, pack_pos);
+    write_number(buf, pack_sizes.len() as u64);
+    buf.push(id::SIZE);
+    for &size in pack_sizes {
+        write_number(buf, size);
+    }
+    buf.push(id::END);
+}
+
+/// Writes folders that each consist of a single LZMA2 coder, with the CRCs of their
+/// data if `crcs` isn't empty.
+fn write_unpack_info(
+    buf: &mut crate::Vec<u8>,
+    options: &LZMA2Options,
+    unpack_sizes: &[u64],
+    crcs: &[u32],
+) {
+    buf.push(id::UNPACK_INFO);
+    buf.push(id::FOLDER);
+    write_number(buf, unpack_sizes.len() as u64);
+    buf.push(0);
+    for _ in unpack_sizes {
+        buf.push(1);
+        buf.push(0x20 | method_id::LZMA2.len() as u8);
+        buf.extend_from_slice(method_id::LZMA2);
+        buf.push(1);
+        buf.push(options.dict_size_prop());
+    }
+    buf.push(id::CODERS_UNPACK_SIZE);
+    for &size in unpack_sizes {
+        write_number(buf, size);
+    }
+    if !crcs.is_empty() {
+        buf.push(id::CRC);
+        buf.push(1);
+        for &crc in crcs {
+            buf.extend_from_slice(&crc.to_le_bytes());
+        }
+    }
+    buf.push(id::END);
+}
+
+/// Encodes a property with optional values as the "all defined" bit field,
+/// the external flag and the defined values. Returns `None` if no value is defined.
+fn encode_optional<T: Copy>(
+    values: &[Option<T>],
+    encode: impl Fn(T) -> crate::Vec<u8>,
+) -> Option<crate::Vec<u8>> {
+    if values.iter().all(Option::is_none) {
+        return None;
+    }
+    let mut buf = crate::Vec::new();
+    if values.iter().all(Option::is_some) {
+        buf.push(1);
+    } else {
+        buf.push(0);
+        let defined: crate::Vec<bool> = values.iter().map(Option::is_some).collect();
+        write_bits(&mut buf, &defined);
+    }
+    buf.push(0);
+    for value in values.iter().flatten() {
+        buf.extend_from_slice(&encode(*value));
+    }
+    Some(buf)
+}
+
+/// Options for compressing the header, with a dictionary no larger than needed.
+fn header_options(header: &[u8], options: &LZMA2Options) -> LZMA2Options {
+    let mut options = options.clone();
+    options.dict_size = options.dict_size.min((header.len() as u64).max(4096));
+    options
+}
+
+fn compress_header<W: Write>(inner: W, header: &[u8], options: &LZMA2Options) -> Result<(W, u64)> {
+    let options = header_options(header, options);
+    let mut lzma2 = LZMA2Writer::new(CountingWriter::new(inner), &options)?;
+    lzma2.write_all(header)?;
+    lzma2.finish()?;
+    let pack_size = lzma2.inner.written_bytes() as u64;
+    Ok((lzma2.inner.inner, pack_size))
+}
+
+/// Encodes the streams info describing the compressed header.
+fn encode_header_info(
+    header: &[u8],
+    pack_pos: u64,
+    pack_size: u64,
+    options: &LZMA2Options,
+) -> crate::Vec<u8> {
+    let mut buf = vec![id::ENCODED_HEADER];
+    write_pack_info(&mut buf, pack_pos, &[pack_size]);
+    write_unpack_info(
+        &mut buf,
+        &header_options(header, options),
+        &[header.len() as u64],
+        &[Crc32::checksum(header)],
+    );
+    buf.push(id::END);
+    buf
+}
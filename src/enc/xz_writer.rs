@@ -0,0 +1,217 @@
+use crate::io::{error, ErrorKind, Result, Write};
+use crate::xz::{
+    block_padding_size, encode_stream_header, BlockCheck, BlockHeader, CheckType, FilterFlags,
+    StreamFlags, StreamFooter, XZIndex, FILTER_ID_LZMA2,
+};
+
+use super::{CountingWriter, LZMA2Options, LZMA2Writer};
+
+/// Options for [XZWriter].
+#[derive(Debug, Clone)]
+pub struct XZOptions {
+    pub lzma2: LZMA2Options,
+    pub check: CheckType,
+    /// Maximum uncompressed size of a block. `None` puts all data into a single block.
+    pub block_size: Option<u64>,
+}
+
+impl Default for XZOptions {
+    fn default() -> Self {
+        Self::with_preset(6)
+    }
+}
+
+impl XZOptions {
+    /// preset: [0..9]
+    pub fn with_preset(preset: u64) -> Self {
+        Self {
+            lzma2: LZMA2Options::with_preset(preset),
+            check: CheckType::CRC32,
+            block_size: None,
+        }
+    }
+}
+
+/// Compresses into the .xz file format.
+///
+/// The data is split into blocks of [XZOptions::block_size], each compressed with [LZMA2Writer].
+/// The index and stream footer are written by [finish](XZWriter::finish) or
+/// by writing an empty buffer.
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::{XZOptions, XZReader, XZWriter};
+/// let mut writer = XZWriter::new(Vec::new(), &XZOptions::with_preset(6)).unwrap();
+/// writer.write_all(b"Hello, world!").unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.into_inner();
+///
+/// let mut reader = XZReader::new(&compressed[..]).unwrap();
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], b"Hello, world!");
+/// ```
+pub struct XZWriter<W: Write> {
+    inner: Option<W>,
+    block: Option<BlockWriter<W>>,
+    options: XZOptions,
+    stream_flags: StreamFlags,
+    index: XZIndex,
+    finished: bool,
+}
+
+impl<W: Write> XZWriter<W> {
+    /// Creates a new .xz compressor and writes the stream header.
+    pub fn new(mut inner: W, options: &XZOptions) -> Result<Self> {
+        if options.lzma2.preset_dict.is_some() {
+            return error!(
+                ErrorKind::Unsupported,
+                "Preset dictionary is not supported by the XZ format"
+            );
+        }
+        options.lzma2.validate()?;
+        if options.block_size == Some(0) {
+            return error!(ErrorKind::InvalidInput, "XZ block size must not be 0");
+        }
+        let stream_flags = StreamFlags {
+            check: options.check,
+        };
+        inner.write_all(&encode_stream_header(&stream_flags))?;
+        Ok(Self {
+            inner: Some(inner),
+            block: None,
+            options: options.clone(),
+            stream_flags,
+            index: XZIndex::new(),
+            finished: false,
+        })
+    }
+
+    /// Returns the underlying writer. Call [finish](Self::finish) first to complete the stream.
+    pub fn into_inner(self) -> W {
+        match self.block {
+            Some(block) => block.lzma2.inner.inner,
+            None => self.inner.unwrap(),
+        }
+    }
+
+    fn end_block(&mut self) -> Result<()> {
+        if let Some(block) = self.block.take() {
+            let (inner, unpadded_size, uncompressed_size) = block.finish()?;
+            self.inner = Some(inner);
+            self.index.add(unpadded_size, uncompressed_size)?;
+        }
+        Ok(())
+    }
+
+    /// Finishes the current block and writes the index and stream footer.
+    pub fn finish(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.end_block()?;
+        let inner = self.inner.as_mut().unwrap();
+        self.index.write_to(inner)?;
+        let footer = StreamFooter {
+            flags: self.stream_flags,
+            backward_size: self.index.index_size(),
+        };
+        inner.write_all(&footer.encode())?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for XZWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        if buf.is_empty() && !self.finished {
+            self.finish()?;
+            return Ok(0);
+        }
+        if self.finished {
+            return error!(ErrorKind::Other, "XZ finished");
+        }
+        let mut off = 0;
+        while off < buf.len() {
+            if self.block.is_none() {
+                let inner = self.inner.take().unwrap();
+                self.block = Some(BlockWriter::new(inner, &self.options)?);
+            }
+            let block = self.block.as_mut().unwrap();
+            let mut len = buf.len() - off;
+            if let Some(block_size) = self.options.block_size {
+                len = len.min((block_size - block.uncompressed_size) as usize);
+            }
+            block.write_all(&buf[off..off + len])?;
+            off += len;
+            if Some(block.uncompressed_size) == self.options.block_size {
+                self.end_block()?;
+            }
+        }
+        Ok(off)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self.block.as_mut() {
+            Some(block) => block.lzma2.flush(),
+            None => self.inner.as_mut().unwrap().flush(),
+        }
+    }
+}
+
+/// Compresses the data of a single block and keeps track of its sizes and check.
+struct BlockWriter<W: Write> {
+    lzma2: LZMA2Writer<W>,
+    header_size: usize,
+    check: BlockCheck,
+    uncompressed_size: u64,
+}
+
+impl<W: Write> BlockWriter<W> {
+    fn new(mut inner: W, options: &XZOptions) -> Result<Self> {
+        let header = BlockHeader {
+            header_size: 0,
+            compressed_size: None,
+            uncompressed_size: None,
+            filters: vec![FilterFlags {
+                id: FILTER_ID_LZMA2,
+                props: vec![options.lzma2.dict_size_prop()],
+            }],
+        }
+        .encode();
+        inner.write_all(&header)?;
+        Ok(Self {
+            lzma2: LZMA2Writer::new(CountingWriter::new(inner), &options.lzma2)?,
+            header_size: header.len(),
+            check: BlockCheck::new(options.check),
+            uncompressed_size: 0,
+        })
+    }
+
+    /// Writes the end of the LZMA2 data, the block padding and the check.
+    /// Returns the underlying writer with the unpadded and uncompressed sizes of the block.
+    fn finish(mut self) -> Result<(W, u64, u64)> {
+        self.lzma2.finish()?;
+        let compressed_size = self.lzma2.inner.written_bytes() as u64;
+        let mut inner = self.lzma2.inner.inner;
+        inner.write_all(&[0u8; 3][..block_padding_size(compressed_size)])?;
+        let check = self.check.encode();
+        inner.write_all(&check)?;
+        let unpadded_size = self.header_size as u64 + compressed_size + check.len() as u64;
+        Ok((inner, unpadded_size, self.uncompressed_size))
+    }
+}
+
+impl<W: Write> Write for BlockWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.lzma2.write_all(buf)?;
+        self.check.update(buf);
+        self.uncompressed_size += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.lzma2.flush()
+    }
+}
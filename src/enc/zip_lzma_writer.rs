@@ -0,0 +1,83 @@
+use crate::io::{error, ErrorKind, Result, Write};
+use crate::zip::encode_header;
+
+use super::{CountingWriter, LZMA2Options, LZMAWriter};
+
+/// Compresses the data of a ZIP entry with the LZMA compression method (14).
+///
+/// The header with the LZMA properties is written by [new](Self::new), the LZMA data is
+/// finished by [finish](Self::finish) or by writing an empty buffer. If the data is written
+/// with an end of stream marker, the ZIP crate has to set
+/// [FLAG_EOS_MARKER](crate::zip::FLAG_EOS_MARKER) for the entry.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use lzma_rust::{LZMA2Options, ZipLzmaWriter};
+/// let mut writer = ZipLzmaWriter::new(Vec::new(), &LZMA2Options::with_preset(6), false).unwrap();
+/// writer.write_all(b"Hello, world!").unwrap();
+/// writer.finish().unwrap();
+/// let compressed_size = writer.compressed_size();
+/// let compressed = writer.into_inner();
+/// assert_eq!(compressed.len() as u64, compressed_size);
+/// assert_eq!(&compressed[2..5], &[5, 0, 93]);
+/// ```
+pub struct ZipLzmaWriter<W: Write> {
+    lzma: LZMAWriter<W>,
+}
+
+impl<W: Write> ZipLzmaWriter<W> {
+    /// Creates a new compressor and writes the header.
+    /// - [eos_marker] - whether to terminate the data with an end of stream marker.
+    pub fn new(inner: W, options: &LZMA2Options, eos_marker: bool) -> Result<Self> {
+        if options.preset_dict.is_some() {
+            return error!(
+                ErrorKind::Unsupported,
+                "Preset dictionary is not supported by the ZIP LZMA method"
+            );
+        }
+        if options.dict_size > u32::MAX as u64 {
+            return error!(
+                ErrorKind::InvalidInput,
+                "Dictionary size does not fit into the ZIP LZMA header"
+            );
+        }
+        let mut inner = CountingWriter::new(inner);
+        inner.write_all(&encode_header(
+            options.get_props(),
+            options.dict_size as u32,
+        ))?;
+        Ok(Self {
+            lzma: LZMAWriter::new_no_header(inner, options, eos_marker)?,
+        })
+    }
+
+    /// Number of bytes written so far, including the header. After [finish](Self::finish)
+    /// this is the compressed size of the entry.
+    pub fn compressed_size(&self) -> u64 {
+        self.lzma.rc.inner.written_bytes() as u64
+    }
+
+    pub fn uncompressed_size(&self) -> u64 {
+        self.lzma.get_uncompressed_size()
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.lzma.finish()
+    }
+
+    /// Returns the underlying writer. Call [finish](Self::finish) first to complete the data.
+    pub fn into_inner(self) -> W {
+        self.lzma.rc.inner.inner
+    }
+}
+
+impl<W: Write> Write for ZipLzmaWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        self.lzma.write(buf)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.lzma.rc.inner.flush()
+    }
+}
@@ -0,0 +1,707 @@
+use super::FILTER_BUF_SIZE;
+use crate::io::Read;
+
+/// Filter ID of the x86 BCJ filter in xz block headers.
+pub const FILTER_ID_X86: u64 = 0x04;
+/// Filter ID of the PowerPC (big endian) BCJ filter in xz block headers.
+pub const FILTER_ID_POWERPC: u64 = 0x05;
+/// Filter ID of the IA-64 (Itanium) BCJ filter in xz block headers.
+pub const FILTER_ID_IA64: u64 = 0x06;
+/// Filter ID of the ARM (little endian) BCJ filter in xz block headers.
+pub const FILTER_ID_ARM: u64 = 0x07;
+/// Filter ID of the ARM-Thumb (little endian) BCJ filter in xz block headers.
+pub const FILTER_ID_ARMTHUMB: u64 = 0x08;
+/// Filter ID of the SPARC BCJ filter in xz block headers.
+pub const FILTER_ID_SPARC: u64 = 0x09;
+/// Filter ID of the ARM64 BCJ filter in xz block headers.
+pub const FILTER_ID_ARM64: u64 = 0x0A;
+/// Filter ID of the RISC-V BCJ filter in xz block headers.
+pub const FILTER_ID_RISCV: u64 = 0x0B;
+
+const MASK_TO_ALLOWED_STATUS: [bool; 8] = [true, true, true, false, true, false, false, false];
+const MASK_TO_BIT_NUMBER: [usize; 8] = [0, 1, 2, 2, 3, 3, 3, 3];
+
+const IA64_BRANCH_TABLE: [u32; 32] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 4, 6, 6, 0, 0, 7, 7, 4, 4, 0, 0, 4, 4, 0, 0,
+];
+
+/// Instruction set architectures supported by the BCJ filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BCJArch {
+    X86,
+    PowerPC,
+    IA64,
+    ARM,
+    ARMThumb,
+    SPARC,
+    ARM64,
+    RISCV,
+}
+
+impl BCJArch {
+    /// The ID of the filter for this architecture in xz block headers.
+    pub fn filter_id(self) -> u64 {
+        match self {
+            Self::X86 => FILTER_ID_X86,
+            Self::PowerPC => FILTER_ID_POWERPC,
+            Self::IA64 => FILTER_ID_IA64,
+            Self::ARM => FILTER_ID_ARM,
+            Self::ARMThumb => FILTER_ID_ARMTHUMB,
+            Self::SPARC => FILTER_ID_SPARC,
+            Self::ARM64 => FILTER_ID_ARM64,
+            Self::RISCV => FILTER_ID_RISCV,
+        }
+    }
+
+    pub fn from_filter_id(id: u64) -> Option<Self> {
+        match id {
+            FILTER_ID_X86 => Some(Self::X86),
+            FILTER_ID_POWERPC => Some(Self::PowerPC),
+            FILTER_ID_IA64 => Some(Self::IA64),
+            FILTER_ID_ARM => Some(Self::ARM),
+            FILTER_ID_ARMTHUMB => Some(Self::ARMThumb),
+            FILTER_ID_SPARC => Some(Self::SPARC),
+            FILTER_ID_ARM64 => Some(Self::ARM64),
+            FILTER_ID_RISCV => Some(Self::RISCV),
+            _ => None,
+        }
+    }
+}
+
+/// Options of a BCJ filter, as stored in xz filter flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BCJOptions {
+    pub arch: BCJArch,
+    /// Start offset of the converted addresses, usually 0.
+    pub start_pos: u32,
+}
+
+impl BCJOptions {
+    pub fn new(arch: BCJArch) -> Self {
+        Self { arch, start_pos: 0 }
+    }
+}
+
+/// Branch/Call/Jump converter, which turns the relative target addresses of branch
+/// instructions in executable code into absolute ones when encoding, and back when decoding.
+/// Calls to the same function then look alike and compress better.
+///
+/// The converter works in place and keeps its state between calls, so data can be converted
+/// in pieces. An instruction that crosses the end of a piece is left to the next call.
+pub struct BCJFilter {
+    is_encoder: bool,
+    pos: u32,
+    prev_mask: u32,
+    filter: fn(&mut BCJFilter, &mut [u8]) -> usize,
+}
+
+impl BCJFilter {
+    fn new(is_encoder: bool, pos: u32, filter: fn(&mut BCJFilter, &mut [u8]) -> usize) -> Self {
+        Self {
+            is_encoder,
+            pos,
+            prev_mask: 0,
+            filter,
+        }
+    }
+
+    /// Creates an x86 (32-bit and 64-bit) converter for the data that starts at `start_pos`.
+    pub fn new_x86(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos.wrapping_add(5), Self::x86_code)
+    }
+
+    /// Creates a converter for 32-bit ARM code.
+    pub fn new_arm(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos.wrapping_add(8), Self::arm_code)
+    }
+
+    /// Creates a converter for ARM-Thumb code.
+    pub fn new_arm_thumb(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos.wrapping_add(4), Self::arm_thumb_code)
+    }
+
+    /// Creates a converter for ARM64 (AArch64) code.
+    pub fn new_arm64(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos, Self::arm64_code)
+    }
+
+    /// Creates a converter for big endian PowerPC code.
+    pub fn new_ppc(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos, Self::ppc_code)
+    }
+
+    /// Creates a converter for SPARC code.
+    pub fn new_sparc(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos, Self::sparc_code)
+    }
+
+    /// Creates a converter for IA-64 (Itanium) code.
+    pub fn new_ia64(is_encoder: bool, start_pos: u32) -> Self {
+        Self::new(is_encoder, start_pos, Self::ia64_code)
+    }
+
+    /// Creates a converter for RISC-V code.
+    pub fn new_riscv(is_encoder: bool, start_pos: u32) -> Self {
+        if is_encoder {
+            Self::new(is_encoder, start_pos, Self::riscv_encode)
+        } else {
+            Self::new(is_encoder, start_pos, Self::riscv_decode)
+        }
+    }
+
+    pub fn from_options(options: &BCJOptions, is_encoder: bool) -> Self {
+        let start_pos = options.start_pos;
+        match options.arch {
+            BCJArch::X86 => Self::new_x86(is_encoder, start_pos),
+            BCJArch::PowerPC => Self::new_ppc(is_encoder, start_pos),
+            BCJArch::IA64 => Self::new_ia64(is_encoder, start_pos),
+            BCJArch::ARM => Self::new_arm(is_encoder, start_pos),
+            BCJArch::ARMThumb => Self::new_arm_thumb(is_encoder, start_pos),
+            BCJArch::SPARC => Self::new_sparc(is_encoder, start_pos),
+            BCJArch::ARM64 => Self::new_arm64(is_encoder, start_pos),
+            BCJArch::RISCV => Self::new_riscv(is_encoder, start_pos),
+        }
+    }
+
+    /// Converts the instructions in `buf` and returns the number of bytes that have been
+    /// processed. The remaining bytes may be the start of an instruction and have to be
+    /// passed again, followed by more data, in the next call.
+    pub fn code(&mut self, buf: &mut [u8]) -> usize {
+        (self.filter)(self, buf)
+    }
+
+    fn x86_code(&mut self, buf: &mut [u8]) -> usize {
+        #[inline]
+        fn test_86_ms_byte(b: u8) -> bool {
+            b == 0x00 || b == 0xFF
+        }
+
+        let mut prev_pos = -1isize;
+        let mut i = 0;
+        while i + 5 <= buf.len() {
+            if buf[i] & 0xFE != 0xE8 {
+                i += 1;
+                continue;
+            }
+
+            let distance = i as isize - prev_pos;
+            if distance & !3 != 0 {
+                self.prev_mask = 0;
+            } else {
+                self.prev_mask = (self.prev_mask << (distance - 1)) & 7;
+                if self.prev_mask != 0 {
+                    let mask = self.prev_mask as usize;
+                    if !MASK_TO_ALLOWED_STATUS[mask]
+                        || test_86_ms_byte(buf[i + 4 - MASK_TO_BIT_NUMBER[mask]])
+                    {
+                        prev_pos = i as isize;
+                        self.prev_mask = (self.prev_mask << 1) | 1;
+                        i += 1;
+                        continue;
+                    }
+                }
+            }
+
+            prev_pos = i as isize;
+
+            if test_86_ms_byte(buf[i + 4]) {
+                let mut src = u32::from_le_bytes([buf[i + 1], buf[i + 2], buf[i + 3], buf[i + 4]]);
+                let pos = self.pos.wrapping_add(i as u32);
+                let mut dest;
+                loop {
+                    dest = self.convert(src, pos);
+                    if self.prev_mask == 0 {
+                        break;
+                    }
+                    let index = MASK_TO_BIT_NUMBER[self.prev_mask as usize] * 8;
+                    if !test_86_ms_byte((dest >> (24 - index)) as u8) {
+                        break;
+                    }
+                    src = dest ^ ((1 << (32 - index)) - 1);
+                }
+
+                buf[i + 1] = dest as u8;
+                buf[i + 2] = (dest >> 8) as u8;
+                buf[i + 3] = (dest >> 16) as u8;
+                buf[i + 4] = !(((dest >> 24) & 1).wrapping_sub(1)) as u8;
+                i += 5;
+            } else {
+                self.prev_mask = (self.prev_mask << 1) | 1;
+                i += 1;
+            }
+        }
+
+        let distance = i as isize - prev_pos;
+        self.prev_mask = if distance & !3 != 0 {
+            0
+        } else {
+            (self.prev_mask << (distance - 1)) & 7
+        };
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    /// Adds the position to `src` when encoding and subtracts it when decoding.
+    #[inline]
+    fn convert(&self, src: u32, pos: u32) -> u32 {
+        if self.is_encoder {
+            src.wrapping_add(pos)
+        } else {
+            src.wrapping_sub(pos)
+        }
+    }
+
+    fn arm_code(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i + 4 <= buf.len() {
+            if buf[i + 3] == 0xEB {
+                let src = u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], 0]) << 2;
+                let dest = self.convert(src, self.pos.wrapping_add(i as u32)) >> 2;
+                buf[i + 2] = (dest >> 16) as u8;
+                buf[i + 1] = (dest >> 8) as u8;
+                buf[i] = dest as u8;
+            }
+            i += 4;
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn arm_thumb_code(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i + 4 <= buf.len() {
+            if buf[i + 1] & 0xF8 == 0xF0 && buf[i + 3] & 0xF8 == 0xF8 {
+                let src = ((buf[i + 1] as u32 & 0x07) << 19)
+                    | ((buf[i] as u32) << 11)
+                    | ((buf[i + 3] as u32 & 0x07) << 8)
+                    | buf[i + 2] as u32;
+                let dest = self.convert(src << 1, self.pos.wrapping_add(i as u32)) >> 1;
+                buf[i + 1] = 0xF0 | ((dest >> 19) & 0x07) as u8;
+                buf[i] = (dest >> 11) as u8;
+                buf[i + 3] = 0xF8 | ((dest >> 8) & 0x07) as u8;
+                buf[i + 2] = dest as u8;
+                i += 2;
+            }
+            i += 2;
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn arm64_code(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i + 4 <= buf.len() {
+            let pc = self.pos.wrapping_add(i as u32);
+            let mut instr = u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+            if instr >> 26 == 0x25 {
+                // BL instruction
+                let src = instr;
+                instr = 0x94000000 | (self.convert(src, pc >> 2) & 0x03FFFFFF);
+                buf[i..i + 4].copy_from_slice(&instr.to_le_bytes());
+            } else if instr & 0x9F000000 == 0x90000000 {
+                // ADRP instruction, only converted within +/-512 MiB
+                let src = ((instr >> 29) & 3) | ((instr >> 3) & 0x001FFFFC);
+                if src.wrapping_add(0x00020000) & 0x001C0000 == 0 {
+                    let dest = self.convert(src, pc >> 12);
+                    instr &= 0x9000001F;
+                    instr |= (dest & 3) << 29;
+                    instr |= (dest & 0x0003FFFC) << 3;
+                    instr |= 0u32.wrapping_sub(dest & 0x00020000) & 0x00E00000;
+                    buf[i..i + 4].copy_from_slice(&instr.to_le_bytes());
+                }
+            }
+            i += 4;
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn ppc_code(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i + 4 <= buf.len() {
+            if buf[i] & 0xFC == 0x48 && buf[i + 3] & 0x03 == 0x01 {
+                let src =
+                    u32::from_be_bytes([buf[i] & 0x03, buf[i + 1], buf[i + 2], buf[i + 3]]) & !0x03;
+                let dest = self.convert(src, self.pos.wrapping_add(i as u32));
+                buf[i] = 0x48 | ((dest >> 24) & 0x03) as u8;
+                buf[i + 1] = (dest >> 16) as u8;
+                buf[i + 2] = (dest >> 8) as u8;
+                buf[i + 3] = (buf[i + 3] & 0x03) | (dest as u8 & !0x03);
+            }
+            i += 4;
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn sparc_code(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i + 4 <= buf.len() {
+            if (buf[i] == 0x40 && buf[i + 1] & 0xC0 == 0x00)
+                || (buf[i] == 0x7F && buf[i + 1] & 0xC0 == 0xC0)
+            {
+                let src = u32::from_be_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]) << 2;
+                let mut dest = self.convert(src, self.pos.wrapping_add(i as u32)) >> 2;
+                dest = ((0u32.wrapping_sub((dest >> 22) & 1) << 22) & 0x3FFFFFFF)
+                    | (dest & 0x3FFFFF)
+                    | 0x40000000;
+                buf[i..i + 4].copy_from_slice(&dest.to_be_bytes());
+            }
+            i += 4;
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn ia64_code(&mut self, buf: &mut [u8]) -> usize {
+        let mut i = 0;
+        while i + 16 <= buf.len() {
+            let mask = IA64_BRANCH_TABLE[(buf[i] & 0x1F) as usize];
+            for slot in 0..3 {
+                if (mask >> slot) & 1 == 0 {
+                    continue;
+                }
+                let bit_pos = 5 + 41 * slot;
+                let byte_pos = i + bit_pos / 8;
+                let bit_res = bit_pos % 8;
+                let mut instr = 0u64;
+                for j in 0..6 {
+                    instr |= (buf[byte_pos + j] as u64) << (8 * j);
+                }
+                let mut instr_norm = instr >> bit_res;
+                if (instr_norm >> 37) & 0x0F != 0x05 || (instr_norm >> 9) & 0x07 != 0x00 {
+                    continue;
+                }
+
+                let mut src = ((instr_norm >> 13) & 0x0FFFFF) as u32;
+                src |= ((instr_norm >> 36) as u32 & 1) << 20;
+                let dest = self.convert(src << 4, self.pos.wrapping_add(i as u32)) >> 4;
+
+                instr_norm &= !(0x8FFFFF << 13);
+                instr_norm |= ((dest & 0x0FFFFF) as u64) << 13;
+                instr_norm |= ((dest & 0x100000) as u64) << (36 - 20);
+                instr &= (1 << bit_res) - 1;
+                instr |= instr_norm << bit_res;
+                for j in 0..6 {
+                    buf[byte_pos + j] = (instr >> (8 * j)) as u8;
+                }
+            }
+            i += 16;
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn riscv_encode(&mut self, buf: &mut [u8]) -> usize {
+        if buf.len() < 8 {
+            return 0;
+        }
+        let mut i = 0;
+        while i + 8 <= buf.len() {
+            let mut inst = buf[i] as u32;
+            if inst == 0xEF {
+                // JAL, only converted with rd=x1 (ra) or rd=x5 (t0). The address is stored
+                // in big endian order, which compresses slightly better.
+                let b1 = buf[i + 1] as u32;
+                if b1 & 0x0D != 0 {
+                    i += 2;
+                    continue;
+                }
+                let b2 = buf[i + 2] as u32;
+                let b3 = buf[i + 3] as u32;
+                let mut addr = ((b1 & 0xF0) << 8)
+                    | ((b2 & 0x0F) << 16)
+                    | ((b2 & 0x10) << 7)
+                    | ((b2 & 0xE0) >> 4)
+                    | ((b3 & 0x7F) << 4)
+                    | ((b3 & 0x80) << 13);
+                addr = addr.wrapping_add(self.pos.wrapping_add(i as u32));
+                buf[i + 1] = ((b1 & 0x0F) | ((addr >> 13) & 0xF0)) as u8;
+                buf[i + 2] = (addr >> 9) as u8;
+                buf[i + 3] = (addr >> 1) as u8;
+                i += 4;
+            } else if inst & 0x7F == 0x17 {
+                // AUIPC
+                inst = u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+                let mut inst2 =
+                    u32::from_le_bytes([buf[i + 4], buf[i + 5], buf[i + 6], buf[i + 7]]);
+                if inst & 0xE80 != 0 {
+                    // rd is neither x0 nor x2: convert an AUIPC pair to the special form with
+                    // rd=x2, followed by the absolute address in big endian order.
+                    if riscv_not_auipc_pair(inst, inst2) {
+                        i += 6;
+                        continue;
+                    }
+                    let mut addr = inst & 0xFFFFF000;
+                    addr = addr
+                        .wrapping_add(inst2 >> 20)
+                        .wrapping_sub((inst2 >> 19) & 0x1000);
+                    addr = addr.wrapping_add(self.pos.wrapping_add(i as u32));
+                    inst = 0x17 | (2 << 7) | (inst2 << 12);
+                    buf[i..i + 4].copy_from_slice(&inst.to_le_bytes());
+                    buf[i + 4..i + 8].copy_from_slice(&addr.to_be_bytes());
+                } else {
+                    // rd is x0 or x2: data that looks like the special form is stored so that
+                    // the decoder restores it.
+                    let fake_rs1 = inst >> 27;
+                    if riscv_not_special_auipc(inst, fake_rs1) {
+                        i += 4;
+                        continue;
+                    }
+                    let fake_addr = inst2;
+                    inst2 = (inst >> 12) | (fake_addr << 20);
+                    inst = 0x17 | (fake_rs1 << 7) | (fake_addr & 0xFFFFF000);
+                    buf[i..i + 4].copy_from_slice(&inst.to_le_bytes());
+                    buf[i + 4..i + 8].copy_from_slice(&inst2.to_le_bytes());
+                }
+                i += 8;
+            } else {
+                i += 2;
+            }
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+
+    fn riscv_decode(&mut self, buf: &mut [u8]) -> usize {
+        if buf.len() < 8 {
+            return 0;
+        }
+        let mut i = 0;
+        while i + 8 <= buf.len() {
+            let mut inst = buf[i] as u32;
+            if inst == 0xEF {
+                // JAL
+                let b1 = buf[i + 1] as u32;
+                if b1 & 0x0D != 0 {
+                    i += 2;
+                    continue;
+                }
+                let b2 = buf[i + 2] as u32;
+                let b3 = buf[i + 3] as u32;
+                let mut addr = ((b1 & 0xF0) << 13) | (b2 << 9) | (b3 << 1);
+                addr = addr.wrapping_sub(self.pos.wrapping_add(i as u32));
+                buf[i + 1] = ((b1 & 0x0F) | ((addr >> 8) & 0xF0)) as u8;
+                buf[i + 2] =
+                    (((addr >> 16) & 0x0F) | ((addr >> 7) & 0x10) | ((addr << 4) & 0xE0)) as u8;
+                buf[i + 3] = (((addr >> 4) & 0x7F) | ((addr >> 13) & 0x80)) as u8;
+                i += 4;
+            } else if inst & 0x7F == 0x17 {
+                // AUIPC
+                inst = u32::from_le_bytes([buf[i], buf[i + 1], buf[i + 2], buf[i + 3]]);
+                let mut inst2 =
+                    u32::from_le_bytes([buf[i + 4], buf[i + 5], buf[i + 6], buf[i + 7]]);
+                if inst & 0xE80 != 0 {
+                    // rd is neither x0 nor x2: undo the encoding of data that looked like
+                    // the special form.
+                    if riscv_not_auipc_pair(inst, inst2) {
+                        i += 6;
+                        continue;
+                    }
+                    let addr = (inst & 0xFFFFF000).wrapping_add(inst2 >> 20);
+                    inst = 0x17 | (2 << 7) | (inst2 << 12);
+                    inst2 = addr;
+                } else {
+                    // rd is x0 or x2: restore an AUIPC pair from the special form.
+                    let inst2_rs1 = inst >> 27;
+                    if riscv_not_special_auipc(inst, inst2_rs1) {
+                        i += 4;
+                        continue;
+                    }
+                    let mut addr =
+                        u32::from_be_bytes([buf[i + 4], buf[i + 5], buf[i + 6], buf[i + 7]]);
+                    addr = addr.wrapping_sub(self.pos.wrapping_add(i as u32));
+                    inst2 = (inst >> 12) | (addr << 20);
+                    inst = 0x17 | (inst2_rs1 << 7) | (addr.wrapping_add(0x800) & 0xFFFFF000);
+                }
+                buf[i..i + 4].copy_from_slice(&inst.to_le_bytes());
+                buf[i + 4..i + 8].copy_from_slice(&inst2.to_le_bytes());
+                i += 8;
+            } else {
+                i += 2;
+            }
+        }
+        self.pos = self.pos.wrapping_add(i as u32);
+        i
+    }
+}
+
+/// Whether the rd of an AUIPC differs from the rs1 of the next instruction, or that isn't
+/// a 32-bit instruction.
+#[inline]
+fn riscv_not_auipc_pair(auipc: u32, inst2: u32) -> bool {
+    ((auipc << 8) ^ inst2.wrapping_sub(3)) & 0xF8003 != 0
+}
+
+/// Whether an AUIPC with rd=x0 or rd=x2 is not in the special form of a converted pair, whose
+/// rd is x2 and which holds a 32-bit instruction with an rs1 other than x0 and x2.
+#[inline]
+fn riscv_not_special_auipc(auipc: u32, inst2_rs1: u32) -> bool {
+    (auipc.wrapping_sub(0x3117) << 18) >= (inst2_rs1 & 0x1D)
+}
+
+/// Decodes the data of a BCJ filter, usually read from an [LZMA2Reader](crate::LZMA2Reader).
+/// The converted data is buffered on the stack, so the reader works without `alloc` too.
+///
+/// # Examples
+/// ```
+/// use std::io::Read;
+/// use lzma_rust::{BCJReader, BCJWriter, LZMA2Options, LZMA2Reader, LZMA2Writer, CountingWriter};
+/// let code = [0x55, 0xE8, 0x10, 0x00, 0x00, 0x00, 0x5D, 0xC3].repeat(100);
+/// let options = LZMA2Options::with_preset(6);
+///
+/// let lzma2 = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+/// let mut writer = BCJWriter::new_x86(lzma2, 0);
+/// std::io::Write::write_all(&mut writer, &code).unwrap();
+/// std::io::Write::write(&mut writer, &[]).unwrap();
+/// let compressed = writer.into_inner().inner.inner;
+///
+/// let lzma2 = LZMA2Reader::new(&compressed[..], options.dict_size, None);
+/// let mut reader = BCJReader::new_x86(lzma2, 0);
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, code);
+/// ```
+pub struct BCJReader<R> {
+    inner: R,
+    decoder: BCJDecoder,
+}
+
+impl<R> BCJReader<R> {
+    pub fn new(inner: R, filter: BCJFilter) -> Self {
+        Self {
+            inner,
+            decoder: BCJDecoder::new(filter),
+        }
+    }
+
+    /// Creates a reader that decodes the x86 BCJ filter.
+    /// - [start_pos] - the start offset of the filter, 0 unless the encoder used another one.
+    pub fn new_x86(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_x86(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the ARM BCJ filter.
+    pub fn new_arm(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_arm(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the ARM-Thumb BCJ filter.
+    pub fn new_arm_thumb(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_arm_thumb(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the ARM64 BCJ filter.
+    pub fn new_arm64(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_arm64(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the PowerPC BCJ filter.
+    pub fn new_ppc(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_ppc(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the SPARC BCJ filter.
+    pub fn new_sparc(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_sparc(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the IA-64 BCJ filter.
+    pub fn new_ia64(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_ia64(false, start_pos))
+    }
+
+    /// Creates a reader that decodes the RISC-V BCJ filter.
+    pub fn new_riscv(inner: R, start_pos: u32) -> Self {
+        Self::new(inner, BCJFilter::new_riscv(false, start_pos))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl<R: Read> embedded_io::ErrorType for BCJReader<R> {
+    type Error = <R as embedded_io::ErrorType>::Error;
+}
+
+impl<R: Read> Read for BCJReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> crate::io::lzma_reader_result!(R, usize) {
+        self.decoder.read(&mut self.inner, buf)
+    }
+}
+
+/// The buffer of a [BCJReader], which holds the data read from the inner reader until
+/// the instructions in it are complete.
+pub(crate) struct BCJDecoder {
+    filter: BCJFilter,
+    buf: [u8; FILTER_BUF_SIZE],
+    pos: usize,
+    filtered: usize,
+    unfiltered: usize,
+    end_reached: bool,
+}
+
+impl BCJDecoder {
+    pub(crate) fn new(filter: BCJFilter) -> Self {
+        Self {
+            filter,
+            buf: [0; FILTER_BUF_SIZE],
+            pos: 0,
+            filtered: 0,
+            unfiltered: 0,
+            end_reached: false,
+        }
+    }
+
+    pub(crate) fn read<R: Read>(
+        &mut self,
+        inner: &mut R,
+        mut buf: &mut [u8],
+    ) -> crate::io::lzma_reader_result!(R, usize) {
+        let mut size = 0;
+        loop {
+            let copy_size = self.filtered.min(buf.len());
+            buf[..copy_size].copy_from_slice(&self.buf[self.pos..self.pos + copy_size]);
+            buf = &mut buf[copy_size..];
+            self.pos += copy_size;
+            self.filtered -= copy_size;
+            size += copy_size;
+
+            // Move the pending data to the start once the end of the buffer is reached.
+            let end = self.pos + self.filtered + self.unfiltered;
+            if end == FILTER_BUF_SIZE {
+                self.buf.copy_within(self.pos..end, 0);
+                self.pos = 0;
+            }
+
+            if buf.is_empty() || self.end_reached || size > 0 {
+                return Ok(size);
+            }
+
+            let end = self.pos + self.filtered + self.unfiltered;
+            let in_size = inner.read(&mut self.buf[end..])?;
+            if in_size == 0 {
+                // The last bytes can't be the start of an instruction anymore.
+                self.end_reached = true;
+                self.filtered = self.unfiltered;
+                self.unfiltered = 0;
+            } else {
+                self.unfiltered += in_size;
+                self.filtered = self
+                    .filter
+                    .code(&mut self.buf[self.pos..self.pos + self.unfiltered]);
+                self.unfiltered -= self.filtered;
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+use super::bcj::BCJDecoder;
+use super::{BCJArch, BCJFilter, BCJOptions, DeltaFilter, DeltaOptions, FILTER_ID_DELTA};
+use crate::io::{error, ErrorKind, Read, Result};
+use crate::xz::{FilterFlags, FILTERS_MAX, FILTER_ID_LZMA2};
+use crate::LZMA2Reader;
+#[cfg(feature = "encoder")]
+use crate::{io::Write, BCJWriter, CountingWriter, DeltaWriter, LZMA2Options, LZMA2Writer};
+
+/// A filter before LZMA2 in a [FilterChain], which provides a decoder for reading and wraps
+/// a writer with its encoder.
+pub trait Filter {
+    /// The ID of the filter in xz block headers.
+    fn id(&self) -> u64;
+
+    /// The properties of the filter in xz block headers.
+    fn props(&self) -> crate::Vec<u8>;
+
+    /// Checks the options, called when a chain is built.
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// A new decoder of the filter.
+    fn decoder(&self) -> Box<dyn FilterDecoder>;
+
+    /// Encodes the data before it is written to `inner`. Writing an empty buffer to the
+    /// returned writer has to finish it and then `inner`.
+    #[cfg(feature = "encoder")]
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> Result<Box<dyn Write + 'a>>;
+}
+
+/// Decodes the data of a [Filter] in a [FilterChainReader].
+pub trait FilterDecoder: Send {
+    /// Reads decoded data into `buf` from the data read from `inner`, the next stage of
+    /// the chain. Returns 0 at the end of the data.
+    fn read(&mut self, inner: &mut dyn Read, buf: &mut [u8]) -> Result<usize>;
+}
+
+impl FilterDecoder for BCJDecoder {
+    fn read(&mut self, inner: &mut dyn Read, buf: &mut [u8]) -> Result<usize> {
+        BCJDecoder::read(self, &mut &mut *inner, buf)
+    }
+}
+
+impl FilterDecoder for DeltaFilter {
+    fn read(&mut self, inner: &mut dyn Read, buf: &mut [u8]) -> Result<usize> {
+        let size = inner.read(buf)?;
+        self.decode(&mut buf[..size]);
+        Ok(size)
+    }
+}
+
+impl Filter for BCJOptions {
+    fn id(&self) -> u64 {
+        self.arch.filter_id()
+    }
+
+    /// Empty for the default start offset 0, otherwise the start offset in little endian.
+    fn props(&self) -> crate::Vec<u8> {
+        if self.start_pos == 0 {
+            crate::Vec::new()
+        } else {
+            self.start_pos.to_le_bytes().to_vec()
+        }
+    }
+
+    fn decoder(&self) -> Box<dyn FilterDecoder> {
+        Box::new(BCJDecoder::new(BCJFilter::from_options(self, false)))
+    }
+
+    #[cfg(feature = "encoder")]
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> Result<Box<dyn Write + 'a>> {
+        Ok(Box::new(BCJWriter::new(
+            inner,
+            BCJFilter::from_options(self, true),
+        )))
+    }
+}
+
+impl Filter for DeltaOptions {
+    fn id(&self) -> u64 {
+        FILTER_ID_DELTA
+    }
+
+    fn props(&self) -> crate::Vec<u8> {
+        vec![(self.distance - 1) as u8]
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !(DeltaFilter::DISTANCE_MIN..=DeltaFilter::DISTANCE_MAX).contains(&self.distance) {
+            return error!(ErrorKind::InvalidInput, "Delta distance must be 1..=256");
+        }
+        Ok(())
+    }
+
+    fn decoder(&self) -> Box<dyn FilterDecoder> {
+        Box::new(DeltaFilter::new(self.distance))
+    }
+
+    #[cfg(feature = "encoder")]
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> Result<Box<dyn Write + 'a>> {
+        Ok(Box::new(DeltaWriter::new(inner, self.distance)))
+    }
+}
+
+/// Up to three filters followed by LZMA2, like the filter chain of an xz block.
+///
+/// The filters are listed in the order they are applied when encoding, so the writer passes
+/// the data through the first filter first, and the reader undoes it last.
+///
+/// Building a chain and writing need the `encoder` feature; a chain read from the filter
+/// flags of an xz block header can be decoded without it.
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::filter::{BCJArch, BCJOptions, DeltaOptions};
+/// use lzma_rust::{FilterChain, LZMA2Options};
+/// let chain = FilterChain::builder()
+///     .filter(BCJOptions::new(BCJArch::X86))
+///     .lzma2(LZMA2Options::with_preset(6))
+///     .build()
+///     .unwrap();
+///
+/// let data = [0xE8, 0x10, 0x00, 0x00, 0x00, 0xC3].repeat(100);
+/// let mut compressed = Vec::new();
+/// let mut writer = chain.writer(&mut compressed).unwrap();
+/// writer.write_all(&data).unwrap();
+/// writer.write(&[]).unwrap();
+/// drop(writer);
+///
+/// // The filter flags of an xz block header describe the chain.
+/// let chain = FilterChain::from_filter_flags(&chain.filter_flags()).unwrap();
+/// let mut decompressed = Vec::new();
+/// chain.reader(&compressed[..]).read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, data);
+/// ```
+pub struct FilterChain {
+    filters: crate::Vec<Box<dyn Filter>>,
+    dict_size: u64,
+    #[cfg(feature = "encoder")]
+    lzma2: LZMA2Options,
+}
+
+impl FilterChain {
+    #[cfg(feature = "encoder")]
+    pub fn builder() -> FilterChainBuilder {
+        FilterChainBuilder::default()
+    }
+
+    /// Rebuilds a chain from the filter flags of an xz block header. Only the dictionary
+    /// size is known of the LZMA2 options, which is all that decoding needs.
+    pub fn from_filter_flags(flags: &[FilterFlags]) -> Result<Self> {
+        let (last, filters) = match flags.split_last() {
+            Some(split) if flags.len() <= FILTERS_MAX => split,
+            _ => return error!(ErrorKind::InvalidData, "Invalid number of filters"),
+        };
+        if last.id != FILTER_ID_LZMA2 {
+            return error!(
+                ErrorKind::Unsupported,
+                "Unsupported filter chain, LZMA2 must be the last filter"
+            );
+        }
+        if last.props.len() != 1 {
+            return error!(ErrorKind::Unsupported, "Unsupported LZMA2 properties");
+        }
+        let dict_size = crate::lzma2_dict_size_from_prop(last.props[0])?;
+
+        let mut chain = Self {
+            filters: crate::Vec::new(),
+            dict_size,
+            #[cfg(feature = "encoder")]
+            lzma2: LZMA2Options {
+                dict_size,
+                ..Default::default()
+            },
+        };
+        for flags in filters {
+            let props = &flags.props[..];
+            let filter: Box<dyn Filter> = match (flags.id, BCJArch::from_filter_id(flags.id)) {
+                (_, Some(arch)) => {
+                    let start_pos = match props.len() {
+                        0 => 0,
+                        4 => u32::from_le_bytes([props[0], props[1], props[2], props[3]]),
+                        _ => return error!(ErrorKind::Unsupported, "Unsupported BCJ properties"),
+                    };
+                    Box::new(BCJOptions { arch, start_pos })
+                }
+                (FILTER_ID_DELTA, None) if props.len() == 1 => {
+                    Box::new(DeltaOptions::new(props[0] as usize + 1))
+                }
+                (FILTER_ID_DELTA, None) => {
+                    return error!(ErrorKind::Unsupported, "Unsupported Delta properties")
+                }
+                (FILTER_ID_LZMA2, None) => {
+                    return error!(
+                        ErrorKind::Unsupported,
+                        "Unsupported filter chain, LZMA2 must be the last filter"
+                    )
+                }
+                (id, None) => {
+                    return error!(
+                        ErrorKind::Unsupported,
+                        format!("Unsupported filter {:#04x}", id)
+                    )
+                }
+            };
+            chain.filters.push(filter);
+        }
+        Ok(chain)
+    }
+
+    /// The filters before LZMA2.
+    pub fn filters(&self) -> &[Box<dyn Filter>] {
+        &self.filters
+    }
+
+    /// The dictionary size of the LZMA2 stage.
+    pub fn dict_size(&self) -> u64 {
+        self.dict_size
+    }
+
+    #[cfg(feature = "encoder")]
+    pub fn lzma2(&self) -> &LZMA2Options {
+        &self.lzma2
+    }
+
+    /// The filter flags of an xz block header, ending with LZMA2.
+    #[cfg(feature = "encoder")]
+    pub fn filter_flags(&self) -> crate::Vec<FilterFlags> {
+        let mut flags: crate::Vec<_> = self
+            .filters
+            .iter()
+            .map(|f| FilterFlags {
+                id: f.id(),
+                props: f.props(),
+            })
+            .collect();
+        flags.push(FilterFlags {
+            id: FILTER_ID_LZMA2,
+            props: vec![self.lzma2.dict_size_prop()],
+        });
+        flags
+    }
+
+    /// Decompresses the data read from `inner`.
+    pub fn reader<R: Read>(&self, inner: R) -> FilterChainReader<R> {
+        #[cfg(feature = "encoder")]
+        let preset_dict = self.lzma2.preset_dict.as_deref();
+        #[cfg(not(feature = "encoder"))]
+        let preset_dict = None;
+        FilterChainReader {
+            lzma2: LZMA2Reader::new(inner, self.dict_size, preset_dict),
+            decoders: self.filters.iter().map(|f| f.decoder()).collect(),
+        }
+    }
+
+    /// Compresses the data written to the returned writer into `inner`. Write an empty
+    /// buffer to finish all stages. Pass `&mut W` to keep access to the compressed data.
+    #[cfg(feature = "encoder")]
+    pub fn writer<'a, W: Write + 'a>(&self, inner: W) -> Result<Box<dyn Write + 'a>> {
+        let lzma2 = LZMA2Writer::new(CountingWriter::new(inner), &self.lzma2)?;
+        let mut writer: Box<dyn Write + 'a> = Box::new(lzma2);
+        for filter in self.filters.iter().rev() {
+            writer = filter.writer(writer)?;
+        }
+        Ok(writer)
+    }
+}
+
+/// Decompresses the data of a [FilterChain], see [FilterChain::reader].
+pub struct FilterChainReader<R> {
+    lzma2: LZMA2Reader<R>,
+    /// The decoders in the order of the filters, so the first one returns the output.
+    decoders: crate::Vec<Box<dyn FilterDecoder>>,
+}
+
+impl<R> FilterChainReader<R> {
+    pub fn into_inner(self) -> R {
+        self.lzma2.into_inner()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.lzma2.get_ref()
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        self.lzma2.get_mut()
+    }
+}
+
+impl<R: Read> Read for FilterChainReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        Stages {
+            decoders: &mut self.decoders,
+            lzma2: &mut self.lzma2,
+        }
+        .read(buf)
+    }
+}
+
+/// The decoders of a [FilterChainReader] from one of them on, with LZMA2 at the end.
+struct Stages<'a> {
+    decoders: &'a mut [Box<dyn FilterDecoder>],
+    lzma2: &'a mut dyn Read,
+}
+
+impl Read for Stages<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        match self.decoders.split_first_mut() {
+            Some((decoder, decoders)) => decoder.read(
+                &mut Stages {
+                    decoders,
+                    lzma2: &mut *self.lzma2,
+                },
+                buf,
+            ),
+            None => self.lzma2.read(buf),
+        }
+    }
+}
+
+/// Builder of a [FilterChain].
+#[cfg(feature = "encoder")]
+#[derive(Default)]
+pub struct FilterChainBuilder {
+    filters: crate::Vec<Box<dyn Filter>>,
+    lzma2: LZMA2Options,
+}
+
+#[cfg(feature = "encoder")]
+impl FilterChainBuilder {
+    /// Appends a filter, which is applied after the ones added before when encoding.
+    pub fn filter<F: Filter + 'static>(mut self, filter: F) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Sets the options of the LZMA2 stage at the end of the chain, preset 6 by default.
+    pub fn lzma2(mut self, options: LZMA2Options) -> Self {
+        self.lzma2 = options;
+        self
+    }
+
+    /// Checks the options of every filter, including LZMA2, and that there are at most three
+    /// filters before LZMA2.
+    pub fn build(self) -> Result<FilterChain> {
+        if self.filters.len() >= FILTERS_MAX {
+            return error!(
+                ErrorKind::InvalidInput,
+                "A filter chain can have at most 3 filters before LZMA2"
+            );
+        }
+        for filter in self.filters.iter() {
+            if filter.id() == FILTER_ID_LZMA2 {
+                return error!(
+                    ErrorKind::InvalidInput,
+                    "LZMA2 must only be the last filter"
+                );
+            }
+            filter.validate()?;
+        }
+        self.lzma2.validate()?;
+        Ok(FilterChain {
+            filters: self.filters,
+            dict_size: self.lzma2.dict_size,
+            lzma2: self.lzma2,
+        })
+    }
+}
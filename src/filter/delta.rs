@@ -0,0 +1,145 @@
+use crate::io::Read;
+
+/// Filter ID of the Delta filter in xz block headers.
+pub const FILTER_ID_DELTA: u64 = 0x03;
+
+const DISTANCE_MASK: usize = DeltaFilter::DISTANCE_MAX - 1;
+
+/// Options of the Delta filter, as stored in xz filter flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeltaOptions {
+    /// Distance in bytes, `DeltaFilter::DISTANCE_MIN..=DeltaFilter::DISTANCE_MAX`.
+    pub distance: usize,
+}
+
+impl DeltaOptions {
+    pub fn new(distance: usize) -> Self {
+        Self { distance }
+    }
+}
+
+/// Delta coder, which stores the difference of every byte to the byte `distance` bytes
+/// before it. This helps LZMA2 with data made of fixed-size samples, e.g. 16-bit stereo PCM
+/// audio with a distance of 4, where neighbouring samples have similar values.
+///
+/// The coder keeps the last bytes between calls, so data can be converted in pieces.
+pub struct DeltaFilter {
+    distance: usize,
+    history: [u8; DeltaFilter::DISTANCE_MAX],
+    pos: u8,
+}
+
+impl DeltaFilter {
+    pub const DISTANCE_MIN: usize = 1;
+    pub const DISTANCE_MAX: usize = 256;
+
+    /// Creates a delta coder.
+    ///
+    /// # Panics
+    /// If `distance` is not in `DISTANCE_MIN..=DISTANCE_MAX`.
+    pub fn new(distance: usize) -> Self {
+        assert!(
+            (Self::DISTANCE_MIN..=Self::DISTANCE_MAX).contains(&distance),
+            "delta distance must be 1..=256"
+        );
+        Self {
+            distance,
+            history: [0; Self::DISTANCE_MAX],
+            pos: 0,
+        }
+    }
+
+    /// Creates a delta coder from the property byte of the xz filter flags.
+    pub fn from_props(props: u8) -> Self {
+        Self::new(props as usize + 1)
+    }
+
+    pub fn distance(&self) -> usize {
+        self.distance
+    }
+
+    /// The property byte of the xz filter flags, which is `distance - 1`.
+    pub fn props(&self) -> u8 {
+        (self.distance - 1) as u8
+    }
+
+    pub fn encode(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            let tmp = self.history[(self.distance + self.pos as usize) & DISTANCE_MASK];
+            self.history[self.pos as usize] = *b;
+            self.pos = self.pos.wrapping_sub(1);
+            *b = b.wrapping_sub(tmp);
+        }
+    }
+
+    pub fn decode(&mut self, buf: &mut [u8]) {
+        for b in buf.iter_mut() {
+            *b = b.wrapping_add(self.history[(self.distance + self.pos as usize) & DISTANCE_MASK]);
+            self.history[self.pos as usize] = *b;
+            self.pos = self.pos.wrapping_sub(1);
+        }
+    }
+}
+
+/// Decodes the data of a Delta filter, usually read from an [LZMA2Reader](crate::LZMA2Reader).
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::{CountingWriter, DeltaReader, DeltaWriter, LZMA2Options, LZMA2Reader, LZMA2Writer};
+/// // 16-bit stereo samples
+/// let pcm: Vec<u8> = (0..4096i16).flat_map(|i| [i * 3, -i * 5]).flat_map(i16::to_le_bytes).collect();
+/// let options = LZMA2Options::with_preset(6);
+///
+/// let lzma2 = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+/// let mut writer = DeltaWriter::new(lzma2, 4);
+/// writer.write_all(&pcm).unwrap();
+/// writer.write(&[]).unwrap();
+/// let compressed = writer.into_inner().inner.inner;
+///
+/// let lzma2 = LZMA2Reader::new(&compressed[..], options.dict_size, None);
+/// let mut reader = DeltaReader::new(lzma2, 4);
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, pcm);
+/// ```
+pub struct DeltaReader<R> {
+    inner: R,
+    filter: DeltaFilter,
+}
+
+impl<R> DeltaReader<R> {
+    /// Creates a reader that decodes the Delta filter.
+    /// - [distance] - the distance in bytes, see [DeltaFilter::new].
+    pub fn new(inner: R, distance: usize) -> Self {
+        Self {
+            inner,
+            filter: DeltaFilter::new(distance),
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+#[cfg(feature = "no_std")]
+impl<R: Read> embedded_io::ErrorType for DeltaReader<R> {
+    type Error = <R as embedded_io::ErrorType>::Error;
+}
+
+impl<R: Read> Read for DeltaReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> crate::io::lzma_reader_result!(R, usize) {
+        let size = self.inner.read(buf)?;
+        self.filter.decode(&mut buf[..size]);
+        Ok(size)
+    }
+}
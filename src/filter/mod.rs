@@ -0,0 +1,18 @@
+//! Filters that preprocess the data before LZMA2 compresses it, as used by the xz format.
+//!
+//! A filter doesn't change the size of the data; it only transforms it into something that
+//! LZMA2 compresses better. The readers undo the filter on the data read from an
+//! [LZMA2Reader](crate::LZMA2Reader), the writers apply it before the data is passed on to an
+//! `LZMA2Writer`.
+mod bcj;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+mod chain;
+mod delta;
+
+pub use bcj::*;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use chain::*;
+pub use delta::*;
+
+/// Size of the buffers of the filter readers and writers.
+pub(crate) const FILTER_BUF_SIZE: usize = 4096;
@@ -16,10 +16,14 @@
     allow(incomplete_features)
 )]
 
+pub mod check;
 #[cfg_attr(feature = "alloc", path = "./decoder_alloc.rs")]
 #[cfg_attr(not(feature = "alloc"), path = "./decoder_no_alloc.rs")]
 pub mod decoder;
+pub mod filter;
 pub mod lz;
+#[cfg(feature = "alloc")]
+mod lzma_header;
 #[cfg_attr(feature = "alloc", path = "./lzma2_reader_alloc.rs")]
 #[cfg_attr(not(feature = "alloc"), path = "./lzma2_reader_no_alloc.rs")]
 pub mod lzma2_reader;
@@ -35,15 +39,50 @@ mod state;
 #[macro_use]
 pub extern crate alloc;
 
+#[cfg(feature = "alloc")]
+pub use lzma2_reader::dict_size_from_prop as lzma2_dict_size_from_prop;
+pub use filter::{BCJReader, DeltaReader};
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use filter::{FilterChain, FilterChainReader};
+#[cfg(all(feature = "encoder", feature = "alloc", not(feature = "no_std")))]
+pub use filter::FilterChainBuilder;
 pub use lzma2_reader::get_memory_usage as lzma2_get_memory_usage;
 pub use lzma2_reader::LZMA2Reader;
 pub use lzma_reader::get_memory_usage as lzma_get_memory_usage;
 pub use lzma_reader::get_memory_usage_by_props as lzma_get_memory_usage_by_props;
 pub use lzma_reader::LZMAReader;
+#[cfg(feature = "alloc")]
+pub use lzma_header::LZMAHeader;
 #[cfg(all(feature = "encoder", feature = "alloc"))]
 pub mod enc;
 #[cfg(all(feature = "encoder", feature = "alloc"))]
 pub use enc::*;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+mod counting_reader;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub mod lzip;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use lzip::LzipReader;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+mod lzma2_mt_reader;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use lzma2_mt_reader::LZMA2MTReader;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+mod microlzma_reader;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use microlzma_reader::MicroLZMAReader;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub mod sevenz;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use sevenz::{SevenZEntry, SevenZReader};
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub mod xz;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use xz::{SeekableXZReader, XZReader};
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub mod zip;
+#[cfg(all(feature = "alloc", not(feature = "no_std")))]
+pub use zip::ZipLzmaReader;
 
 use state::*;
 
@@ -69,13 +108,6 @@ mod io_alloc {
         };
     }
 
-    macro_rules! lzma_reader_result {
-        ($reader: ty, $out: ty) => {
-            std::io::Result<$out>
-        };
-    }
-
-    pub(crate) use lzma_reader_result;
     pub(crate) use read_exact_result;
     pub(crate) use write_result;
 
@@ -102,12 +134,24 @@ mod io_alloc {
     pub(crate) use transmute_result_error_type;
 }
 
+#[cfg(not(feature = "no_std"))]
+mod io_result {
+    macro_rules! lzma_reader_result {
+        ($reader: ty, $out: ty) => {
+            std::io::Result<$out>
+        };
+    }
+
+    pub(crate) use lzma_reader_result;
+}
+
 #[cfg(not(feature = "no_std"))]
 pub mod io {
     pub use std::io::*;
 
     #[cfg(feature = "alloc")]
     pub(crate) use super::io_alloc::*;
+    pub(crate) use super::io_result::*;
 
     pub type Result<T> = std::io::Result<T>;
 }
@@ -132,14 +176,6 @@ mod io_alloc {
     }
     pub(crate) use read_exact_error_kind;
 
-    macro_rules! lzma_reader_result {
-        ($reader: ty, $out: ty) => {
-            core::result::Result<$out, <$reader as embedded_io::ErrorType>::Error>
-        };
-    }
-
-    pub(crate) use lzma_reader_result;
-
     macro_rules! write_error_kind {
         ($writer: ty, $kind:expr) => {{
             let kind = unsafe {
@@ -188,6 +224,17 @@ mod io_alloc {
     pub(crate) use write_result;
 }
 
+#[cfg(feature = "no_std")]
+mod io_result {
+    macro_rules! lzma_reader_result {
+        ($reader: ty, $out: ty) => {
+            core::result::Result<$out, <$reader as embedded_io::ErrorType>::Error>
+        };
+    }
+
+    pub(crate) use lzma_reader_result;
+}
+
 #[cfg(feature = "no_std")]
 pub mod io {
 
@@ -196,6 +243,7 @@ pub mod io {
 
     #[cfg(feature = "alloc")]
     pub(crate) use super::io_alloc::*;
+    pub(crate) use super::io_result::*;
 }
 
 pub const DICT_SIZE_MIN: u64 = 4096;
@@ -0,0 +1,248 @@
+use super::{LZEncoder, MatchFind, Matches};
+use crate::vec;
+
+const HASH2_SIZE: usize = 1 << 16;
+
+/// Binary tree match finder that indexes the first two bytes directly, which finds every
+/// match of two bytes.
+pub struct BT2 {
+    hash2_table: crate::Vec<i64>,
+    tree: crate::Vec<i64>,
+    depth_limit: i64,
+
+    cyclic_size: i64,
+    cyclic_pos: i64,
+    lz_pos: i64,
+}
+
+const MAX_POS: i64 = 0x7fffffff;
+#[inline(always)]
+fn hash2(buf: &[u8]) -> usize {
+    u16::from_le_bytes([buf[0], buf[1]]) as usize
+}
+#[inline(always)]
+fn sh_left(i: i64) -> i64 {
+    ((i as u64) << 1) as i64
+}
+impl BT2 {
+    pub fn new(dict_size: u64, nice_len: u64, depth_limit: i64) -> Self {
+        let cyclic_size = dict_size as i64 + 1;
+        Self {
+            hash2_table: vec![0; HASH2_SIZE],
+            tree: vec![0; cyclic_size as usize * 2],
+            depth_limit: if depth_limit > 0 {
+                depth_limit
+            } else {
+                16 + nice_len as i64 / 2
+            },
+            cyclic_size,
+            cyclic_pos: -1,
+            lz_pos: cyclic_size,
+        }
+    }
+
+    pub fn get_mem_usage(dict_size: u64) -> u64 {
+        HASH2_SIZE as u64 / (1024 / 8) + 4 + dict_size / (1024 / 16) + 10
+    }
+
+    fn move_pos(&mut self, encoder: &mut super::LZEncoderData) -> i64 {
+        let avail = encoder.move_pos(encoder.nice_len as _, 2);
+        if avail != 0 {
+            self.lz_pos += 1;
+            if self.lz_pos == MAX_POS {
+                let normalization_offset = MAX_POS - self.cyclic_size;
+                LZEncoder::normalize(&mut self.hash2_table, normalization_offset);
+                LZEncoder::normalize(
+                    &mut self.tree[..self.cyclic_size as usize * 2],
+                    normalization_offset,
+                );
+                self.lz_pos -= normalization_offset;
+            }
+            self.cyclic_pos += 1;
+            if self.cyclic_pos == self.cyclic_size {
+                self.cyclic_pos = 0;
+            }
+        }
+        avail
+    }
+
+    fn skip(
+        &mut self,
+        encoder: &mut super::LZEncoderData,
+        nice_len_limit: i64,
+        mut current_match: i64,
+    ) {
+        let mut depth = self.depth_limit;
+
+        let mut ptr0 = sh_left(self.cyclic_pos) + 1;
+        let mut ptr1 = sh_left(self.cyclic_pos);
+        let mut len0 = 0;
+        let mut len1 = 0;
+
+        loop {
+            let delta = self.lz_pos - current_match;
+
+            if depth == 0 || delta >= self.cyclic_size {
+                self.tree[ptr0 as usize] = 0;
+                self.tree[ptr1 as usize] = 0;
+                return;
+            }
+            depth -= 1;
+
+            let pair = self.cyclic_pos - delta
+                + (if delta > self.cyclic_pos {
+                    self.cyclic_size
+                } else {
+                    0
+                });
+            let pair = sh_left(pair);
+            let mut len = len0.min(len1);
+
+            if encoder.get_byte(len, delta) == encoder.get_byte(len, 0) {
+                // No need to look for longer matches than niceLenLimit
+                // because we only are updating the tree, not returning
+                // matches found to the caller.
+                loop {
+                    len += 1;
+                    if len == nice_len_limit {
+                        self.tree[ptr1 as usize] = self.tree[pair as usize];
+                        self.tree[ptr0 as usize] = self.tree[pair as usize + 1];
+                        return;
+                    }
+                    if encoder.get_byte(len, delta) != encoder.get_byte(len, 0) {
+                        break;
+                    }
+                }
+            }
+
+            if encoder.get_byte(len, delta) < encoder.get_byte(len, 0) {
+                self.tree[ptr1 as usize] = current_match;
+                ptr1 = pair + 1;
+                current_match = self.tree[ptr1 as usize];
+                len1 = len;
+            } else {
+                self.tree[ptr0 as usize] = current_match;
+                ptr0 = pair;
+                current_match = self.tree[ptr0 as usize];
+                len0 = len;
+            }
+        }
+    }
+}
+
+impl MatchFind for BT2 {
+    fn find_matches(&mut self, encoder: &mut super::LZEncoderData, matches: &mut Matches) {
+        matches.count = 0;
+
+        let mut match_len_limit = encoder.match_len_max as i64;
+        let mut nice_len_limit = encoder.nice_len as i64;
+        let avail = self.move_pos(encoder);
+
+        if avail < match_len_limit {
+            if avail == 0 {
+                return;
+            }
+            match_len_limit = avail;
+            if nice_len_limit > avail {
+                nice_len_limit = avail;
+            }
+        }
+
+        let hash2_value = hash2(encoder.buf_mut());
+        let mut current_match = self.hash2_table[hash2_value];
+        self.hash2_table[hash2_value] = self.lz_pos;
+
+        let mut len_best = 1;
+        let mut depth = self.depth_limit;
+
+        let mut ptr0 = sh_left(self.cyclic_pos) + 1;
+        let mut ptr1 = sh_left(self.cyclic_pos);
+        let mut len0 = 0;
+        let mut len1 = 0;
+
+        loop {
+            let delta = self.lz_pos - current_match;
+
+            // Return if the search depth limit has been reached or
+            // if the distance of the potential match exceeds the
+            // dictionary size.
+            if depth == 0 || delta >= self.cyclic_size {
+                self.tree[ptr0 as usize] = 0;
+                self.tree[ptr1 as usize] = 0;
+                return;
+            }
+            depth -= 1;
+
+            let pair = self.cyclic_pos - delta
+                + (if delta > self.cyclic_pos {
+                    self.cyclic_size
+                } else {
+                    0
+                });
+            let pair = sh_left(pair);
+            let mut len = len0.min(len1);
+
+            if encoder.get_byte(len, delta) == encoder.get_byte(len, 0) {
+                len += 1;
+                while len < match_len_limit
+                    && encoder.get_byte(len, delta) == encoder.get_byte(len, 0)
+                {
+                    len += 1;
+                }
+                if len > len_best {
+                    len_best = len;
+                    let count = matches.count as usize;
+                    matches.len[count] = len as _;
+                    matches.dist[count] = delta - 1;
+                    matches.count += 1;
+
+                    if len >= nice_len_limit {
+                        self.tree[ptr1 as usize] = self.tree[pair as usize];
+                        self.tree[ptr0 as usize] = self.tree[pair as usize + 1];
+                        return;
+                    }
+                }
+            }
+
+            if encoder.get_byte(len, delta) < encoder.get_byte(len, 0) {
+                self.tree[ptr1 as usize] = current_match;
+                ptr1 = pair + 1;
+                current_match = self.tree[ptr1 as usize];
+                len1 = len;
+            } else {
+                self.tree[ptr0 as usize] = current_match;
+                ptr0 = pair;
+                current_match = self.tree[ptr0 as usize];
+                len0 = len;
+            }
+        }
+    }
+
+    fn skip(&mut self, encoder: &mut super::LZEncoderData, mut len: usize) {
+        while len > 0 {
+            len -= 1;
+            let mut nice_len_limit = encoder.nice_len as i64;
+            let avail = self.move_pos(encoder);
+
+            if avail < nice_len_limit {
+                if avail == 0 {
+                    continue;
+                }
+                nice_len_limit = avail;
+            }
+
+            let hash2_value = hash2(encoder.buf_mut());
+            let current_match = self.hash2_table[hash2_value];
+            self.hash2_table[hash2_value] = self.lz_pos;
+
+            self.skip(encoder, nice_len_limit, current_match);
+        }
+    }
+
+    fn reset(&mut self) {
+        LZEncoder::normalize(&mut self.hash2_table, self.lz_pos);
+        LZEncoder::normalize(&mut self.tree, self.lz_pos);
+        self.cyclic_pos = -1;
+        self.lz_pos = self.cyclic_size;
+    }
+}
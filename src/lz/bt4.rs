@@ -34,7 +34,7 @@ impl BT4 {
     }
 
     pub fn get_mem_usage(dict_size: u64) -> u64 {
-        Hash234::get_mem_usage(dict_size) + dict_size / (1024 / 8) + 10
+        Hash234::get_mem_usage(dict_size) + dict_size / (1024 / 16) + 10
     }
 
     fn move_pos(&mut self, encoder: &mut super::LZEncoderData) -> i64 {
@@ -304,4 +304,11 @@ impl MatchFind for BT4 {
             self.skip(encoder, nice_len_limit, current_match);
         }
     }
+
+    fn reset(&mut self) {
+        self.hash.normalize(self.lz_pos);
+        LZEncoder::normalize(&mut self.tree, self.lz_pos);
+        self.cyclic_pos = -1;
+        self.lz_pos = self.cyclic_size;
+    }
 }
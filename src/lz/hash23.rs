@@ -0,0 +1,72 @@
+use super::hash234::CRC_TABLE;
+use super::lz_encoder::LZEncoder;
+use crate::vec;
+
+const HASH2_SIZE: u64 = 1 << 10;
+const HASH2_MASK: u64 = HASH2_SIZE - 1;
+
+/// Hash tables of the first two and three bytes, used by the HC3 and BT3 match finders.
+pub struct Hash23 {
+    hash3_mask: u64,
+    hash2_table: crate::Vec<i64>,
+    hash3_table: crate::Vec<i64>,
+    hash2_value: i64,
+    hash3_value: i64,
+}
+
+impl Hash23 {
+    fn get_hash3_size(dict_size: u64) -> u64 {
+        let mut h = dict_size - 1;
+        h |= h >> 1;
+        h |= h >> 2;
+        h |= h >> 4;
+        h |= h >> 8;
+        h >>= 1;
+        h |= 0xffff;
+        // Three bytes can't have more than 2^24 different hashes.
+        if h > (1 << 24) {
+            h = (1 << 24) - 1;
+        }
+        h + 1
+    }
+
+    pub fn get_mem_usage(dict_size: u64) -> u64 {
+        (HASH2_SIZE + Self::get_hash3_size(dict_size)) / (1024 / 8) + 4
+    }
+
+    pub fn new(dict_size: u64) -> Self {
+        let hash3_size = Self::get_hash3_size(dict_size);
+        Self {
+            hash3_mask: hash3_size - 1,
+            hash2_table: vec![0; HASH2_SIZE as _],
+            hash3_table: vec![0; hash3_size as _],
+            hash2_value: 0,
+            hash3_value: 0,
+        }
+    }
+
+    pub fn calc_hashes(&mut self, buf: &[u8]) {
+        let tmp = CRC_TABLE[buf[0] as usize] ^ (buf[1] as u64);
+        self.hash2_value = (tmp & HASH2_MASK) as i64;
+        let tmp = tmp ^ ((buf[2] as u64) << 8);
+        self.hash3_value = (tmp & self.hash3_mask) as i64;
+    }
+
+    pub fn get_hash2_pos(&self) -> i64 {
+        self.hash2_table[self.hash2_value as usize]
+    }
+
+    pub fn get_hash3_pos(&self) -> i64 {
+        self.hash3_table[self.hash3_value as usize]
+    }
+
+    pub fn update_tables(&mut self, pos: i64) {
+        self.hash2_table[self.hash2_value as usize] = pos;
+        self.hash3_table[self.hash3_value as usize] = pos;
+    }
+
+    pub fn normalize(&mut self, offset: i64) {
+        LZEncoder::normalize(&mut self.hash2_table, offset);
+        LZEncoder::normalize(&mut self.hash3_table, offset);
+    }
+}
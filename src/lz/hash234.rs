@@ -46,7 +46,7 @@ impl Hash234 {
     }
 
     pub fn get_mem_usage(dict_size: u64) -> u64 {
-        (HASH2_MASK + HASH2_SIZE + Self::get_hash4_size(dict_size)) / (1024 / 4) + 4
+        (HASH2_SIZE + HASH3_SIZE + Self::get_hash4_size(dict_size)) / (1024 / 8) + 4
     }
 
     pub fn new(dict_size: u64) -> Self {
@@ -101,7 +101,7 @@ impl Hash234 {
     }
 }
 
-const CRC_TABLE: &[u64] = &[
+pub(super) const CRC_TABLE: &[u64] = &[
     0x0, 0x77073096, 0xee0e612c, 0x990951ba, 0x76dc419, 0x706af48f, 0xe963a535, 0x9e6495a3,
     0xedb8832, 0x79dcb8a4, 0xe0d5e91e, 0x97d2d988, 0x9b64c2b, 0x7eb17cbd, 0xe7b82d07, 0x90bf1d91,
     0x1db71064, 0x6ab020f2, 0xf3b97148, 0x84be41de, 0x1adad47d, 0x6ddde4eb, 0xf4d4b551, 0x83d385c7,
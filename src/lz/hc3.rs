@@ -0,0 +1,167 @@
+use super::{
+    hash23::Hash23,
+    lz_encoder::{LZEncoder, MatchFind, Matches},
+    LZEncoderData,
+};
+
+use crate::vec;
+
+/// Hash chain match finder that hashes three bytes. It finds matches of three bytes that
+/// HC4 misses, at the cost of longer chains.
+pub struct HC3 {
+    hash: Hash23,
+    chain: crate::Vec<i64>,
+    depth_limit: i64,
+    cyclic_size: i64,
+    cyclic_pos: i64,
+    lz_pos: i64,
+}
+
+impl HC3 {
+    pub fn get_mem_usage(dict_size: u64) -> u64 {
+        Hash23::get_mem_usage(dict_size) + dict_size / (1024 / 8) + 10
+    }
+
+    pub fn new(dict_size: u64, nice_len: u64, depth_limit: i64) -> Self {
+        Self {
+            hash: Hash23::new(dict_size),
+            chain: vec![0; dict_size as usize + 1],
+            depth_limit: if depth_limit > 0 {
+                depth_limit
+            } else {
+                4 + nice_len as i64 / 4
+            },
+            cyclic_size: dict_size as i64 + 1,
+            cyclic_pos: -1,
+            lz_pos: dict_size as i64 + 1,
+        }
+    }
+
+    fn move_pos(&mut self, encoder: &mut LZEncoderData) -> i64 {
+        let avail = encoder.move_pos(3, 3);
+        if avail != 0 {
+            self.lz_pos += 1;
+            if self.lz_pos == 0x7fffffff {
+                let norm_offset = 0x7fffffff - self.cyclic_size;
+                self.hash.normalize(norm_offset);
+                LZEncoder::normalize(&mut self.chain[..self.cyclic_size as usize], norm_offset);
+                self.lz_pos = self.lz_pos.wrapping_sub(norm_offset);
+            }
+
+            self.cyclic_pos += 1;
+            if self.cyclic_pos == self.cyclic_size {
+                self.cyclic_pos = 0;
+            }
+        }
+
+        avail
+    }
+}
+
+impl MatchFind for HC3 {
+    fn find_matches(&mut self, encoder: &mut LZEncoderData, matches: &mut Matches) {
+        matches.count = 0;
+        let mut match_len_limit = encoder.match_len_max as i64;
+        let mut nice_len_limit = encoder.nice_len as i64;
+        let avail = self.move_pos(encoder);
+
+        if avail < match_len_limit {
+            if avail == 0 {
+                return;
+            }
+            match_len_limit = avail;
+            if nice_len_limit > avail {
+                nice_len_limit = avail;
+            }
+        }
+        self.hash.calc_hashes(encoder.buf_mut());
+        let delta2 = self.lz_pos.wrapping_sub(self.hash.get_hash2_pos());
+        let mut current_match = self.hash.get_hash3_pos();
+        self.hash.update_tables(self.lz_pos);
+        self.chain[self.cyclic_pos as usize] = current_match;
+        let mut len_best = 0;
+
+        // The hash of the first two bytes guarantees that the second byte matches if the
+        // first one does.
+        if delta2 < self.cyclic_size
+            && encoder.get_byte_backward(delta2) == encoder.get_current_byte()
+        {
+            len_best = 2;
+            while len_best < match_len_limit
+                && encoder.get_byte(len_best, delta2) == encoder.get_byte(len_best, 0)
+            {
+                len_best += 1;
+            }
+            matches.len[0] = len_best as u64;
+            matches.dist[0] = delta2 - 1;
+            matches.count = 1;
+
+            if len_best >= nice_len_limit {
+                return;
+            }
+        }
+
+        if len_best < 2 {
+            len_best = 2;
+        }
+
+        let mut depth = self.depth_limit;
+        loop {
+            let delta = self.lz_pos - current_match;
+            if depth == 0 || delta >= self.cyclic_size {
+                return;
+            }
+            depth -= 1;
+            let i = self.cyclic_pos - delta
+                + if delta > self.cyclic_pos {
+                    self.cyclic_size
+                } else {
+                    0
+                };
+            current_match = self.chain[i as usize];
+
+            if encoder.get_byte(len_best, delta) == encoder.get_byte(len_best, 0)
+                && encoder.get_byte(0, delta) == encoder.get_current_byte()
+            {
+                let mut len = 1;
+                while len < match_len_limit
+                    && encoder.get_byte(len, delta) == encoder.get_byte(len, 0)
+                {
+                    len += 1;
+                }
+
+                // Use the match if and only if it is better than the longest
+                // match found so far.
+                if len > len_best {
+                    len_best = len;
+                    let count = matches.count as usize;
+                    matches.len[count] = len as _;
+                    matches.dist[count] = delta - 1;
+                    matches.count += 1;
+
+                    if len >= nice_len_limit {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    fn skip(&mut self, encoder: &mut LZEncoderData, mut len: usize) {
+        while len > 0 {
+            len -= 1;
+            if self.move_pos(encoder) != 0 {
+                self.hash.calc_hashes(encoder.buf_mut());
+                self.chain[self.cyclic_pos as usize] = self.hash.get_hash3_pos();
+                self.hash.update_tables(self.lz_pos);
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.hash.normalize(self.lz_pos);
+        LZEncoder::normalize(&mut self.chain, self.lz_pos);
+        self.cyclic_pos = -1;
+        self.lz_pos = self.cyclic_size;
+    }
+}
@@ -18,7 +18,7 @@ pub struct HC4 {
 
 impl HC4 {
     pub fn get_mem_usage(dict_size: u64) -> u64 {
-        Hash234::get_mem_usage(dict_size) + dict_size / (1024 / 4) + 10
+        Hash234::get_mem_usage(dict_size) + dict_size / (1024 / 8) + 10
     }
 
     pub fn new(dict_size: u64, nice_len: u64, depth_limit: i64) -> Self {
@@ -193,4 +193,11 @@ impl MatchFind for HC4 {
             }
         }
     }
+
+    fn reset(&mut self) {
+        self.hash.normalize(self.lz_pos);
+        LZEncoder::normalize(&mut self.chain, self.lz_pos);
+        self.cyclic_pos = -1;
+        self.lz_pos = self.cyclic_size;
+    }
 }
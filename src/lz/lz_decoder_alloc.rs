@@ -102,7 +102,7 @@ impl LZDecoder {
                 let buf_ptr = self.buf.as_mut_ptr();
                 let src = buf_ptr.add(back);
                 let dest = buf_ptr.add(self.pos);
-                core::ptr::copy_nonoverlapping(src, dest, copy_size);
+                core::ptr::copy(src, dest, copy_size);
             }
             self.pos += copy_size;
             back = 0;
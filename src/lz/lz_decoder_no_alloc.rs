@@ -128,7 +128,7 @@ impl<const DICT_SIZE: usize> LZDecoder<DICT_SIZE> {
                 let buf_ptr = self.buf.as_mut_ptr();
                 let src = buf_ptr.add(back);
                 let dest = buf_ptr.add(self.pos);
-                core::ptr::copy_nonoverlapping(src, dest, copy_size);
+                core::ptr::copy(src, dest, copy_size);
             }
             self.pos += copy_size;
             back = 0;
@@ -1,35 +1,62 @@
 use crate::{io::Write, vec};
 use core::ops::Deref;
 
-use super::{bt4::BT4, hc4::HC4};
+use super::{bt2::BT2, bt3::BT3, bt4::BT4, hc3::HC3, hc4::HC4};
 
 pub trait MatchFind {
     fn find_matches(&mut self, encoder: &mut LZEncoderData, matches: &mut Matches);
     fn skip(&mut self, encoder: &mut LZEncoderData, len: usize);
+    /// Forgets all positions, so that no match is found before the next one.
+    fn reset(&mut self);
 }
 pub enum MatchFinders {
+    HC3(HC3),
     HC4(HC4),
+    BT2(BT2),
+    BT3(BT3),
     BT4(BT4),
 }
 
 impl MatchFind for MatchFinders {
     fn find_matches(&mut self, encoder: &mut LZEncoderData, matches: &mut Matches) {
         match self {
+            MatchFinders::HC3(m) => m.find_matches(encoder, matches),
             MatchFinders::HC4(m) => m.find_matches(encoder, matches),
+            MatchFinders::BT2(m) => m.find_matches(encoder, matches),
+            MatchFinders::BT3(m) => m.find_matches(encoder, matches),
             MatchFinders::BT4(m) => m.find_matches(encoder, matches),
         }
     }
 
     fn skip(&mut self, encoder: &mut LZEncoderData, len: usize) {
         match self {
+            MatchFinders::HC3(m) => m.skip(encoder, len),
             MatchFinders::HC4(m) => m.skip(encoder, len),
+            MatchFinders::BT2(m) => m.skip(encoder, len),
+            MatchFinders::BT3(m) => m.skip(encoder, len),
             MatchFinders::BT4(m) => m.skip(encoder, len),
         }
     }
+
+    fn reset(&mut self) {
+        match self {
+            MatchFinders::HC3(m) => m.reset(),
+            MatchFinders::HC4(m) => m.reset(),
+            MatchFinders::BT2(m) => m.reset(),
+            MatchFinders::BT3(m) => m.reset(),
+            MatchFinders::BT4(m) => m.reset(),
+        }
+    }
 }
+/// Match finder of the encoder. The hash chains (HC) are faster, the binary trees (BT) find
+/// better matches. The number is how many bytes are hashed; the shorter hashes find more
+/// short matches, which helps with text and data with few different byte values.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MFType {
+    HC3,
     HC4,
+    BT2,
+    BT3,
     BT4,
 }
 
@@ -42,7 +69,10 @@ impl MFType {
     #[inline]
     fn get_memory_usage(self, dict_size: u64) -> u64 {
         match self {
+            MFType::HC3 => HC3::get_mem_usage(dict_size),
             MFType::HC4 => HC4::get_mem_usage(dict_size),
+            MFType::BT2 => BT2::get_mem_usage(dict_size),
+            MFType::BT3 => BT3::get_mem_usage(dict_size),
             MFType::BT4 => BT4::get_mem_usage(dict_size),
         }
     }
@@ -95,7 +125,26 @@ impl LZEncoder {
             extra_size_before,
             extra_size_after,
             match_len_max,
-        ) + mf.get_memory_usage(dict_size)
+        ) / 1024
+            + mf.get_memory_usage(dict_size)
+    }
+
+    pub fn new_hc3(
+        dict_size: u64,
+        extra_size_before: u64,
+        extra_size_after: u64,
+        nice_len: u64,
+        match_len_max: u64,
+        depth_limit: i64,
+    ) -> Self {
+        Self::new(
+            dict_size,
+            extra_size_before,
+            extra_size_after,
+            nice_len,
+            match_len_max,
+            MatchFinders::HC3(HC3::new(dict_size, nice_len, depth_limit)),
+        )
     }
 
     pub fn new_hc4(
@@ -116,6 +165,42 @@ impl LZEncoder {
         )
     }
 
+    pub fn new_bt2(
+        dict_size: u64,
+        extra_size_before: u64,
+        extra_size_after: u64,
+        nice_len: u64,
+        match_len_max: u64,
+        depth_limit: i64,
+    ) -> Self {
+        Self::new(
+            dict_size,
+            extra_size_before,
+            extra_size_after,
+            nice_len,
+            match_len_max,
+            MatchFinders::BT2(BT2::new(dict_size, nice_len, depth_limit)),
+        )
+    }
+
+    pub fn new_bt3(
+        dict_size: u64,
+        extra_size_before: u64,
+        extra_size_after: u64,
+        nice_len: u64,
+        match_len_max: u64,
+        depth_limit: i64,
+    ) -> Self {
+        Self::new(
+            dict_size,
+            extra_size_before,
+            extra_size_after,
+            nice_len,
+            match_len_max,
+            MatchFinders::BT3(BT3::new(dict_size, nice_len, depth_limit)),
+        )
+    }
+
     pub fn new_bt4(
         dict_size: u64,
         extra_size_before: u64,
@@ -133,6 +218,7 @@ impl LZEncoder {
             MatchFinders::BT4(BT4::new(dict_size, nice_len, depth_limit)),
         )
     }
+
     fn new(
         dict_size: u64,
         extra_size_before: u64,
@@ -198,6 +284,16 @@ impl LZEncoder {
             .set_preset_dict(dict_size, preset_dict, &mut self.match_finder)
     }
 
+    /// Empties the window and the match finder, as if the encoder was new. Everything in the
+    /// window must have been encoded.
+    pub fn reset(&mut self) {
+        self.data.read_pos = -1;
+        self.data.read_limit = -1;
+        self.data.write_pos = 0;
+        self.data.pending_size = 0;
+        self.match_finder.reset();
+    }
+
     pub fn set_finishing(&mut self) {
         self.data.set_finishing(&mut self.match_finder)
     }
@@ -243,14 +339,8 @@ impl LZEncoderData {
         assert!(move_offset >= 0);
         let move_size = move_size as usize;
         let offset = move_offset as usize;
-        let end = offset + move_size;
-        unsafe {
-            core::ptr::copy_nonoverlapping(
-                self.buf[offset..end].as_ptr(),
-                self.buf[0..].as_mut_ptr(),
-                move_size,
-            );
-        }
+        // The kept data can be longer than the offset it's moved by.
+        self.buf.copy_within(offset..offset + move_size, 0);
         self.read_pos -= move_offset;
         self.read_limit -= move_offset;
         self.write_pos -= move_offset;
@@ -1,8 +1,16 @@
 #[cfg(feature = "alloc")]
+mod bt2;
+#[cfg(feature = "alloc")]
+mod bt3;
+#[cfg(feature = "alloc")]
 mod bt4;
 #[cfg(feature = "alloc")]
+mod hash23;
+#[cfg(feature = "alloc")]
 mod hash234;
 #[cfg(feature = "alloc")]
+mod hc3;
+#[cfg(feature = "alloc")]
 mod hc4;
 #[cfg_attr(feature = "alloc", path = "lz_decoder_alloc.rs")]
 #[cfg_attr(not(feature = "alloc"), path = "lz_decoder_no_alloc.rs")]
@@ -0,0 +1,190 @@
+use super::{decode_header, Trailer, HEADER_SIZE, LC, LP, PB, TRAILER_SIZE};
+use crate::check::{Check, Crc32};
+use crate::counting_reader::CountingReader;
+use crate::io::{error, Chain, Cursor, ErrorKind, Read, Result};
+use crate::{lzma_get_memory_usage, LZMAReader};
+
+/// Size of the first bytes of the LZMA data, which initialize the range decoder.
+const RC_INIT_SIZE: usize = 5;
+
+/// Decodes the LZMA data of a member. Its first bytes are read ahead and checked, so the inner
+/// reader is only moved into the decoder once creating it can't fail anymore.
+type MemberReader<R> = LZMAReader<CountingReader<Chain<Cursor<[u8; RC_INIT_SIZE]>, R>>>;
+
+/// Decompresses a lzip (.lz) file.
+///
+/// Every member is decoded with [LZMAReader] and validated against its trailer:
+/// the CRC32 and size of the uncompressed data and the size of the member.
+/// Members are decoded until the end of the input; any other trailing data is reported as an error.
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::{LzipOptions, LzipReader, LzipWriter};
+/// let mut writer = LzipWriter::new(Vec::new(), &LzipOptions::with_preset(6)).unwrap();
+/// writer.write_all(b"Hello, world!").unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.into_inner();
+///
+/// let mut reader = LzipReader::new(&compressed[..]).unwrap();
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], b"Hello, world!");
+/// ```
+pub struct LzipReader<R> {
+    inner: Option<R>,
+    member: Option<MemberReader<R>>,
+    /// Dictionary size of the next member, whose header has already been read.
+    dict_size: u32,
+    crc32: Crc32,
+    data_size: u64,
+    mem_limit_kb: u64,
+    end_reached: bool,
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<R: Read> LzipReader<R> {
+    /// Creates a new lzip decompressor without a memory usage limit.
+    /// The header of the first member is read and validated immediately.
+    pub fn new(inner: R) -> Result<Self> {
+        Self::new_mem_limit(inner, u64::MAX)
+    }
+
+    /// Creates a new lzip decompressor with a memory usage limit.
+    /// - [mem_limit_kb] - memory usage limit in kibibytes (KiB). u64::MAX means no limit.
+    pub fn new_mem_limit(mut inner: R, mem_limit_kb: u64) -> Result<Self> {
+        let mut buf = [0u8; HEADER_SIZE];
+        inner.read_exact(&mut buf)?;
+        let dict_size = decode_header(&buf)?;
+        Ok(Self {
+            inner: Some(inner),
+            member: None,
+            dict_size,
+            crc32: Crc32::new(),
+            data_size: 0,
+            mem_limit_kb,
+            end_reached: false,
+            error: None,
+        })
+    }
+
+    /// Returns the underlying reader, also after an error.
+    pub fn into_inner(self) -> R {
+        match self.member {
+            Some(member) => member.into_inner().inner.into_inner().1,
+            None => self.inner.unwrap(),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut R {
+        self.inner.as_mut().unwrap()
+    }
+
+    fn start_member(&mut self) -> Result<()> {
+        let need_mem = lzma_get_memory_usage(self.dict_size as u64, LC, LP)?;
+        if self.mem_limit_kb < need_mem {
+            return error!(
+                ErrorKind::OutOfMemory,
+                format!(
+                    "{}kb memory needed,but limit was {}kb",
+                    need_mem, self.mem_limit_kb
+                )
+            );
+        }
+        let mut init = [0u8; RC_INIT_SIZE];
+        self.inner_mut().read_exact(&mut init)?;
+        if init[0] != 0x00 {
+            return error!(ErrorKind::InvalidData, "lzip member data is corrupt");
+        }
+        let inner = Cursor::new(init).chain(self.inner.take().unwrap());
+        self.member = Some(LZMAReader::new(
+            CountingReader::new(inner),
+            u64::MAX,
+            LC,
+            LP,
+            PB,
+            self.dict_size as u64,
+            None,
+        )?);
+        self.crc32 = Crc32::new();
+        self.data_size = 0;
+        Ok(())
+    }
+
+    /// Validates the trailer of the current member and reads the header of the next one.
+    fn finish_member(&mut self) -> Result<()> {
+        let member = self.member.take().unwrap();
+        let CountingReader { inner, count } = member.into_inner();
+        let inner = self.inner.insert(inner.into_inner().1);
+        let mut buf = [0u8; TRAILER_SIZE];
+        inner.read_exact(&mut buf)?;
+        let trailer = Trailer::decode(&buf);
+        if trailer.crc32 != self.crc32.value() {
+            return error!(
+                ErrorKind::InvalidData,
+                "Integrity check (CRC32) does not match"
+            );
+        }
+        if trailer.data_size != self.data_size
+            || trailer.member_size != (HEADER_SIZE + TRAILER_SIZE) as u64 + count
+        {
+            return error!(
+                ErrorKind::InvalidData,
+                "lzip member sizes do not match the trailer"
+            );
+        }
+
+        let mut header = [0u8; HEADER_SIZE];
+        let mut size = 0;
+        while size < HEADER_SIZE {
+            match inner.read(&mut header[size..]) {
+                Ok(0) => break,
+                Ok(n) => size += n,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        match size {
+            0 => self.end_reached = true,
+            HEADER_SIZE => self.dict_size = decode_header(&header)?,
+            _ => return error!(ErrorKind::InvalidData, "Trailing data after lzip member"),
+        }
+        Ok(())
+    }
+
+    fn read_decode(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while !self.end_reached {
+            match self.member.as_mut() {
+                None => self.start_member()?,
+                Some(member) => {
+                    let size = member.read(buf)?;
+                    if size > 0 {
+                        self.crc32.update(&buf[..size]);
+                        self.data_size += size as u64;
+                        return Ok(size);
+                    }
+                    self.finish_member()?;
+                }
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl<R: Read> Read for LzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = &self.error {
+            return error!(e.0, e.1.clone());
+        }
+        match self.read_decode(buf) {
+            Ok(size) => Ok(size),
+            Err(e) => {
+                self.error = Some((e.kind(), e.to_string()));
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! Building blocks of the lzip (.lz) file format.
+//!
+//! See the [lzip manual](https://www.nongnu.org/lzip/manual/lzip_manual.html#File-format).
+
+mod lzip_reader;
+pub use lzip_reader::*;
+
+use crate::io::{error, ErrorKind, Result};
+
+pub const MAGIC: [u8; 4] = *b"LZIP";
+pub const VERSION: u8 = 1;
+pub const HEADER_SIZE: usize = 6;
+pub const TRAILER_SIZE: usize = 20;
+pub const DICT_SIZE_MIN: u32 = 1 << 12;
+pub const DICT_SIZE_MAX: u32 = 1 << 29;
+/// lzip always uses these literal context, literal position and position bits.
+pub const LC: u64 = 3;
+pub const LP: u64 = 0;
+pub const PB: u64 = 2;
+
+/// Decodes the dictionary size byte of the header: the low 5 bits are the base 2
+/// logarithm of a power of two, from which the high 3 bits subtract that many sixteenths.
+pub fn decode_dict_size(coded: u8) -> Result<u32> {
+    let exp = coded & 0x1F;
+    if !(12..=29).contains(&exp) {
+        return error!(ErrorKind::InvalidData, "Invalid lzip dictionary size");
+    }
+    let base = 1u32 << exp;
+    let dict_size = base - (base / 16) * (coded >> 5) as u32;
+    if dict_size < DICT_SIZE_MIN {
+        return error!(ErrorKind::InvalidData, "Invalid lzip dictionary size");
+    }
+    Ok(dict_size)
+}
+
+/// Encodes a dictionary size into the header byte, rounding it up to the next size
+/// that the byte can represent.
+pub fn encode_dict_size(dict_size: u64) -> Result<u8> {
+    if dict_size > DICT_SIZE_MAX as u64 {
+        return error!(
+            ErrorKind::InvalidInput,
+            "Dictionary size is too large for lzip"
+        );
+    }
+    let dict_size = dict_size.max(DICT_SIZE_MIN as u64);
+    let exp = 64 - (dict_size - 1).leading_zeros();
+    let base = 1u64 << exp;
+    let mut fraction = 7;
+    while base - (base / 16) * fraction < dict_size {
+        fraction -= 1;
+    }
+    Ok(exp as u8 | (fraction as u8) << 5)
+}
+
+/// Decodes the member header, returning the dictionary size.
+pub fn decode_header(buf: &[u8; HEADER_SIZE]) -> Result<u32> {
+    if buf[..4] != MAGIC {
+        return error!(ErrorKind::InvalidData, "Invalid lzip magic");
+    }
+    if buf[4] != VERSION {
+        return error!(
+            ErrorKind::Unsupported,
+            format!("Unsupported lzip version {}", buf[4])
+        );
+    }
+    decode_dict_size(buf[5])
+}
+
+pub fn encode_header(coded_dict_size: u8) -> [u8; HEADER_SIZE] {
+    let mut buf = [0u8; HEADER_SIZE];
+    buf[..4].copy_from_slice(&MAGIC);
+    buf[4] = VERSION;
+    buf[5] = coded_dict_size;
+    buf
+}
+
+/// The trailer stored after the LZMA data of each member.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Trailer {
+    /// CRC32 of the uncompressed data.
+    pub crc32: u32,
+    /// Size of the uncompressed data.
+    pub data_size: u64,
+    /// Size of the whole member, including header and trailer.
+    pub member_size: u64,
+}
+
+impl Trailer {
+    pub fn decode(buf: &[u8; TRAILER_SIZE]) -> Self {
+        let mut crc32 = [0u8; 4];
+        let mut data_size = [0u8; 8];
+        let mut member_size = [0u8; 8];
+        crc32.copy_from_slice(&buf[..4]);
+        data_size.copy_from_slice(&buf[4..12]);
+        member_size.copy_from_slice(&buf[12..]);
+        Self {
+            crc32: u32::from_le_bytes(crc32),
+            data_size: u64::from_le_bytes(data_size),
+            member_size: u64::from_le_bytes(member_size),
+        }
+    }
+
+    pub fn encode(&self) -> [u8; TRAILER_SIZE] {
+        let mut buf = [0u8; TRAILER_SIZE];
+        buf[..4].copy_from_slice(&self.crc32.to_le_bytes());
+        buf[4..12].copy_from_slice(&self.data_size.to_le_bytes());
+        buf[12..].copy_from_slice(&self.member_size.to_le_bytes());
+        buf
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::VecDeque;
+use std::io::{Chain, Cursor};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::io::{error, ErrorKind, Read, Result};
+use crate::LZMA2Reader;
+
+/// Chunks from one dictionary reset up to the next one, which can be decoded on their own.
+struct Segment {
+    /// The chunks followed by an end marker.
+    data: Vec<u8>,
+    uncompressed_size: u64,
+    /// The first segment is decoded with the preset dictionary and doesn't need to start with
+    /// a dictionary reset.
+    is_first: bool,
+}
+
+struct Job {
+    segment: Segment,
+    result: Sender<Result<Vec<u8>>>,
+}
+
+/// Decompresses a raw LZMA2 stream on multiple threads.
+///
+/// A chunk with the control byte `0x01` or `>= 0xE0` resets the dictionary, so it and the
+/// chunks up to the next reset don't depend on earlier data. The reader scans the chunk
+/// headers for these resets and decodes the segments between them in parallel, like the
+/// blocks written by [LZMA2MTWriter](crate::LZMA2MTWriter). The compressed data of a segment
+/// is buffered until the next reset is found, and up to two segments per thread are decoded
+/// ahead of the reads.
+///
+/// The first segment is decoded on the calling thread while it's read. A segment can only be
+/// buffered up to [set_segment_size_max](Self::set_segment_size_max). Once a segment grows
+/// larger, like the single segment of a long stream written by
+/// [LZMA2Writer](crate::LZMA2Writer), the rest of the stream is decoded on the calling thread
+/// as well. A stream with a single segment is decoded without starting any threads.
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::{LZMA2MTOptions, LZMA2MTReader, LZMA2MTWriter, LZMA2Options};
+/// let mut options = LZMA2MTOptions::new(LZMA2Options::with_preset(1), 4);
+/// options.block_size = 64 << 10;
+/// let data: Vec<u8> = (0..1000000u32).flat_map(|i| (i % 1000).to_le_bytes()).collect();
+/// let mut writer = LZMA2MTWriter::new(Vec::new(), &options).unwrap();
+/// writer.write_all(&data).unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.into_inner();
+///
+/// let mut reader = LZMA2MTReader::new(&compressed[..], options.lzma2.dict_size, None, 4);
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(decompressed, data);
+/// ```
+pub struct LZMA2MTReader<R> {
+    inner: Option<R>,
+    dict_size: u64,
+    preset_dict: Option<Arc<[u8]>>,
+    threads: usize,
+    segment_size_max: usize,
+    segment: Option<Segment>,
+    /// Set at the end of the stream, or when `segment` grew larger than `segment_size_max`.
+    input_end: bool,
+    /// Decodes the first segment, which is read before the output of the threads.
+    first: Option<LZMA2Reader<Cursor<Vec<u8>>>>,
+    /// Decodes the rest of the stream, starting with the segment that grew too large.
+    sequential: Option<LZMA2Reader<Chain<Cursor<Vec<u8>>, R>>>,
+    pending: VecDeque<Receiver<Result<Vec<u8>>>>,
+    jobs: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+    output: Vec<u8>,
+    output_pos: usize,
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<R> LZMA2MTReader<R> {
+    pub fn into_inner(self) -> R {
+        match self.sequential {
+            Some(reader) => reader.into_inner().into_inner().1,
+            None => self.inner.unwrap(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        match &self.sequential {
+            Some(reader) => reader.get_ref().get_ref().1,
+            None => self.inner.as_ref().unwrap(),
+        }
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        match &mut self.sequential {
+            Some(reader) => reader.get_mut().get_mut().1,
+            None => self.inner.as_mut().unwrap(),
+        }
+    }
+}
+
+impl<R: Read> LZMA2MTReader<R> {
+    /// Create a new multi-threaded LZMA2 reader.
+    /// `inner` is the reader to read compressed data from.
+    /// `dict_size` is the dictionary size in bytes.
+    /// `threads` is the maximum number of decoding threads, at least 1 is used.
+    pub fn new(inner: R, dict_size: u64, preset_dict: Option<&[u8]>, threads: usize) -> Self {
+        Self {
+            inner: Some(inner),
+            dict_size,
+            preset_dict: preset_dict.map(Arc::from),
+            threads: threads.max(1),
+            segment_size_max: usize::try_from(dict_size.saturating_mul(3))
+                .unwrap_or(usize::MAX)
+                .max(1 << 20),
+            segment: Some(Segment {
+                data: Vec::new(),
+                uncompressed_size: 0,
+                is_first: true,
+            }),
+            input_end: false,
+            first: None,
+            sequential: None,
+            pending: VecDeque::new(),
+            jobs: None,
+            workers: Vec::new(),
+            output: Vec::new(),
+            output_pos: 0,
+            error: None,
+        }
+    }
+
+    /// Sets the maximum size of a segment that is buffered to decode it on another thread.
+    /// Both the compressed data and the uncompressed size in the chunk headers are limited.
+    /// The default is three times the dictionary size, but at least 1 MiB, which fits the
+    /// blocks of [LZMA2MTWriter](crate::LZMA2MTWriter) with the default options. Up to
+    /// `2 * threads + 1` segments are buffered at a time.
+    pub fn set_segment_size_max(&mut self, size: usize) {
+        self.segment_size_max = size;
+    }
+
+    fn inner_mut(&mut self) -> &mut R {
+        self.inner.as_mut().unwrap()
+    }
+
+    /// Reads the chunks up to the next dictionary reset or the end of the stream, and
+    /// returns the chunks before the reset. Returns `None` at the end of the stream, or when
+    /// the current segment grew too large, which is then left in `self.segment`.
+    fn scan_segment(&mut self) -> Result<Option<Segment>> {
+        while !self.input_end {
+            let mut header = [0u8; 6];
+            self.inner_mut().read_exact(&mut header[..1])?;
+            let control = header[0];
+            let (header_size, uncompressed_size, data_size) = match control {
+                0x00 => {
+                    self.input_end = true;
+                    break;
+                }
+                0x01 | 0x02 => {
+                    self.inner_mut().read_exact(&mut header[1..3])?;
+                    let size = u16::from_be_bytes([header[1], header[2]]) as usize + 1;
+                    (3, size, size)
+                }
+                0x80..=0xFF => {
+                    let header_size = if control >= 0xC0 { 6 } else { 5 };
+                    self.inner_mut().read_exact(&mut header[1..header_size])?;
+                    let uncompressed_size = ((control as usize & 0x1F) << 16)
+                        + u16::from_be_bytes([header[1], header[2]]) as usize
+                        + 1;
+                    let compressed_size = u16::from_be_bytes([header[3], header[4]]) as usize + 1;
+                    (header_size, uncompressed_size, compressed_size)
+                }
+                _ => {
+                    return error!(ErrorKind::InvalidInput, "Corrupted input data (LZMA2:2)");
+                }
+            };
+
+            let mut done = None;
+            let is_dict_reset = control == 0x01 || control >= 0xE0;
+            if is_dict_reset && !self.segment.as_ref().unwrap().data.is_empty() {
+                done = self.segment.replace(Segment {
+                    data: Vec::new(),
+                    uncompressed_size: 0,
+                    is_first: false,
+                });
+            }
+            let segment = self.segment.as_mut().unwrap();
+            segment.data.extend_from_slice(&header[..header_size]);
+            let start = segment.data.len();
+            segment.data.resize(start + data_size, 0);
+            let inner = self.inner.as_mut().unwrap();
+            inner.read_exact(&mut segment.data[start..])?;
+            segment.uncompressed_size += uncompressed_size as u64;
+            if done.is_some() {
+                return Ok(done);
+            }
+            if segment.data.len() > self.segment_size_max
+                || segment.uncompressed_size > self.segment_size_max as u64
+            {
+                self.input_end = true;
+                return Ok(None);
+            }
+        }
+        Ok(self
+            .segment
+            .take()
+            .filter(|segment| !segment.data.is_empty()))
+    }
+
+    fn start_workers(&mut self) {
+        let (jobs, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        self.workers = (0..self.threads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let dict_size = self.dict_size;
+                thread::spawn(move || loop {
+                    let job = match receiver.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    let result = decode_segment(job.segment, dict_size);
+                    // The reader has been dropped if nobody waits for the result.
+                    let _ = job.result.send(result);
+                })
+            })
+            .collect();
+        self.jobs = Some(jobs);
+    }
+
+    fn submit_segment(&mut self, mut segment: Segment) -> Result<()> {
+        if segment.is_first {
+            // Its output isn't buffered, it's decoded while the threads decode the next ones.
+            segment.data.push(0x00);
+            let preset_dict = self.preset_dict.as_deref();
+            let reader = LZMA2Reader::new(Cursor::new(segment.data), self.dict_size, preset_dict);
+            self.first = Some(reader);
+            return Ok(());
+        }
+        if self.jobs.is_none() {
+            self.start_workers();
+        }
+        let (result, receiver) = mpsc::channel();
+        let job = Job { segment, result };
+        if self.jobs.as_ref().unwrap().send(job).is_err() {
+            return error!(ErrorKind::Other, "LZMA2 decompression threads have stopped");
+        }
+        self.pending.push_back(receiver);
+        Ok(())
+    }
+
+    /// Decodes the next segment into the output buffer. Returns false at the end of the stream.
+    fn next_segment(&mut self) -> Result<bool> {
+        while self.pending.len() < self.threads * 2 && !self.input_end {
+            match self.scan_segment()? {
+                Some(segment) => self.submit_segment(segment)?,
+                None => break,
+            }
+        }
+        if self.first.is_some() {
+            return Ok(true);
+        }
+        let receiver = match self.pending.pop_front() {
+            Some(receiver) => receiver,
+            None => {
+                self.jobs = None;
+                for worker in self.workers.drain(..) {
+                    let _ = worker.join();
+                }
+                // Only a segment that grew too large is left.
+                if let Some(segment) = self.segment.take() {
+                    let preset_dict = self.preset_dict.as_deref().filter(|_| segment.is_first);
+                    let inner = Cursor::new(segment.data).chain(self.inner.take().unwrap());
+                    self.sequential = Some(LZMA2Reader::new(inner, self.dict_size, preset_dict));
+                    return Ok(true);
+                }
+                return Ok(false);
+            }
+        };
+        match receiver.recv() {
+            Ok(output) => self.output = output?,
+            Err(_) => return error!(ErrorKind::Other, "LZMA2 decompression thread panicked"),
+        }
+        self.output_pos = 0;
+        Ok(true)
+    }
+
+    fn read_decode(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while self.output_pos == self.output.len() {
+            if let Some(reader) = self.first.as_mut() {
+                match reader.read(buf)? {
+                    0 => self.first = None,
+                    size => return Ok(size),
+                }
+                continue;
+            }
+            if let Some(reader) = self.sequential.as_mut() {
+                return reader.read(buf);
+            }
+            if !self.next_segment()? {
+                return Ok(0);
+            }
+        }
+        let size = buf.len().min(self.output.len() - self.output_pos);
+        buf[..size].copy_from_slice(&self.output[self.output_pos..self.output_pos + size]);
+        self.output_pos += size;
+        Ok(size)
+    }
+}
+
+fn decode_segment(mut segment: Segment, dict_size: u64) -> Result<Vec<u8>> {
+    // Nothing before the start of the segment can be referenced.
+    let dict_size = dict_size.min(segment.uncompressed_size.max(crate::DICT_SIZE_MIN));
+    segment.data.push(0x00);
+    // The sizes in the chunk headers aren't verified yet.
+    let capacity = segment.uncompressed_size.min(segment.data.len() as u64 * 4);
+    let mut output = Vec::with_capacity(capacity as usize);
+    LZMA2Reader::new(&segment.data[..], dict_size, None).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+impl<R: Read> Read for LZMA2MTReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some((kind, msg)) = &self.error {
+            return error!(*kind, msg.clone());
+        }
+        match self.read_decode(buf) {
+            Ok(size) => Ok(size),
+            Err(e) => {
+                self.error = Some((e.kind(), e.to_string()));
+                Err(e)
+            }
+        }
+    }
+}
@@ -12,15 +12,14 @@ use crate::range_dec::RangeSource;
 /// Decompresses a raw LZMA2 stream (no XZ headers).
 /// # Examples
 /// ```
-/// use Read;
+/// use std::io::Read;
 /// use lzma_rust::LZMA2Reader;
 /// use lzma_rust::LZMA2Options;
 /// let compressed = [1, 0, 12, 72, 101, 108, 108, 111, 44, 32, 119, 111, 114, 108, 100, 33, 0];
-/// let mut reader = LZMA2Reader::new(compressed, LZMA2Options::DICT_SIZE_DEFAULT, None);
-/// let mut decompressed = crate::Vec::new();
-/// reader.read_to_end(&mut decompressed);
+/// let mut reader = LZMA2Reader::new(&compressed[..], LZMA2Options::DICT_SIZE_DEFAULT, None);
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
 /// assert_eq!(&decompressed[..], b"Hello, world!");
-///
 /// ```
 pub struct LZMA2Reader<R> {
     inner: R,
@@ -42,6 +41,27 @@ pub fn get_memory_usage(dict_size: u64) -> u64 {
     40 + COMPRESSED_SIZE_MAX / 1024 + get_dict_size(dict_size) / 1024
 }
 
+/// Decodes the dictionary size from an LZMA2 dictionary size property byte, as stored
+/// in the filter properties of .xz and .7z files. Valid values are 0..=40.
+///
+/// # Examples
+/// ```
+/// use lzma_rust::lzma2_dict_size_from_prop;
+/// assert_eq!(lzma2_dict_size_from_prop(0).unwrap(), 4 << 10);
+/// assert_eq!(lzma2_dict_size_from_prop(19).unwrap(), 3 << 20);
+/// assert_eq!(lzma2_dict_size_from_prop(40).unwrap(), u32::MAX as u64);
+/// assert!(lzma2_dict_size_from_prop(41).is_err());
+/// ```
+pub fn dict_size_from_prop(prop: u8) -> crate::io::Result<u64> {
+    if prop > 40 {
+        return error!(ErrorKind::InvalidData, "Invalid LZMA2 dictionary size property");
+    }
+    if prop == 40 {
+        return Ok(u32::MAX as u64);
+    }
+    Ok((2 | (prop as u64 & 1)) << (prop / 2 + 11))
+}
+
 #[inline]
 fn get_dict_size(dict_size: u64) -> u64 {
     (dict_size + 15) & !15
@@ -0,0 +1,91 @@
+use crate::io::{error, ErrorKind, Read, Result, Write};
+#[cfg(feature = "no_std")]
+use embedded_io::Error;
+
+/// The 13 byte header of the legacy .lzma (LZMA-alone) file format:
+/// the properties byte, the dictionary size (u32 LE) and the uncompressed size (u64 LE,
+/// all ones if unknown).
+///
+/// # Examples
+/// ```
+/// use lzma_rust::LZMAHeader;
+/// let header = LZMAHeader::parse(&[93, 0, 0, 128, 0, 13, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+/// assert_eq!((header.lc, header.lp, header.pb), (3, 0, 2));
+/// assert_eq!(header.dict_size, 8 << 20);
+/// assert_eq!(header.uncompressed_size, Some(13));
+/// assert_eq!(header.props(), 93);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LZMAHeader {
+    pub lc: u64,
+    pub lp: u64,
+    pub pb: u64,
+    pub dict_size: u32,
+    /// `None` if the size is unknown and the data ends with an end marker.
+    pub uncompressed_size: Option<u64>,
+}
+
+impl LZMAHeader {
+    pub const SIZE: usize = 13;
+
+    /// Decodes a header, validating the properties byte.
+    pub fn parse(buf: &[u8; Self::SIZE]) -> Result<Self> {
+        let mut props = buf[0];
+        if props > (4 * 5 + 4) * 9 + 8 {
+            return error!(ErrorKind::InvalidData, "Invalid props byte");
+        }
+        let pb = props / (9 * 5);
+        props -= pb * 9 * 5;
+        let lp = props / 9;
+        let lc = props - lp * 9;
+        let dict_size = u32::from_le_bytes([buf[1], buf[2], buf[3], buf[4]]);
+        let mut size = [0u8; 8];
+        size.copy_from_slice(&buf[5..]);
+        let uncompressed_size = match u64::from_le_bytes(size) {
+            u64::MAX => None,
+            size => Some(size),
+        };
+        Ok(Self {
+            lc: lc as u64,
+            lp: lp as u64,
+            pb: pb as u64,
+            dict_size,
+            uncompressed_size,
+        })
+    }
+
+    pub fn read_from<R: Read>(reader: &mut R) -> crate::io::read_exact_result!(R, Self) {
+        let mut buf = [0u8; Self::SIZE];
+        reader.read_exact(&mut buf)?;
+        match Self::parse(&buf) {
+            Ok(header) => Ok(header),
+            Err(e) => error!(
+                crate::io::read_exact_error_kind!(R, e.kind()),
+                e.to_string()
+            ),
+        }
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> crate::io::write_result!(W, ()) {
+        writer.write_all(&self.to_bytes())
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut buf = [0u8; Self::SIZE];
+        buf[0] = self.props();
+        buf[1..5].copy_from_slice(&self.dict_size.to_le_bytes());
+        buf[5..].copy_from_slice(&self.uncompressed_size.unwrap_or(u64::MAX).to_le_bytes());
+        buf
+    }
+
+    /// The properties byte encoding lc, lp and pb.
+    #[inline]
+    pub fn props(&self) -> u8 {
+        ((self.pb * 5 + self.lp) * 9 + self.lc) as u8
+    }
+
+    /// Memory needed to decode the data in kibibytes (KiB), see [LZMAReader::new_mem_limit](crate::LZMAReader::new_mem_limit).
+    pub fn memory_usage(&self) -> u64 {
+        crate::lzma_get_memory_usage(self.dict_size as u64, self.lc, self.lp).unwrap_or(u64::MAX)
+    }
+}
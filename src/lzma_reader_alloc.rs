@@ -6,6 +6,7 @@ use super::decoder::LZMADecoder;
 use super::lz::LZDecoder;
 use super::range_dec::RangeDecoder;
 use super::*;
+use crate::LZMAHeader;
 
 pub fn get_memory_usage_by_props(dict_size: u64, props_byte: u8) -> Result<u64> {
     if dict_size > DICT_SIZE_MAX {
@@ -34,21 +35,16 @@ fn get_dict_size(dict_size: u64) -> Result<u64> {
     Ok((dict_size + 15) & !15)
 }
 
+/// Decompresses the legacy .lzma file format or a raw LZMA stream.
+///
 /// # Examples
 /// ```
 /// use std::io::Read;
 /// use lzma_rust::LZMAReader;
 /// let compressed = [93, 0, 0, 128, 0, 255, 255, 255, 255, 255, 255, 255, 255, 0, 36, 25, 73, 152, 111, 22, 2, 140, 232, 230, 91, 177, 71, 198, 206, 183, 99, 255, 255, 60, 172, 0, 0];
-/// let mut reader = LZMAReader::new(&compressed[..]).unwrap();
-/// let mut buf = [0; 1024];
-/// let mut out = crate::Vec::new();
-/// loop {
-///    let n = reader.read(&mut buf).unwrap();
-///   if n == 0 {
-///      break;
-///   }
-///   out.extend_from_slice(&buf[..n]);
-/// }
+/// let mut reader = LZMAReader::new_mem_limit(&compressed[..], u64::MAX, None).unwrap();
+/// let mut out = Vec::new();
+/// reader.read_to_end(&mut out).unwrap();
 /// assert_eq!(out, b"Hello, world!");
 /// ```
 pub struct LZMAReader<R> {
@@ -60,9 +56,15 @@ pub struct LZMAReader<R> {
     remaining_size: u64,
 }
 
-impl<R> Drop for LZMAReader<R> {
-    fn drop(&mut self) {
-        // self.reader.clone().release();
+impl<R> LZMAReader<R> {
+    /// Returns the underlying reader. After the end of the data has been reached
+    /// it is positioned right after the last byte of the compressed data.
+    pub fn into_inner(self) -> R {
+        self.rc.into_inner()
+    }
+
+    pub fn get_ref(&self) -> &R {
+        self.rc.get_ref()
     }
 }
 
@@ -176,14 +178,8 @@ impl<R: Read> LZMAReader<R> {
         mem_limit_kb: u64,
         preset_dict: Option<&[u8]>,
     ) -> crate::io::read_exact_result!(R, Self) {
-        let props = read_u8(&mut reader)?;
-        let dict_size = read_u64_le(&mut reader)?;
-
-        let uncomp_size = read_u64_le(&mut reader)?;
-        let need_mem = match get_memory_usage_by_props(dict_size, props) {
-            Ok(mem) => mem,
-            Err(e) => return error!(read_exact_error_kind!(R, e.kind()), ""),
-        };
+        let header = LZMAHeader::read_from(&mut reader)?;
+        let need_mem = header.memory_usage();
         if mem_limit_kb < need_mem {
             return error!(
                 read_exact_error_kind!(R, ErrorKind::OutOfMemory),
@@ -193,7 +189,25 @@ impl<R: Read> LZMAReader<R> {
                 )
             );
         }
-        match Self::construct1(reader, uncomp_size, props, dict_size, preset_dict) {
+        Self::new_with_header(reader, &header, preset_dict)
+    }
+
+    /// Creates a new .lzma file format decompressor from an already parsed header.
+    /// `reader` must be positioned right after the header.
+    pub fn new_with_header(
+        reader: R,
+        header: &LZMAHeader,
+        preset_dict: Option<&[u8]>,
+    ) -> crate::io::read_exact_result!(R, Self) {
+        match Self::construct2(
+            reader,
+            header.uncompressed_size.unwrap_or(u64::MAX),
+            header.lc,
+            header.lp,
+            header.pb,
+            header.dict_size as u64,
+            preset_dict,
+        ) {
             Ok(out) => Ok(out),
             Err(e) => error!(read_exact_error_kind!(R, e.kind()), ""),
         }
@@ -0,0 +1,47 @@
+use crate::io::{error, Chain, ErrorKind, Read, Result, Take};
+use crate::{lzma_get_memory_usage_by_props, LZMAReader};
+
+/// Decompresses MicroLZMA, the LZMA variant used by EROFS, see
+/// [microlzma_compress](crate::microlzma_compress) for the format.
+///
+/// The compressed size, the uncompressed size and the dictionary size are not stored
+/// in the data and have to be passed in. The reader never reads more than `compressed_size`
+/// bytes from `inner`.
+pub struct MicroLZMAReader<R> {
+    lzma: LZMAReader<Chain<&'static [u8], Take<R>>>,
+}
+
+impl<R: Read> MicroLZMAReader<R> {
+    /// Creates a new MicroLZMA decompressor, reading the props byte immediately.
+    /// - [compressed_size] - the size of the MicroLZMA data, including the props byte.
+    /// - [uncompressed_size] - the exact size of the decompressed data.
+    /// - [dict_size] - the dictionary size used by the encoder.
+    pub fn new(
+        mut inner: R,
+        compressed_size: u64,
+        uncompressed_size: u64,
+        dict_size: u64,
+    ) -> Result<Self> {
+        if compressed_size == 0 {
+            return error!(ErrorKind::InvalidInput, "MicroLZMA data can't be empty");
+        }
+        let mut props = [0u8];
+        inner.read_exact(&mut props)?;
+        let props = !props[0];
+        lzma_get_memory_usage_by_props(dict_size, props)?;
+        // The range decoder expects the first byte that the props byte replaced.
+        let inner = (&[0x00u8][..]).chain(inner.take(compressed_size - 1));
+        let lzma = LZMAReader::new_with_props(inner, uncompressed_size, props, dict_size, None)?;
+        Ok(Self { lzma })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.lzma.into_inner().into_inner().1.into_inner()
+    }
+}
+
+impl<R: Read> Read for MicroLZMAReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.lzma.read(buf)
+    }
+}
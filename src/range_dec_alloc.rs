@@ -7,7 +7,7 @@ use crate::{
 
 pub trait RangeSource: Read {
     fn next_byte(&mut self) -> crate::io::read_exact_result!(Self, u8);
-    fn next_u64(&mut self) -> crate::io::read_exact_result!(Self, u64);
+    fn next_u32(&mut self) -> crate::io::read_exact_result!(Self, u32);
     fn read_u8(&mut self) -> crate::io::read_exact_result!(Self, u8);
     fn read_u16_be(&mut self) -> crate::io::read_exact_result!(Self, u16);
     fn read_u16_le(&mut self) -> crate::io::read_exact_result!(Self, u16);
@@ -64,8 +64,8 @@ impl<R: Read> RangeSource for R {
     fn next_byte(&mut self) -> crate::io::read_exact_result!(R, u8) {
         self.read_u8()
     }
-    fn next_u64(&mut self) -> crate::io::read_exact_result!(R, u64) {
-        self.read_u64_be()
+    fn next_u32(&mut self) -> crate::io::read_exact_result!(R, u32) {
+        self.read_u32_be()
     }
 }
 
@@ -93,7 +93,7 @@ impl<R: RangeSource> RangeDecoder<R> {
                 "range decoder first byte is 0"
             );
         }
-        let code = inner.next_u64()?;
+        let code = inner.next_u32()? as u64;
         Ok(Self {
             inner,
             code,
@@ -106,6 +106,16 @@ impl<R: RangeSource> RangeDecoder<R> {
     }
 }
 
+impl<R> RangeDecoder<R> {
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+}
+
 impl<R: RangeSource> RangeDecoder<R> {
     pub fn normalize(&mut self) -> crate::io::read_exact_result!(R, ()) {
         if self.range < 0x0100_0000 {
@@ -173,7 +183,7 @@ impl<R: RangeSource> RangeDecoder<R> {
             // loop {
             self.normalize()?;
             self.range >>= 1;
-            let t = (self.code.wrapping_sub(self.range)) >> 31;
+            let t = (self.code.wrapping_sub(self.range)) >> 63;
             self.code -= self.range & (t.wrapping_sub(1));
             result = (result << 1) | (1u64.wrapping_sub(t));
             // count -= 1;
@@ -209,7 +219,7 @@ impl RangeDecoder<RangeDecoderBuffer> {
                 "range decoder first byte is 0"
             );
         }
-        self.code = reader.read_u64_be()?;
+        self.code = reader.read_u32_be()? as u64;
 
         self.range = 0xFFFFFFFFu64;
         let len = len - 5;
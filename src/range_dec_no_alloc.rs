@@ -3,7 +3,7 @@ use crate::io::{ErrorKind, ErrorType, Read};
 
 pub trait RangeSource: Read {
     fn next_byte(&mut self) -> u8;
-    fn next_u64(&mut self) -> u64;
+    fn next_u32(&mut self) -> u32;
     fn read_u8(&mut self) -> u8;
     fn read_u16_be(&mut self) -> u16;
     fn read_u16_le(&mut self) -> u16;
@@ -60,8 +60,8 @@ impl<T: Read> RangeSource for T {
     fn next_byte(&mut self) -> u8 {
         self.read_u8()
     }
-    fn next_u64(&mut self) -> u64 {
-        self.read_u64_be()
+    fn next_u32(&mut self) -> u32 {
+        self.read_u32_be()
     }
 }
 
@@ -90,7 +90,7 @@ impl<R: RangeSource> RangeDecoder<R> {
         if b != 0x00 {
             unreachable!()
         }
-        let code = inner.next_u64();
+        let code = inner.next_u32() as u64;
         Self {
             inner,
             code,
@@ -167,7 +167,7 @@ impl<R: RangeSource> RangeDecoder<R> {
             // loop {
             self.normalize();
             self.range = self.range >> 1;
-            let t = (self.code.wrapping_sub(self.range)) >> 31;
+            let t = (self.code.wrapping_sub(self.range)) >> 63;
             self.code -= self.range & (t.wrapping_sub(1));
             result = (result << 1) | (1u64.wrapping_sub(t));
             // count -= 1;
@@ -214,7 +214,7 @@ impl<const DICT_SIZE: usize> RangeDecoder<RangeDecoderBuffer<DICT_SIZE>> {
         if b != 0x00 {
             unreachable!()
         }
-        self.code = reader.read_u64_be();
+        self.code = reader.read_u32_be() as u64;
 
         self.range = 0xFFFFFFFFu64;
         let len = len - 5;
@@ -0,0 +1,485 @@
+use super::{method_id, property_id as id, ByteReader};
+use crate::io::{error, ErrorKind, Result};
+use crate::Vec;
+
+/// Limit of 7-Zip for the number of coders and streams of a folder.
+const FOLDER_STREAMS_MAX: u64 = 64;
+
+/// A coder of a folder with its properties, e.g. the dictionary size of LZMA2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coder {
+    pub method_id: Vec<u8>,
+    pub num_in_streams: u64,
+    pub num_out_streams: u64,
+    pub properties: Vec<u8>,
+}
+
+impl Coder {
+    /// Whether this crate can decode data of the coder.
+    pub fn is_supported(&self) -> bool {
+        [method_id::COPY, method_id::LZMA, method_id::LZMA2].contains(&&self.method_id[..])
+    }
+}
+
+/// Connects the output stream `out_index` of one coder to the input stream `in_index` of another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindPair {
+    pub in_index: u64,
+    pub out_index: u64,
+}
+
+/// A folder is a graph of coders decoding some packed streams into one uncompressed stream,
+/// which may be split into the data of several files (substreams).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Folder {
+    pub coders: Vec<Coder>,
+    pub bind_pairs: Vec<BindPair>,
+    /// Indices of the coder input streams that are read from packed streams.
+    pub packed_streams: Vec<u64>,
+    /// Size of every coder output stream.
+    pub unpack_sizes: Vec<u64>,
+    pub crc: Option<u32>,
+    /// Number of files stored in the folder.
+    pub num_unpack_streams: usize,
+}
+
+impl Folder {
+    /// Index of the output stream that isn't bound to another coder, i.e. the folder's data.
+    pub fn main_out_stream(&self) -> Option<u64> {
+        let total = self.unpack_sizes.len() as u64;
+        (0..total).find(|&i| !self.bind_pairs.iter().any(|bp| bp.out_index == i))
+    }
+
+    /// Size of the uncompressed data of the folder.
+    pub fn unpack_size(&self) -> u64 {
+        self.main_out_stream()
+            .map(|i| self.unpack_sizes[i as usize])
+            .unwrap_or(0)
+    }
+
+    fn read(reader: &mut ByteReader) -> Result<Self> {
+        let num_coders = reader.read_number()?;
+        if num_coders == 0 || num_coders > FOLDER_STREAMS_MAX {
+            return error!(ErrorKind::InvalidData, "7z folder is corrupt");
+        }
+        let mut coders = Vec::new();
+        let mut total_in_streams = 0;
+        let mut total_out_streams = 0;
+        for _ in 0..num_coders {
+            let flags = reader.read_u8()?;
+            if flags & 0xC0 != 0 {
+                return error!(
+                    ErrorKind::Unsupported,
+                    "Unsupported 7z coder with alternative methods"
+                );
+            }
+            let method_id = reader.read_bytes((flags & 0x0F) as usize)?.to_vec();
+            let (num_in_streams, num_out_streams) = if flags & 0x10 != 0 {
+                (reader.read_number()?, reader.read_number()?)
+            } else {
+                (1, 1)
+            };
+            let properties = if flags & 0x20 != 0 {
+                let size = reader.read_number()?;
+                reader
+                    .read_bytes(size.min(usize::MAX as u64) as usize)?
+                    .to_vec()
+            } else {
+                Vec::new()
+            };
+            total_in_streams += num_in_streams;
+            total_out_streams += num_out_streams;
+            if total_in_streams > FOLDER_STREAMS_MAX || total_out_streams > FOLDER_STREAMS_MAX {
+                return error!(ErrorKind::InvalidData, "7z folder is corrupt");
+            }
+            coders.push(Coder {
+                method_id,
+                num_in_streams,
+                num_out_streams,
+                properties,
+            });
+        }
+        if total_out_streams == 0 || total_in_streams < total_out_streams - 1 {
+            return error!(ErrorKind::InvalidData, "7z folder is corrupt");
+        }
+
+        let mut bind_pairs = Vec::new();
+        for _ in 0..total_out_streams - 1 {
+            let in_index = reader.read_number()?;
+            let out_index = reader.read_number()?;
+            if in_index >= total_in_streams || out_index >= total_out_streams {
+                return error!(ErrorKind::InvalidData, "7z folder is corrupt");
+            }
+            bind_pairs.push(BindPair {
+                in_index,
+                out_index,
+            });
+        }
+
+        let num_packed_streams = total_in_streams - bind_pairs.len() as u64;
+        let mut packed_streams = Vec::new();
+        if num_packed_streams == 1 {
+            if let Some(i) =
+                (0..total_in_streams).find(|&i| !bind_pairs.iter().any(|bp| bp.in_index == i))
+            {
+                packed_streams.push(i);
+            }
+        } else {
+            for _ in 0..num_packed_streams {
+                packed_streams.push(reader.read_number()?);
+            }
+        }
+        Ok(Self {
+            coders,
+            bind_pairs,
+            packed_streams,
+            unpack_sizes: Vec::new(),
+            crc: None,
+            num_unpack_streams: 1,
+        })
+    }
+}
+
+/// A file or directory of an archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SevenZEntry {
+    pub name: String,
+    /// Whether the entry has data. Directories and empty files don't.
+    pub has_stream: bool,
+    pub is_directory: bool,
+    /// Anti items mark files to be deleted when an update archive is applied.
+    pub is_anti_item: bool,
+    /// Size of the uncompressed data.
+    pub size: u64,
+    /// CRC32 of the uncompressed data, if stored.
+    pub crc: Option<u32>,
+    /// Last modification time as a Windows FILETIME: 100-nanosecond intervals since 1601-01-01.
+    pub mtime: Option<u64>,
+    /// Windows file attributes. If bit 15 is set, the high 16 bits are the unix mode.
+    pub attributes: Option<u32>,
+}
+
+/// The contents of the header database: where the packed streams are, how to decode
+/// them and which files they contain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Archive {
+    /// Offset of the first packed stream, relative to the end of the signature header.
+    pub pack_pos: u64,
+    pub pack_sizes: Vec<u64>,
+    pub folders: Vec<Folder>,
+    pub entries: Vec<SevenZEntry>,
+}
+
+/// Sizes and CRCs of the files stored in the folders.
+#[derive(Debug, Default)]
+struct SubStreamsInfo {
+    sizes: Vec<u64>,
+    crcs: Vec<Option<u32>>,
+}
+
+impl Archive {
+    /// Reads a streams info structure: the pack info, the folders and the substreams.
+    /// This is the content of an encoded header, which only describes the packed header.
+    pub(crate) fn read_streams_info(reader: &mut ByteReader) -> Result<Self> {
+        let mut archive = Self::default();
+        archive.read_streams_info_into(reader)?;
+        Ok(archive)
+    }
+
+    /// Reads a (decoded) header database.
+    pub(crate) fn read_header(reader: &mut ByteReader) -> Result<Self> {
+        let mut archive = Self::default();
+        let mut nid = reader.read_u8()?;
+        if nid == id::ARCHIVE_PROPERTIES {
+            loop {
+                if reader.read_u8()? == id::END {
+                    break;
+                }
+                skip_property(reader)?;
+            }
+            nid = reader.read_u8()?;
+        }
+        if nid == id::ADDITIONAL_STREAMS_INFO {
+            return error!(ErrorKind::Unsupported, "Unsupported 7z additional streams");
+        }
+        let mut substreams = SubStreamsInfo::default();
+        if nid == id::MAIN_STREAMS_INFO {
+            substreams = archive.read_streams_info_into(reader)?;
+            nid = reader.read_u8()?;
+        }
+        if nid == id::FILES_INFO {
+            archive.read_files_info(reader, substreams)?;
+            nid = reader.read_u8()?;
+        }
+        if nid != id::END {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        Ok(archive)
+    }
+
+    fn read_streams_info_into(&mut self, reader: &mut ByteReader) -> Result<SubStreamsInfo> {
+        let mut nid = reader.read_u8()?;
+        if nid == id::PACK_INFO {
+            self.read_pack_info(reader)?;
+            nid = reader.read_u8()?;
+        }
+        if nid == id::UNPACK_INFO {
+            self.read_unpack_info(reader)?;
+            nid = reader.read_u8()?;
+        }
+        let substreams = if nid == id::SUBSTREAMS_INFO {
+            let substreams = self.read_substreams_info(reader)?;
+            nid = reader.read_u8()?;
+            substreams
+        } else {
+            SubStreamsInfo {
+                sizes: self.folders.iter().map(Folder::unpack_size).collect(),
+                crcs: self.folders.iter().map(|f| f.crc).collect(),
+            }
+        };
+        if nid != id::END {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        let num_packed_streams: usize = self.folders.iter().map(|f| f.packed_streams.len()).sum();
+        if num_packed_streams > self.pack_sizes.len() {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        Ok(substreams)
+    }
+
+    fn read_pack_info(&mut self, reader: &mut ByteReader) -> Result<()> {
+        self.pack_pos = reader.read_number()?;
+        let num_pack_streams = reader.read_count()?;
+        let mut nid = reader.read_u8()?;
+        if nid == id::SIZE {
+            self.pack_sizes = (0..num_pack_streams)
+                .map(|_| reader.read_number())
+                .collect::<Result<_>>()?;
+            nid = reader.read_u8()?;
+        }
+        if nid == id::CRC {
+            // The CRCs of packed streams are not written by 7-Zip and not needed for decoding.
+            reader.read_digests(num_pack_streams)?;
+            nid = reader.read_u8()?;
+        }
+        if nid != id::END || self.pack_sizes.len() != num_pack_streams {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        Ok(())
+    }
+
+    fn read_unpack_info(&mut self, reader: &mut ByteReader) -> Result<()> {
+        if reader.read_u8()? != id::FOLDER {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        let num_folders = reader.read_count()?;
+        if reader.read_u8()? != 0 {
+            return error!(ErrorKind::Unsupported, "Unsupported 7z external folders");
+        }
+        self.folders = (0..num_folders)
+            .map(|_| Folder::read(reader))
+            .collect::<Result<_>>()?;
+        if reader.read_u8()? != id::CODERS_UNPACK_SIZE {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        for folder in self.folders.iter_mut() {
+            let num_out_streams = folder.coders.iter().map(|c| c.num_out_streams).sum();
+            folder.unpack_sizes = (0..num_out_streams)
+                .map(|_| reader.read_number())
+                .collect::<Result<_>>()?;
+        }
+        let mut nid = reader.read_u8()?;
+        if nid == id::CRC {
+            let crcs = reader.read_digests(num_folders)?;
+            for (folder, crc) in self.folders.iter_mut().zip(crcs) {
+                folder.crc = crc;
+            }
+            nid = reader.read_u8()?;
+        }
+        if nid != id::END {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        Ok(())
+    }
+
+    fn read_substreams_info(&mut self, reader: &mut ByteReader) -> Result<SubStreamsInfo> {
+        let mut nid = reader.read_u8()?;
+        if nid == id::NUM_UNPACK_STREAM {
+            let mut total = 0;
+            let num_folders = self.folders.len();
+            for folder in self.folders.iter_mut() {
+                folder.num_unpack_streams = reader.read_count()?;
+                total += folder.num_unpack_streams;
+                if total > reader.remaining() * 8 + num_folders {
+                    return error!(ErrorKind::InvalidData, "7z header is corrupt");
+                }
+            }
+            nid = reader.read_u8()?;
+        }
+
+        let mut info = SubStreamsInfo::default();
+        for folder in self.folders.iter() {
+            if folder.num_unpack_streams == 0 {
+                continue;
+            }
+            let mut sum = 0u64;
+            if nid == id::SIZE {
+                for _ in 1..folder.num_unpack_streams {
+                    let size = reader.read_number()?;
+                    info.sizes.push(size);
+                    sum = sum.saturating_add(size);
+                }
+            } else if folder.num_unpack_streams > 1 {
+                return error!(ErrorKind::InvalidData, "7z header is corrupt");
+            }
+            match folder.unpack_size().checked_sub(sum) {
+                Some(size) => info.sizes.push(size),
+                None => return error!(ErrorKind::InvalidData, "7z header is corrupt"),
+            }
+        }
+        if nid == id::SIZE {
+            nid = reader.read_u8()?;
+        }
+
+        // CRCs are only stored for streams whose CRC is not already known from their folder.
+        let known = |folder: &Folder| folder.num_unpack_streams == 1 && folder.crc.is_some();
+        let num_digests = self
+            .folders
+            .iter()
+            .filter(|f| !known(f))
+            .map(|f| f.num_unpack_streams)
+            .sum();
+        let mut digests = Vec::new().into_iter();
+        while nid != id::END {
+            if nid == id::CRC {
+                digests = reader.read_digests(num_digests)?.into_iter();
+            } else {
+                skip_property(reader)?;
+            }
+            nid = reader.read_u8()?;
+        }
+        for folder in self.folders.iter() {
+            if known(folder) {
+                info.crcs.push(folder.crc);
+            } else {
+                for _ in 0..folder.num_unpack_streams {
+                    info.crcs.push(digests.next().flatten());
+                }
+            }
+        }
+        Ok(info)
+    }
+
+    fn read_files_info(
+        &mut self,
+        reader: &mut ByteReader,
+        substreams: SubStreamsInfo,
+    ) -> Result<()> {
+        let num_files = reader.read_count()?;
+        self.entries = vec![SevenZEntry::default(); num_files];
+        let mut empty_streams = vec![false; num_files];
+        let mut empty_files = Vec::new();
+        let mut anti_items = Vec::new();
+        loop {
+            let property = reader.read_u8()?;
+            if property == id::END {
+                break;
+            }
+            let size = reader.read_number()?;
+            let data = reader.read_bytes(size.min(usize::MAX as u64) as usize)?;
+            let mut reader = ByteReader::new(data);
+            let num_empty_streams = empty_streams.iter().filter(|&&e| e).count();
+            match property {
+                id::EMPTY_STREAM => empty_streams = reader.read_bits(num_files)?,
+                id::EMPTY_FILE => empty_files = reader.read_bits(num_empty_streams)?,
+                id::ANTI => anti_items = reader.read_bits(num_empty_streams)?,
+                id::NAME => {
+                    if reader.read_u8()? != 0 {
+                        return error!(ErrorKind::Unsupported, "Unsupported 7z external names");
+                    }
+                    for entry in self.entries.iter_mut() {
+                        let mut name = Vec::new();
+                        loop {
+                            let c = reader.read_bytes(2)?;
+                            match u16::from_le_bytes([c[0], c[1]]) {
+                                0 => break,
+                                c => name.push(c),
+                            }
+                        }
+                        entry.name = match String::from_utf16(&name) {
+                            Ok(name) => name,
+                            Err(_) => {
+                                return error!(ErrorKind::InvalidData, "Invalid 7z file name")
+                            }
+                        };
+                    }
+                }
+                id::MTIME => {
+                    let defined = reader.read_defined_bits(num_files)?;
+                    if reader.read_u8()? != 0 {
+                        return error!(ErrorKind::Unsupported, "Unsupported 7z external times");
+                    }
+                    for (entry, defined) in self.entries.iter_mut().zip(defined) {
+                        if defined {
+                            entry.mtime = Some(reader.read_u64()?);
+                        }
+                    }
+                }
+                id::WIN_ATTRIBUTES => {
+                    let defined = reader.read_defined_bits(num_files)?;
+                    if reader.read_u8()? != 0 {
+                        return error!(
+                            ErrorKind::Unsupported,
+                            "Unsupported 7z external attributes"
+                        );
+                    }
+                    for (entry, defined) in self.entries.iter_mut().zip(defined) {
+                        if defined {
+                            entry.attributes = Some(reader.read_u32()?);
+                        }
+                    }
+                }
+                // Other properties, like the creation and access times, are not used.
+                _ => {}
+            }
+        }
+
+        let mut sizes = substreams.sizes.into_iter();
+        let mut crcs = substreams.crcs.into_iter();
+        let mut empty_index = 0;
+        for (entry, empty_stream) in self.entries.iter_mut().zip(empty_streams) {
+            entry.has_stream = !empty_stream;
+            if empty_stream {
+                entry.is_directory = !empty_files.get(empty_index).copied().unwrap_or(false);
+                entry.is_anti_item = anti_items.get(empty_index).copied().unwrap_or(false);
+                empty_index += 1;
+            } else {
+                match (sizes.next(), crcs.next()) {
+                    (Some(size), Some(crc)) => {
+                        entry.size = size;
+                        entry.crc = crc;
+                    }
+                    _ => {
+                        return error!(
+                            ErrorKind::InvalidData,
+                            "7z header has more files than streams"
+                        )
+                    }
+                }
+            }
+        }
+        if sizes.next().is_some() {
+            return error!(
+                ErrorKind::InvalidData,
+                "7z header has more streams than files"
+            );
+        }
+        Ok(())
+    }
+}
+
+fn skip_property(reader: &mut ByteReader) -> Result<()> {
+    let size = reader.read_number()?;
+    reader.read_bytes(size.min(usize::MAX as u64) as usize)?;
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Building blocks of the 7z archive format.
+//!
+//! See the `7zFormat.txt` document of the 7-Zip source distribution.
+
+mod archive;
+mod sevenz_reader;
+pub use archive::*;
+pub use sevenz_reader::*;
+
+use crate::check::{Check, Crc32};
+use crate::io::{error, ErrorKind, Result};
+
+pub const SIGNATURE: [u8; 6] = [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+pub const SIGNATURE_HEADER_SIZE: usize = 32;
+pub const VERSION_MAJOR: u8 = 0;
+pub const VERSION_MINOR: u8 = 4;
+
+/// Property IDs of the header database.
+pub mod property_id {
+    pub const END: u8 = 0x00;
+    pub const HEADER: u8 = 0x01;
+    pub const ARCHIVE_PROPERTIES: u8 = 0x02;
+    pub const ADDITIONAL_STREAMS_INFO: u8 = 0x03;
+    pub const MAIN_STREAMS_INFO: u8 = 0x04;
+    pub const FILES_INFO: u8 = 0x05;
+    pub const PACK_INFO: u8 = 0x06;
+    pub const UNPACK_INFO: u8 = 0x07;
+    pub const SUBSTREAMS_INFO: u8 = 0x08;
+    pub const SIZE: u8 = 0x09;
+    pub const CRC: u8 = 0x0A;
+    pub const FOLDER: u8 = 0x0B;
+    pub const CODERS_UNPACK_SIZE: u8 = 0x0C;
+    pub const NUM_UNPACK_STREAM: u8 = 0x0D;
+    pub const EMPTY_STREAM: u8 = 0x0E;
+    pub const EMPTY_FILE: u8 = 0x0F;
+    pub const ANTI: u8 = 0x10;
+    pub const NAME: u8 = 0x11;
+    pub const CTIME: u8 = 0x12;
+    pub const ATIME: u8 = 0x13;
+    pub const MTIME: u8 = 0x14;
+    pub const WIN_ATTRIBUTES: u8 = 0x15;
+    pub const COMMENT: u8 = 0x16;
+    pub const ENCODED_HEADER: u8 = 0x17;
+    pub const START_POS: u8 = 0x18;
+    pub const DUMMY: u8 = 0x19;
+}
+
+/// Method IDs of the supported coders.
+pub mod method_id {
+    pub const COPY: &[u8] = &[0x00];
+    pub const LZMA: &[u8] = &[0x03, 0x01, 0x01];
+    pub const LZMA2: &[u8] = &[0x21];
+}
+
+/// The signature header at the start of every archive, locating the header database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignatureHeader {
+    /// Offset of the header database, relative to the end of the signature header.
+    pub next_header_offset: u64,
+    pub next_header_size: u64,
+    pub next_header_crc: u32,
+}
+
+impl SignatureHeader {
+    pub fn decode(buf: &[u8; SIGNATURE_HEADER_SIZE]) -> Result<Self> {
+        if buf[..6] != SIGNATURE {
+            return error!(ErrorKind::InvalidData, "Invalid 7z signature");
+        }
+        if buf[6] != VERSION_MAJOR {
+            return error!(
+                ErrorKind::Unsupported,
+                format!("Unsupported 7z version {}.{}", buf[6], buf[7])
+            );
+        }
+        let mut reader = ByteReader::new(&buf[8..]);
+        let start_header_crc = reader.read_u32()?;
+        if Crc32::checksum(&buf[12..]) != start_header_crc {
+            return error!(
+                ErrorKind::InvalidData,
+                "7z start header CRC32 does not match"
+            );
+        }
+        Ok(Self {
+            next_header_offset: reader.read_u64()?,
+            next_header_size: reader.read_u64()?,
+            next_header_crc: reader.read_u32()?,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; SIGNATURE_HEADER_SIZE] {
+        let mut buf = [0u8; SIGNATURE_HEADER_SIZE];
+        buf[..6].copy_from_slice(&SIGNATURE);
+        buf[6] = VERSION_MAJOR;
+        buf[7] = VERSION_MINOR;
+        buf[12..20].copy_from_slice(&self.next_header_offset.to_le_bytes());
+        buf[20..28].copy_from_slice(&self.next_header_size.to_le_bytes());
+        buf[28..].copy_from_slice(&self.next_header_crc.to_le_bytes());
+        let crc = Crc32::checksum(&buf[12..]);
+        buf[8..12].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+}
+
+/// Reads the primitive types of the header database from a buffer.
+pub(crate) struct ByteReader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.buf.len() - self.pos
+    }
+
+    pub(crate) fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if len > self.remaining() {
+            return error!(ErrorKind::InvalidData, "7z header is truncated");
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(crate) fn read_u32(&mut self) -> Result<u32> {
+        let mut buf = [0u8; 4];
+        buf.copy_from_slice(self.read_bytes(4)?);
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Result<u64> {
+        let mut buf = [0u8; 8];
+        buf.copy_from_slice(self.read_bytes(8)?);
+        Ok(u64::from_le_bytes(buf))
+    }
+
+    /// Reads a variable length number: the count of leading one bits of the first byte
+    /// is the number of bytes following it, the remaining bits are the most significant ones.
+    pub(crate) fn read_number(&mut self) -> Result<u64> {
+        let first = self.read_u8()? as u64;
+        let mut mask = 0x80;
+        let mut value = 0;
+        for i in 0..8 {
+            if first & mask == 0 {
+                return Ok(value | (first & (mask - 1)) << (8 * i));
+            }
+            value |= (self.read_u8()? as u64) << (8 * i);
+            mask >>= 1;
+        }
+        Ok(value)
+    }
+
+    /// Reads a number of items that follow in the header. Every item takes at least one bit,
+    /// so larger counts are rejected before anything is allocated for them.
+    pub(crate) fn read_count(&mut self) -> Result<usize> {
+        let count = self.read_number()?;
+        if count > self.remaining() as u64 * 8 {
+            return error!(ErrorKind::InvalidData, "7z header is corrupt");
+        }
+        Ok(count as usize)
+    }
+
+    /// Reads a bit field of `len` bits, most significant bit first.
+    pub(crate) fn read_bits(&mut self, len: usize) -> Result<crate::Vec<bool>> {
+        let bytes = self.read_bytes((len + 7) / 8)?;
+        Ok((0..len)
+            .map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+            .collect())
+    }
+
+    /// Reads a bit field that is preceded by an "all defined" byte.
+    pub(crate) fn read_defined_bits(&mut self, len: usize) -> Result<crate::Vec<bool>> {
+        if self.read_u8()? != 0 {
+            return Ok(vec![true; len]);
+        }
+        self.read_bits(len)
+    }
+
+    /// Reads `len` optional CRC32 values.
+    pub(crate) fn read_digests(&mut self, len: usize) -> Result<crate::Vec<Option<u32>>> {
+        let defined = self.read_defined_bits(len)?;
+        defined
+            .into_iter()
+            .map(|defined| match defined {
+                true => self.read_u32().map(Some),
+                false => Ok(None),
+            })
+            .collect()
+    }
+}
+
+/// Writes a variable length number, see [ByteReader::read_number].
+#[cfg(feature = "encoder")]
+pub(crate) fn write_number(buf: &mut crate::Vec<u8>, value: u64) {
+    let mut first = 0u8;
+    let mut mask = 0x80;
+    let mut len = 0;
+    while len < 8 {
+        if value < 1 << (7 * (len + 1)) {
+            first |= (value >> (8 * len)) as u8;
+            break;
+        }
+        first |= mask;
+        mask >>= 1;
+        len += 1;
+    }
+    buf.push(first);
+    buf.extend_from_slice(&value.to_le_bytes()[..len]);
+}
+
+/// Writes a bit field, most significant bit first.
+#[cfg(feature = "encoder")]
+pub(crate) fn write_bits(buf: &mut crate::Vec<u8>, bits: &[bool]) {
+    for chunk in bits.chunks(8) {
+        let byte = chunk
+            .iter()
+            .enumerate()
+            .fold(0u8, |byte, (i, &bit)| byte | (bit as u8) << (7 - i));
+        buf.push(byte);
+    }
+}
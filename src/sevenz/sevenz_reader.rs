@@ -0,0 +1,325 @@
+use super::{
+    method_id, property_id, Archive, ByteReader, Coder, SevenZEntry, SignatureHeader,
+    SIGNATURE_HEADER_SIZE,
+};
+use crate::check::{Check, Crc32};
+use crate::io::{error, ErrorKind, Read, Result, Seek, SeekFrom};
+use crate::{
+    lzma2_dict_size_from_prop, lzma2_get_memory_usage, lzma_get_memory_usage_by_props, LZMA2Reader,
+    LZMAReader,
+};
+
+/// Reads the entries of a 7z archive.
+///
+/// The header database, which is usually LZMA compressed itself, is read when the reader
+/// is created. Files are extracted with [for_each_entry](Self::for_each_entry) in the order
+/// they are stored. Only the Copy, LZMA and LZMA2 coders are supported; any other coder,
+/// e.g. a BCJ filter or encryption, is reported as an [Unsupported](ErrorKind::Unsupported) error.
+///
+/// # Examples
+/// ```no_run
+/// use std::fs::File;
+/// use std::io::Read;
+/// use lzma_rust::SevenZReader;
+/// let mut reader = SevenZReader::new(File::open("archive.7z").unwrap()).unwrap();
+/// reader
+///     .for_each_entry(|entry, data| {
+///         let mut content = Vec::new();
+///         data.read_to_end(&mut content)?;
+///         println!("{}: {} bytes", entry.name, content.len());
+///         Ok(true)
+///     })
+///     .unwrap();
+/// ```
+pub struct SevenZReader<R> {
+    inner: R,
+    archive: Archive,
+    mem_limit_kb: u64,
+}
+
+impl<R: Read + Seek> SevenZReader<R> {
+    /// Creates a new 7z archive reader without a memory usage limit.
+    pub fn new(inner: R) -> Result<Self> {
+        Self::new_mem_limit(inner, u64::MAX)
+    }
+
+    /// Creates a new 7z archive reader with a memory usage limit.
+    /// - [mem_limit_kb] - memory usage limit in kibibytes (KiB). u64::MAX means no limit.
+    ///
+    /// The limit applies to decoding the header as well as the files.
+    pub fn new_mem_limit(mut inner: R, mem_limit_kb: u64) -> Result<Self> {
+        let file_size = inner.seek(SeekFrom::End(0))?;
+        inner.seek(SeekFrom::Start(0))?;
+        let mut buf = [0u8; SIGNATURE_HEADER_SIZE];
+        inner.read_exact(&mut buf)?;
+        let signature = SignatureHeader::decode(&buf)?;
+        if signature.next_header_size == 0 {
+            return Ok(Self {
+                inner,
+                archive: Archive::default(),
+                mem_limit_kb,
+            });
+        }
+        let header_pos = (SIGNATURE_HEADER_SIZE as u64)
+            .checked_add(signature.next_header_offset)
+            .and_then(|pos| pos.checked_add(signature.next_header_size));
+        match header_pos {
+            Some(pos) if pos <= file_size => {}
+            _ => return error!(ErrorKind::InvalidData, "7z file is truncated"),
+        }
+        inner.seek(SeekFrom::Start(
+            SIGNATURE_HEADER_SIZE as u64 + signature.next_header_offset,
+        ))?;
+        let mut header = vec![0u8; signature.next_header_size as usize];
+        inner.read_exact(&mut header)?;
+        if Crc32::checksum(&header) != signature.next_header_crc {
+            return error!(ErrorKind::InvalidData, "7z header CRC32 does not match");
+        }
+
+        let mut reader = ByteReader::new(&header);
+        match reader.read_u8()? {
+            property_id::HEADER => {}
+            property_id::ENCODED_HEADER => {
+                let streams = Archive::read_streams_info(&mut reader)?;
+                header = decode_header(&mut inner, &streams, mem_limit_kb)?;
+                // 7-Zip never encodes the header more than once.
+                if header.first() != Some(&property_id::HEADER) {
+                    return error!(ErrorKind::InvalidData, "7z header is corrupt");
+                }
+            }
+            _ => return error!(ErrorKind::InvalidData, "7z header is corrupt"),
+        }
+        let mut reader = ByteReader::new(&header[1..]);
+        let archive = Archive::read_header(&mut reader)?;
+        Ok(Self {
+            inner,
+            archive,
+            mem_limit_kb,
+        })
+    }
+
+    /// All files and directories of the archive.
+    pub fn entries(&self) -> &[SevenZEntry] {
+        &self.archive.entries
+    }
+
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Calls `f` for every entry with a reader of its data, which is empty for entries
+    /// without a stream. Data the callback doesn't read is skipped, and the CRC32 of every
+    /// file is verified. Returning `false` from the callback stops the iteration.
+    pub fn for_each_entry<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(&SevenZEntry, &mut dyn Read) -> Result<bool>,
+    {
+        let Self {
+            inner,
+            archive,
+            mem_limit_kb,
+        } = self;
+        let mut folders = archive.folders.iter().enumerate();
+        let mut folder_reader: Option<Box<dyn Read + '_>> = None;
+        let mut remaining_streams = 0;
+        for entry in archive.entries.iter() {
+            if !entry.has_stream {
+                if !f(entry, &mut crate::io::empty())? {
+                    return Ok(());
+                }
+                continue;
+            }
+            while remaining_streams == 0 {
+                folder_reader = None;
+                let (index, folder) = match folders.next() {
+                    Some(folder) => folder,
+                    None => return error!(ErrorKind::InvalidData, "7z header is corrupt"),
+                };
+                remaining_streams = folder.num_unpack_streams;
+                if remaining_streams > 0 {
+                    folder_reader = Some(open_folder(inner, archive, index, *mem_limit_kb)?);
+                }
+            }
+            remaining_streams -= 1;
+
+            let mut reader = EntryReader {
+                inner: folder_reader.as_mut().unwrap(),
+                remaining: entry.size,
+                crc32: Crc32::new(),
+                expected_crc: entry.crc,
+            };
+            if !f(entry, &mut reader)? {
+                return Ok(());
+            }
+            crate::io::copy(&mut reader, &mut crate::io::sink())?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodes the packed header described by the streams info of an encoded header.
+fn decode_header<R: Read + Seek>(
+    inner: &mut R,
+    streams: &Archive,
+    mem_limit_kb: u64,
+) -> Result<crate::Vec<u8>> {
+    let mut header = crate::Vec::new();
+    for (index, folder) in streams.folders.iter().enumerate() {
+        let start = header.len();
+        let size = folder.unpack_size();
+        open_folder(inner, streams, index, mem_limit_kb)?
+            .take(size)
+            .read_to_end(&mut header)?;
+        if (header.len() - start) as u64 != size {
+            return error!(ErrorKind::InvalidData, "7z header is truncated");
+        }
+        if matches!(folder.crc, Some(crc) if crc != Crc32::checksum(&header[start..])) {
+            return error!(ErrorKind::InvalidData, "7z header CRC32 does not match");
+        }
+    }
+    Ok(header)
+}
+
+/// Seeks to the packed stream of a folder and returns a reader of its decoded data.
+fn open_folder<'a, R: Read + Seek>(
+    inner: &'a mut R,
+    archive: &Archive,
+    index: usize,
+    mem_limit_kb: u64,
+) -> Result<Box<dyn Read + 'a>> {
+    let folder = &archive.folders[index];
+    if let Some(coder) = folder.coders.iter().find(|c| !c.is_supported()) {
+        return error!(
+            ErrorKind::Unsupported,
+            format!("Unsupported 7z coder {:02X?}", coder.method_id)
+        );
+    }
+
+    // Every supported coder has a single input and output stream, so the coders form a chain
+    // from the main output stream back to the only packed stream.
+    let corrupt = || error!(ErrorKind::InvalidData, "7z folder is corrupt");
+    if folder.packed_streams.len() != 1 || folder.unpack_sizes.len() != folder.coders.len() {
+        return corrupt();
+    }
+    let mut chain = crate::Vec::new();
+    let mut coder_index = match folder.main_out_stream() {
+        Some(index) => index,
+        None => return corrupt(),
+    };
+    loop {
+        if chain.len() == folder.coders.len() {
+            return corrupt();
+        }
+        chain.push(coder_index as usize);
+        match folder
+            .bind_pairs
+            .iter()
+            .find(|bp| bp.in_index == coder_index)
+        {
+            Some(bp) => coder_index = bp.out_index,
+            None => break,
+        }
+    }
+    if folder.packed_streams[0] != coder_index {
+        return corrupt();
+    }
+
+    let pack_index: usize = archive.folders[..index]
+        .iter()
+        .map(|f| f.packed_streams.len())
+        .sum();
+    let pack_offset = archive.pack_sizes[..pack_index]
+        .iter()
+        .try_fold(archive.pack_pos, |pos, &size| pos.checked_add(size));
+    let pack_offset = match pack_offset.and_then(|o| o.checked_add(SIGNATURE_HEADER_SIZE as u64)) {
+        Some(offset) => offset,
+        None => return corrupt(),
+    };
+    inner.seek(SeekFrom::Start(pack_offset))?;
+    let mut reader: Box<dyn Read + 'a> = Box::new(inner.take(archive.pack_sizes[pack_index]));
+    for &coder_index in chain.iter().rev() {
+        let coder = &folder.coders[coder_index];
+        let unpack_size = folder.unpack_sizes[coder_index];
+        reader = open_coder(reader, coder, unpack_size, mem_limit_kb)?;
+    }
+    Ok(reader)
+}
+
+fn open_coder<'a>(
+    inner: Box<dyn Read + 'a>,
+    coder: &Coder,
+    unpack_size: u64,
+    mem_limit_kb: u64,
+) -> Result<Box<dyn Read + 'a>> {
+    let props = &coder.properties[..];
+    let (dict_size, need_mem) = match &coder.method_id[..] {
+        method_id::COPY => return Ok(Box::new(inner.take(unpack_size))),
+        method_id::LZMA if props.len() == 5 => {
+            let dict_size = u32::from_le_bytes([props[1], props[2], props[3], props[4]]) as u64;
+            (
+                dict_size,
+                lzma_get_memory_usage_by_props(dict_size, props[0])?,
+            )
+        }
+        method_id::LZMA2 if props.len() == 1 => {
+            let dict_size = lzma2_dict_size_from_prop(props[0])?;
+            (dict_size, lzma2_get_memory_usage(dict_size))
+        }
+        _ => return error!(ErrorKind::InvalidData, "Invalid 7z coder properties"),
+    };
+    if mem_limit_kb < need_mem {
+        return error!(
+            ErrorKind::OutOfMemory,
+            format!(
+                "{}kb memory needed,but limit was {}kb",
+                need_mem, mem_limit_kb
+            )
+        );
+    }
+    if coder.method_id == method_id::LZMA {
+        let lzma = LZMAReader::new_with_props(inner, unpack_size, props[0], dict_size, None)?;
+        Ok(Box::new(lzma))
+    } else {
+        Ok(Box::new(
+            LZMA2Reader::new(inner, dict_size, None).take(unpack_size),
+        ))
+    }
+}
+
+/// Reads the data of one file from the folder and verifies its size and CRC32.
+struct EntryReader<'a, R: ?Sized> {
+    inner: &'a mut R,
+    remaining: u64,
+    crc32: Crc32,
+    expected_crc: Option<u32>,
+}
+
+impl<'a, R: Read + ?Sized> Read for EntryReader<'a, R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() || self.remaining == 0 {
+            return Ok(0);
+        }
+        let len = buf
+            .len()
+            .min(self.remaining.min(usize::MAX as u64) as usize);
+        let size = self.inner.read(&mut buf[..len])?;
+        if size == 0 {
+            return error!(ErrorKind::UnexpectedEof, "7z data is truncated");
+        }
+        self.crc32.update(&buf[..size]);
+        self.remaining -= size as u64;
+        if self.remaining == 0
+            && matches!(self.expected_crc, Some(crc) if crc != self.crc32.value())
+        {
+            return error!(
+                ErrorKind::InvalidData,
+                "Integrity check (CRC32) does not match"
+            );
+        }
+        Ok(size)
+    }
+}
@@ -0,0 +1,144 @@
+use super::{encode_multibyte, multibyte_size, read_multibyte};
+use crate::check::{Check, Crc32};
+use crate::io::{error, ErrorKind, Read, Result, Write};
+
+const UNPADDED_SIZE_MIN: u64 = 5;
+const UNPADDED_SIZE_MAX: u64 = i64::MAX as u64 & !3;
+
+/// Sizes of one block as stored in the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexRecord {
+    /// Size of the block header, compressed data and check, without block padding.
+    pub unpadded_size: u64,
+    pub uncompressed_size: u64,
+}
+
+impl IndexRecord {
+    /// Size of the whole block in the stream, including block padding.
+    #[inline]
+    pub fn block_size(&self) -> u64 {
+        (self.unpadded_size + 3) & !3
+    }
+}
+
+/// The index of an .xz stream, listing the sizes of all of its blocks.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct XZIndex {
+    /// The records in block order. Append to them with [add](XZIndex::add), which keeps the
+    /// totals up to date.
+    pub records: crate::Vec<IndexRecord>,
+    uncompressed_size: u64,
+    blocks_size: u64,
+}
+
+impl XZIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, unpadded_size: u64, uncompressed_size: u64) -> Result<()> {
+        if !(UNPADDED_SIZE_MIN..=UNPADDED_SIZE_MAX).contains(&unpadded_size)
+            || uncompressed_size > i64::MAX as u64
+        {
+            return error!(ErrorKind::InvalidData, "XZ index record is corrupt");
+        }
+        let record = IndexRecord {
+            unpadded_size,
+            uncompressed_size,
+        };
+        // Like the sizes of single blocks, the totals must fit in a VLI.
+        let totals = self
+            .uncompressed_size
+            .checked_add(uncompressed_size)
+            .zip(self.blocks_size.checked_add(record.block_size()))
+            .filter(|&(u, b)| u <= i64::MAX as u64 && b <= i64::MAX as u64);
+        match totals {
+            Some((uncompressed_size, blocks_size)) => {
+                self.uncompressed_size = uncompressed_size;
+                self.blocks_size = blocks_size;
+            }
+            None => return error!(ErrorKind::InvalidData, "XZ index is too large"),
+        }
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Total uncompressed size of all blocks.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    /// Total size of all blocks, including block padding.
+    pub fn blocks_size(&self) -> u64 {
+        self.blocks_size
+    }
+
+    fn unpadded_index_size(&self) -> u64 {
+        let records_size: usize = self
+            .records
+            .iter()
+            .map(|r| multibyte_size(r.unpadded_size) + multibyte_size(r.uncompressed_size))
+            .sum();
+        (1 + multibyte_size(self.records.len() as u64) + records_size) as u64
+    }
+
+    /// Size of the encoded index field, which is also the backward size of the stream footer.
+    pub fn index_size(&self) -> u64 {
+        ((self.unpadded_index_size() + 3) & !3) + 4
+    }
+
+    /// Size of the whole stream: header, blocks, index and footer.
+    pub fn stream_size(&self) -> u64 {
+        super::STREAM_HEADER_SIZE as u64 * 2 + self.blocks_size() + self.index_size()
+    }
+
+    /// Reads an index, starting at the index indicator byte.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self> {
+        let mut crc = Crc32::new();
+        let mut indicator = [0u8; 1];
+        reader.read_exact(&mut indicator)?;
+        if indicator[0] != 0x00 {
+            return error!(ErrorKind::InvalidData, "XZ index is corrupt");
+        }
+        crc.update(&indicator);
+
+        let count = read_multibyte(reader, &mut crc)?;
+        let mut index = Self::new();
+        for _ in 0..count {
+            let unpadded_size = read_multibyte(reader, &mut crc)?;
+            let uncompressed_size = read_multibyte(reader, &mut crc)?;
+            index.add(unpadded_size, uncompressed_size)?;
+        }
+
+        let mut padding = [0u8; 3];
+        let padding =
+            &mut padding[..(index.index_size() - 4 - index.unpadded_index_size()) as usize];
+        reader.read_exact(padding)?;
+        if padding.iter().any(|&b| b != 0x00) {
+            return error!(ErrorKind::InvalidData, "XZ index is corrupt");
+        }
+        crc.update(padding);
+
+        let mut stored_crc = [0u8; 4];
+        reader.read_exact(&mut stored_crc)?;
+        if crc.value() != u32::from_le_bytes(stored_crc) {
+            return error!(ErrorKind::InvalidData, "XZ index is corrupt");
+        }
+        Ok(index)
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mut buf = vec![0x00];
+        encode_multibyte(self.records.len() as u64, &mut buf);
+        for record in self.records.iter() {
+            encode_multibyte(record.unpadded_size, &mut buf);
+            encode_multibyte(record.uncompressed_size, &mut buf);
+        }
+        while buf.len() % 4 != 0 {
+            buf.push(0x00);
+        }
+        let crc = Crc32::checksum(&buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        out.write_all(&buf)
+    }
+}
@@ -0,0 +1,355 @@
+//! Building blocks of the .xz container format.
+//!
+//! See the [.xz file format specification](https://tukaani.org/xz/xz-file-format.txt).
+
+mod index;
+mod seekable_reader;
+mod xz_reader;
+pub use index::*;
+pub use seekable_reader::*;
+pub use xz_reader::*;
+
+use crate::check::{Check, Crc32, Crc64, Sha256};
+use crate::io::{error, ErrorKind, Read, Result};
+
+pub const HEADER_MAGIC: [u8; 6] = [0xFD, b'7', b'z', b'X', b'Z', 0x00];
+pub const FOOTER_MAGIC: [u8; 2] = *b"YZ";
+pub const STREAM_HEADER_SIZE: usize = 12;
+pub const BLOCK_HEADER_SIZE_MAX: usize = 1024;
+pub const FILTER_ID_LZMA2: u64 = 0x21;
+pub(crate) const FILTERS_MAX: usize = 4;
+const BACKWARD_SIZE_MAX: u64 = 1 << 34;
+
+/// Type of the integrity check stored after each block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckType {
+    None = 0x00,
+    CRC32 = 0x01,
+    CRC64 = 0x04,
+    SHA256 = 0x0A,
+}
+
+impl CheckType {
+    pub fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0x00 => Ok(Self::None),
+            0x01 => Ok(Self::CRC32),
+            0x04 => Ok(Self::CRC64),
+            0x0A => Ok(Self::SHA256),
+            _ => error!(
+                ErrorKind::Unsupported,
+                format!("Unsupported XZ check type {}", id)
+            ),
+        }
+    }
+
+    #[inline]
+    pub fn id(self) -> u8 {
+        self as u8
+    }
+
+    /// Size of the check field in bytes.
+    #[inline]
+    pub fn size(self) -> usize {
+        match self {
+            Self::None => 0,
+            Self::CRC32 => 4,
+            Self::CRC64 => 8,
+            Self::SHA256 => 32,
+        }
+    }
+}
+
+/// Hasher for the check type of a stream.
+#[derive(Debug, Clone)]
+pub(crate) enum BlockCheck {
+    None,
+    CRC32(Crc32),
+    CRC64(Crc64),
+    SHA256(Sha256),
+}
+
+impl BlockCheck {
+    pub(crate) fn new(check: CheckType) -> Self {
+        match check {
+            CheckType::None => Self::None,
+            CheckType::CRC32 => Self::CRC32(Crc32::new()),
+            CheckType::CRC64 => Self::CRC64(Crc64::new()),
+            CheckType::SHA256 => Self::SHA256(Sha256::new()),
+        }
+    }
+
+    pub(crate) fn update(&mut self, buf: &[u8]) {
+        match self {
+            Self::None => {}
+            Self::CRC32(c) => c.update(buf),
+            Self::CRC64(c) => c.update(buf),
+            Self::SHA256(c) => c.update(buf),
+        }
+    }
+
+    /// Encodes the check value as it is stored after the block.
+    pub(crate) fn encode(&self) -> crate::Vec<u8> {
+        match self {
+            Self::None => crate::Vec::new(),
+            Self::CRC32(c) => c.value().to_le_bytes().to_vec(),
+            Self::CRC64(c) => c.value().to_le_bytes().to_vec(),
+            Self::SHA256(c) => c.value().to_vec(),
+        }
+    }
+}
+
+/// The two stream flags bytes shared by the stream header and footer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamFlags {
+    pub check: CheckType,
+}
+
+impl StreamFlags {
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        if buf[0] != 0x00 || buf[1] & 0xF0 != 0 {
+            return error!(ErrorKind::Unsupported, "Unsupported XZ stream flags");
+        }
+        Ok(Self {
+            check: CheckType::from_id(buf[1])?,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; 2] {
+        [0x00, self.check.id()]
+    }
+}
+
+/// Parses and validates a 12 byte stream header.
+pub fn decode_stream_header(buf: &[u8; STREAM_HEADER_SIZE]) -> Result<StreamFlags> {
+    if buf[..6] != HEADER_MAGIC {
+        return error!(ErrorKind::InvalidData, "Input is not in the XZ format");
+    }
+    if Crc32::checksum(&buf[6..8]) != u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]) {
+        return error!(ErrorKind::InvalidData, "XZ stream header is corrupt");
+    }
+    StreamFlags::decode(&buf[6..8])
+}
+
+pub fn encode_stream_header(flags: &StreamFlags) -> [u8; STREAM_HEADER_SIZE] {
+    let mut buf = [0u8; STREAM_HEADER_SIZE];
+    buf[..6].copy_from_slice(&HEADER_MAGIC);
+    buf[6..8].copy_from_slice(&flags.encode());
+    let crc = Crc32::checksum(&buf[6..8]);
+    buf[8..].copy_from_slice(&crc.to_le_bytes());
+    buf
+}
+
+/// The 12 byte stream footer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamFooter {
+    pub flags: StreamFlags,
+    /// Size of the index field in bytes.
+    pub backward_size: u64,
+}
+
+impl StreamFooter {
+    pub fn decode(buf: &[u8; STREAM_HEADER_SIZE]) -> Result<Self> {
+        if buf[10..] != FOOTER_MAGIC {
+            return error!(
+                ErrorKind::InvalidData,
+                "XZ stream footer is corrupt (bad magic)"
+            );
+        }
+        if Crc32::checksum(&buf[4..10]) != u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) {
+            return error!(ErrorKind::InvalidData, "XZ stream footer is corrupt");
+        }
+        let backward_size = u32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]) as u64;
+        Ok(Self {
+            flags: StreamFlags::decode(&buf[8..10])?,
+            backward_size: (backward_size + 1) * 4,
+        })
+    }
+
+    pub fn encode(&self) -> [u8; STREAM_HEADER_SIZE] {
+        debug_assert!(self.backward_size % 4 == 0 && self.backward_size <= BACKWARD_SIZE_MAX);
+        let mut buf = [0u8; STREAM_HEADER_SIZE];
+        buf[4..8].copy_from_slice(&((self.backward_size / 4 - 1) as u32).to_le_bytes());
+        buf[8..10].copy_from_slice(&self.flags.encode());
+        buf[10..].copy_from_slice(&FOOTER_MAGIC);
+        let crc = Crc32::checksum(&buf[4..10]);
+        buf[..4].copy_from_slice(&crc.to_le_bytes());
+        buf
+    }
+}
+
+/// One entry of the filter chain of a block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterFlags {
+    pub id: u64,
+    pub props: crate::Vec<u8>,
+}
+
+/// Block header, including the filter chain used to encode the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// Real size of the encoded header in bytes.
+    pub header_size: usize,
+    pub compressed_size: Option<u64>,
+    pub uncompressed_size: Option<u64>,
+    pub filters: crate::Vec<FilterFlags>,
+}
+
+impl BlockHeader {
+    /// Parses a block header. `buf` must start with the header size byte and
+    /// hold the whole header as indicated by it.
+    pub fn decode(buf: &[u8]) -> Result<Self> {
+        let header_size = (buf[0] as usize + 1) * 4;
+        if buf[0] == 0x00 || buf.len() < header_size {
+            return error!(ErrorKind::InvalidData, "XZ block header is corrupt");
+        }
+        let buf = &buf[..header_size];
+        let crc_pos = header_size - 4;
+        let stored_crc = u32::from_le_bytes([
+            buf[crc_pos],
+            buf[crc_pos + 1],
+            buf[crc_pos + 2],
+            buf[crc_pos + 3],
+        ]);
+        if Crc32::checksum(&buf[..crc_pos]) != stored_crc {
+            return error!(ErrorKind::InvalidData, "XZ block header is corrupt");
+        }
+        let flags = buf[1];
+        if flags & 0x3C != 0 {
+            return error!(
+                ErrorKind::Unsupported,
+                "Unsupported options in XZ block flags"
+            );
+        }
+
+        let mut pos = 2;
+        let body = &buf[..crc_pos];
+        let mut compressed_size = None;
+        let mut uncompressed_size = None;
+        if flags & 0x40 != 0 {
+            let size = decode_multibyte(body, &mut pos)?;
+            if size == 0 {
+                return error!(ErrorKind::InvalidData, "XZ block header is corrupt");
+            }
+            compressed_size = Some(size);
+        }
+        if flags & 0x80 != 0 {
+            uncompressed_size = Some(decode_multibyte(body, &mut pos)?);
+        }
+
+        let filter_count = (flags & 0x03) as usize + 1;
+        let mut filters = crate::Vec::with_capacity(filter_count);
+        for _ in 0..filter_count {
+            let id = decode_multibyte(body, &mut pos)?;
+            let props_size = decode_multibyte(body, &mut pos)? as usize;
+            if props_size > body.len() - pos {
+                return error!(ErrorKind::InvalidData, "XZ block header is corrupt");
+            }
+            filters.push(FilterFlags {
+                id,
+                props: body[pos..pos + props_size].to_vec(),
+            });
+            pos += props_size;
+        }
+
+        if body[pos..].iter().any(|&b| b != 0x00) {
+            return error!(
+                ErrorKind::Unsupported,
+                "Unsupported options in XZ block header"
+            );
+        }
+
+        Ok(Self {
+            header_size,
+            compressed_size,
+            uncompressed_size,
+            filters,
+        })
+    }
+
+    /// Serializes the header, including padding and CRC32. `header_size` is ignored
+    /// and computed from the other fields.
+    pub fn encode(&self) -> crate::Vec<u8> {
+        assert!(!self.filters.is_empty() && self.filters.len() <= FILTERS_MAX);
+        let mut buf = vec![0u8, (self.filters.len() - 1) as u8];
+        if let Some(size) = self.compressed_size {
+            buf[1] |= 0x40;
+            encode_multibyte(size, &mut buf);
+        }
+        if let Some(size) = self.uncompressed_size {
+            buf[1] |= 0x80;
+            encode_multibyte(size, &mut buf);
+        }
+        for filter in self.filters.iter() {
+            encode_multibyte(filter.id, &mut buf);
+            encode_multibyte(filter.props.len() as u64, &mut buf);
+            buf.extend_from_slice(&filter.props);
+        }
+        while buf.len() % 4 != 0 {
+            buf.push(0x00);
+        }
+        buf[0] = (buf.len() / 4) as u8;
+        let crc = Crc32::checksum(&buf);
+        buf.extend_from_slice(&crc.to_le_bytes());
+        buf
+    }
+}
+
+/// Size of the block padding following `compressed_size` bytes of block data.
+#[inline]
+pub fn block_padding_size(compressed_size: u64) -> usize {
+    ((4 - (compressed_size & 3)) & 3) as usize
+}
+
+/// Decodes a multibyte integer from `buf` starting at `pos` and advances `pos` past it.
+pub fn decode_multibyte(buf: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut num = 0u64;
+    let mut i = 0;
+    loop {
+        if i == 9 || *pos >= buf.len() {
+            return error!(ErrorKind::InvalidData, "Corrupt XZ multibyte integer");
+        }
+        let b = buf[*pos];
+        *pos += 1;
+        if b == 0x00 && i > 0 {
+            return error!(ErrorKind::InvalidData, "Corrupt XZ multibyte integer");
+        }
+        num |= ((b & 0x7F) as u64) << (i * 7);
+        i += 1;
+        if b & 0x80 == 0 {
+            return Ok(num);
+        }
+    }
+}
+
+/// Reads a multibyte integer from `reader`, feeding the consumed bytes into `crc`.
+pub fn read_multibyte<R: Read>(reader: &mut R, crc: &mut Crc32) -> Result<u64> {
+    let mut buf = [0u8; 9];
+    for i in 0..buf.len() {
+        reader.read_exact(&mut buf[i..i + 1])?;
+        if buf[i] & 0x80 == 0 {
+            crc.update(&buf[..i + 1]);
+            let mut pos = 0;
+            return decode_multibyte(&buf[..i + 1], &mut pos);
+        }
+    }
+    error!(ErrorKind::InvalidData, "Corrupt XZ multibyte integer")
+}
+
+pub fn encode_multibyte(mut num: u64, out: &mut crate::Vec<u8>) {
+    while num >= 0x80 {
+        out.push((num as u8) | 0x80);
+        num >>= 7;
+    }
+    out.push(num as u8);
+}
+
+#[inline]
+pub fn multibyte_size(mut num: u64) -> usize {
+    let mut size = 1;
+    while num >= 0x80 {
+        num >>= 7;
+        size += 1;
+    }
+    size
+}
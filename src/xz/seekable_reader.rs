@@ -0,0 +1,305 @@
+use super::{
+    decode_stream_header, BlockHeader, BlockReader, CheckType, StreamFooter, XZIndex,
+    BLOCK_HEADER_SIZE_MAX, STREAM_HEADER_SIZE,
+};
+use crate::io::{error, ErrorKind, Read, Result, Seek, SeekFrom};
+
+/// Position and sizes of one block of a .xz file.
+#[derive(Debug, Clone, Copy)]
+struct BlockInfo {
+    /// Offset of the block header in the file.
+    compressed_offset: u64,
+    uncompressed_offset: u64,
+    unpadded_size: u64,
+    uncompressed_size: u64,
+    check: CheckType,
+}
+
+/// Decompresses a .xz file with random access.
+///
+/// The indexes of all streams are read from the end of the file when the reader is created.
+/// A [seek](Seek::seek) only moves the uncompressed position; the next read locates the block
+/// containing it and decodes that block from its start, so seeking is cheap when the file
+/// was compressed with multiple blocks (e.g. with [XZOptions::block_size](crate::XZOptions::block_size)).
+///
+/// # Examples
+/// ```
+/// use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+/// use lzma_rust::{SeekableXZReader, XZOptions, XZWriter};
+/// let data: Vec<u8> = (0..100000u32).flat_map(|i| i.to_le_bytes()).collect();
+/// let mut options = XZOptions::with_preset(1);
+/// options.block_size = Some(64 << 10);
+/// let mut writer = XZWriter::new(Vec::new(), &options).unwrap();
+/// writer.write_all(&data).unwrap();
+/// writer.finish().unwrap();
+///
+/// let mut reader = SeekableXZReader::new(Cursor::new(writer.into_inner())).unwrap();
+/// assert_eq!(reader.uncompressed_size(), data.len() as u64);
+/// reader.seek(SeekFrom::Start(300000)).unwrap();
+/// let mut buf = [0u8; 8];
+/// reader.read_exact(&mut buf).unwrap();
+/// assert_eq!(buf, data[300000..300008]);
+/// ```
+pub struct SeekableXZReader<R> {
+    inner: Option<R>,
+    block: Option<BlockReader<R>>,
+    blocks: crate::Vec<BlockInfo>,
+    stream_count: usize,
+    uncompressed_size: u64,
+    mem_limit_kb: u64,
+    /// Index of the block that `block` decodes.
+    block_index: usize,
+    /// Uncompressed position of the next byte returned by `block`.
+    block_pos: u64,
+    pos: u64,
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<R: Read + Seek> SeekableXZReader<R> {
+    /// Creates a new seekable .xz decompressor without a memory usage limit.
+    pub fn new(inner: R) -> Result<Self> {
+        Self::new_mem_limit(inner, u64::MAX)
+    }
+
+    /// Creates a new seekable .xz decompressor with a memory usage limit.
+    /// - [mem_limit_kb] - memory usage limit in kibibytes (KiB). u64::MAX means no limit.
+    ///
+    /// The stream footers, indexes and stream headers are read and validated immediately.
+    pub fn new_mem_limit(mut inner: R, mem_limit_kb: u64) -> Result<Self> {
+        let mut pos = inner.seek(SeekFrom::End(0))?;
+        if pos % 4 != 0 {
+            return error!(
+                ErrorKind::InvalidData,
+                "XZ file size is not a multiple of four bytes"
+            );
+        }
+
+        let mut streams = crate::Vec::new();
+        let mut buf = [0u8; STREAM_HEADER_SIZE];
+        while pos > 0 {
+            if pos < STREAM_HEADER_SIZE as u64 * 2 {
+                return error!(ErrorKind::InvalidData, "XZ file is truncated");
+            }
+            inner.seek(SeekFrom::Start(pos - STREAM_HEADER_SIZE as u64))?;
+            inner.read_exact(&mut buf)?;
+            if buf[8..] == [0x00; 4] {
+                // stream padding
+                pos -= 4;
+                continue;
+            }
+            let footer = StreamFooter::decode(&buf)?;
+            let index_pos =
+                match (pos - STREAM_HEADER_SIZE as u64).checked_sub(footer.backward_size) {
+                    Some(index_pos) => index_pos,
+                    None => return error!(ErrorKind::InvalidData, "XZ stream footer is corrupt"),
+                };
+            inner.seek(SeekFrom::Start(index_pos))?;
+            let index = XZIndex::read_from(&mut (&mut inner).take(footer.backward_size))?;
+            if index.index_size() != footer.backward_size {
+                return error!(ErrorKind::InvalidData, "XZ index is corrupt");
+            }
+            let stream_start =
+                match index_pos.checked_sub(index.blocks_size() + STREAM_HEADER_SIZE as u64) {
+                    Some(start) => start,
+                    None => return error!(ErrorKind::InvalidData, "XZ index is corrupt"),
+                };
+            inner.seek(SeekFrom::Start(stream_start))?;
+            inner.read_exact(&mut buf)?;
+            if decode_stream_header(&buf)? != footer.flags {
+                return error!(
+                    ErrorKind::InvalidData,
+                    "XZ stream footer does not match the stream header"
+                );
+            }
+            streams.push((stream_start, footer.flags.check, index));
+            pos = stream_start;
+        }
+        if streams.is_empty() {
+            return error!(ErrorKind::InvalidData, "XZ file contains no streams");
+        }
+
+        let mut blocks = crate::Vec::new();
+        let mut uncompressed_offset = 0u64;
+        for (stream_start, check, index) in streams.iter().rev() {
+            // Each index total fits in a VLI, but the sum over all streams has to be checked.
+            match uncompressed_offset.checked_add(index.uncompressed_size()) {
+                Some(end) if end <= i64::MAX as u64 => {}
+                _ => return error!(ErrorKind::InvalidData, "XZ file is too large"),
+            }
+            let mut compressed_offset = stream_start + STREAM_HEADER_SIZE as u64;
+            for record in index.records.iter() {
+                blocks.push(BlockInfo {
+                    compressed_offset,
+                    uncompressed_offset,
+                    unpadded_size: record.unpadded_size,
+                    uncompressed_size: record.uncompressed_size,
+                    check: *check,
+                });
+                compressed_offset += record.block_size();
+                uncompressed_offset += record.uncompressed_size;
+            }
+        }
+
+        Ok(Self {
+            inner: Some(inner),
+            block: None,
+            blocks,
+            stream_count: streams.len(),
+            uncompressed_size: uncompressed_offset,
+            mem_limit_kb,
+            block_index: 0,
+            block_pos: 0,
+            pos: 0,
+            error: None,
+        })
+    }
+
+    /// Total uncompressed size of all streams.
+    pub fn uncompressed_size(&self) -> u64 {
+        self.uncompressed_size
+    }
+
+    pub fn stream_count(&self) -> usize {
+        self.stream_count
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Returns the underlying reader, also after an error.
+    pub fn into_inner(self) -> R {
+        match self.block {
+            Some(block) => block.into_inner(),
+            None => self.inner.unwrap(),
+        }
+    }
+
+    fn drop_block(&mut self) {
+        if let Some(block) = self.block.take() {
+            self.inner = Some(block.into_inner());
+        }
+    }
+
+    /// Starts decoding the block containing `self.pos` and skips to `self.pos` within it.
+    fn locate_block(&mut self) -> Result<()> {
+        let pos = self.pos;
+        let current = &self.blocks[self.block_index];
+        if self.block.is_none()
+            || self.block_pos > pos
+            || pos >= current.uncompressed_offset + current.uncompressed_size
+        {
+            self.drop_block();
+            self.block_index = self
+                .blocks
+                .partition_point(|b| b.uncompressed_offset + b.uncompressed_size <= pos);
+            let info = self.blocks[self.block_index];
+            let inner = self.inner.as_mut().unwrap();
+            inner.seek(SeekFrom::Start(info.compressed_offset))?;
+            let mut buf = [0u8; BLOCK_HEADER_SIZE_MAX];
+            inner.read_exact(&mut buf[..1])?;
+            if buf[0] == 0x00 {
+                return error!(ErrorKind::InvalidData, "XZ index does not match the blocks");
+            }
+            let header_size = (buf[0] as usize + 1) * 4;
+            inner.read_exact(&mut buf[1..header_size])?;
+            let header = BlockHeader::decode(&buf[..header_size])?;
+            let chain = BlockReader::<R>::filter_chain(&header, self.mem_limit_kb)?;
+            self.block = Some(BlockReader::new(
+                self.inner.take().unwrap(),
+                header,
+                info.check,
+                &chain,
+            ));
+            self.block_pos = info.uncompressed_offset;
+        }
+
+        let block = self.block.as_mut().unwrap();
+        let mut buf = [0u8; 4096];
+        while self.block_pos < pos {
+            let len = ((pos - self.block_pos) as usize).min(buf.len());
+            let size = block.read(&mut buf[..len])?;
+            if size == 0 {
+                return error!(ErrorKind::InvalidData, "XZ index does not match the blocks");
+            }
+            self.block_pos += size as u64;
+        }
+        Ok(())
+    }
+
+    fn finish_block(&mut self) -> Result<()> {
+        let info = self.blocks[self.block_index];
+        let (unpadded_size, uncompressed_size) = self.block.as_mut().unwrap().finish()?;
+        self.drop_block();
+        if unpadded_size != info.unpadded_size || uncompressed_size != info.uncompressed_size {
+            return error!(ErrorKind::InvalidData, "XZ index does not match the blocks");
+        }
+        Ok(())
+    }
+
+    fn read_decode(&mut self, buf: &mut [u8]) -> Result<usize> {
+        loop {
+            if self.block.is_none() || self.block_pos != self.pos {
+                if self.pos >= self.uncompressed_size {
+                    return Ok(0);
+                }
+                self.locate_block()?;
+            }
+            let size = self.block.as_mut().unwrap().read(buf)?;
+            if size > 0 {
+                self.pos += size as u64;
+                self.block_pos = self.pos;
+                return Ok(size);
+            }
+            // Also verifies the last block once its data has been read.
+            self.finish_block()?;
+        }
+    }
+}
+
+impl<R: Read + Seek> Read for SeekableXZReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = &self.error {
+            return error!(e.0, e.1.clone());
+        }
+        match self.read_decode(buf) {
+            Ok(size) => Ok(size),
+            Err(e) => {
+                self.error = Some((e.kind(), e.to_string()));
+                Err(e)
+            }
+        }
+    }
+}
+
+impl<R: Read + Seek> Seek for SeekableXZReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(pos) => (pos, 0),
+            SeekFrom::End(offset) => (self.uncompressed_size, offset),
+            SeekFrom::Current(offset) => (self.pos, offset),
+        };
+        let pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.unsigned_abs())
+        };
+        match pos {
+            Some(pos) => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            None => error!(
+                ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position"
+            ),
+        }
+    }
+
+    fn stream_position(&mut self) -> Result<u64> {
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,316 @@
+use super::{
+    block_padding_size, decode_stream_header, BlockCheck, BlockHeader, CheckType, StreamFlags,
+    StreamFooter, XZIndex, BLOCK_HEADER_SIZE_MAX, STREAM_HEADER_SIZE,
+};
+use crate::counting_reader::CountingReader;
+use crate::filter::{FilterChain, FilterChainReader};
+use crate::io::{error, ErrorKind, Read, Result};
+use crate::lzma2_get_memory_usage;
+
+/// Decompresses a .xz file.
+///
+/// Every block is decoded with its [FilterChain], LZMA2 optionally preceded by BCJ and Delta
+/// filters, and the block sizes, index and stream footer are validated so that a corrupt
+/// container is reported as an error.
+/// The integrity check of every block is verified.
+///
+/// Concatenated streams, optionally separated by stream padding, are decoded until the end
+/// of the input. Use [set_single_stream](Self::set_single_stream) to stop after the first
+/// stream when other data follows it.
+///
+/// # Examples
+/// ```
+/// use std::io::Read;
+/// use lzma_rust::XZReader;
+/// let compressed = [
+///     0xfd, 0x37, 0x7a, 0x58, 0x5a, 0x00, 0x00, 0x01, 0x69, 0x22, 0xde, 0x36, 0x04, 0xc0, 0x11,
+///     0x0d, 0x21, 0x01, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x88, 0x88,
+///     0xcd, 0x68, 0x01, 0x00, 0x0c, 0x48, 0x65, 0x6c, 0x6c, 0x6f, 0x2c, 0x20, 0x77, 0x6f, 0x72,
+///     0x6c, 0x64, 0x21, 0x00, 0x00, 0x00, 0x00, 0xe6, 0xc6, 0xe6, 0xeb, 0x00, 0x01, 0x29, 0x0d,
+///     0x7d, 0x56, 0x71, 0x1a, 0x90, 0x42, 0x99, 0x0d, 0x01, 0x00, 0x00, 0x00, 0x00, 0x01, 0x59,
+///     0x5a,
+/// ];
+/// let mut reader = XZReader::new(&compressed[..]).unwrap();
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], b"Hello, world!");
+/// ```
+pub struct XZReader<R> {
+    inner: Option<R>,
+    block: Option<BlockReader<R>>,
+    stream_flags: StreamFlags,
+    index: XZIndex,
+    mem_limit_kb: u64,
+    single_stream: bool,
+    end_reached: bool,
+    error: Option<(ErrorKind, String)>,
+}
+
+impl<R: Read> XZReader<R> {
+    /// Creates a new .xz decompressor without a memory usage limit.
+    /// The stream header is read and validated immediately.
+    pub fn new(inner: R) -> Result<Self> {
+        Self::new_mem_limit(inner, u64::MAX)
+    }
+
+    /// Creates a new .xz decompressor with a memory usage limit.
+    /// - [mem_limit_kb] - memory usage limit in kibibytes (KiB). u64::MAX means no limit.
+    pub fn new_mem_limit(mut inner: R, mem_limit_kb: u64) -> Result<Self> {
+        let mut buf = [0u8; STREAM_HEADER_SIZE];
+        inner.read_exact(&mut buf)?;
+        let stream_flags = decode_stream_header(&buf)?;
+        Ok(Self {
+            inner: Some(inner),
+            block: None,
+            stream_flags,
+            index: XZIndex::new(),
+            mem_limit_kb,
+            single_stream: false,
+            end_reached: false,
+            error: None,
+        })
+    }
+
+    /// Decode only the first stream and leave the underlying reader right after its footer,
+    /// instead of decoding concatenated streams until the end of the input.
+    pub fn set_single_stream(&mut self, single_stream: bool) {
+        self.single_stream = single_stream;
+    }
+
+    /// Type of the integrity check used by the current stream.
+    pub fn check_type(&self) -> CheckType {
+        self.stream_flags.check
+    }
+
+    /// Returns the underlying reader. After the end has been reached in single-stream
+    /// mode it is positioned right after the stream footer. After an error it is returned
+    /// too, at an unspecified position.
+    pub fn into_inner(self) -> R {
+        match self.block {
+            Some(block) => block.into_inner(),
+            None => self.inner.unwrap(),
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut R {
+        self.inner.as_mut().unwrap()
+    }
+
+    fn start_block(&mut self) -> Result<()> {
+        let mut buf = [0u8; BLOCK_HEADER_SIZE_MAX];
+        self.inner_mut().read_exact(&mut buf[..1])?;
+        if buf[0] == 0x00 {
+            self.finish_stream()?;
+            if self.single_stream || !self.start_next_stream()? {
+                self.end_reached = true;
+            }
+            return Ok(());
+        }
+        let header_size = (buf[0] as usize + 1) * 4;
+        self.inner_mut().read_exact(&mut buf[1..header_size])?;
+        let header = BlockHeader::decode(&buf[..header_size])?;
+        // Only take the reader once nothing can fail anymore, so into_inner keeps working.
+        let chain = BlockReader::<R>::filter_chain(&header, self.mem_limit_kb)?;
+        let inner = self.inner.take().unwrap();
+        self.block = Some(BlockReader::new(
+            inner,
+            header,
+            self.stream_flags.check,
+            &chain,
+        ));
+        Ok(())
+    }
+
+    fn finish_stream(&mut self) -> Result<()> {
+        let inner = self.inner_mut();
+        let index = XZIndex::read_from(&mut (&[0x00u8][..]).chain(inner))?;
+        if index != self.index {
+            return error!(ErrorKind::InvalidData, "XZ index does not match the blocks");
+        }
+        let mut buf = [0u8; STREAM_HEADER_SIZE];
+        self.inner_mut().read_exact(&mut buf)?;
+        let footer = StreamFooter::decode(&buf)?;
+        if footer.flags != self.stream_flags || footer.backward_size != index.index_size() {
+            return error!(
+                ErrorKind::InvalidData,
+                "XZ stream footer does not match the stream header"
+            );
+        }
+        Ok(())
+    }
+
+    /// Skips stream padding and reads the header of the next stream.
+    /// Returns `false` if the end of the input has been reached instead.
+    fn start_next_stream(&mut self) -> Result<bool> {
+        let mut buf = [0u8; STREAM_HEADER_SIZE];
+        loop {
+            let mut size = 0;
+            while size < 4 {
+                match self.inner_mut().read(&mut buf[size..4]) {
+                    Ok(0) => break,
+                    Ok(n) => size += n,
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                    Err(e) => return Err(e),
+                }
+            }
+            match size {
+                0 => return Ok(false),
+                4 => {}
+                _ => {
+                    return error!(
+                        ErrorKind::InvalidData,
+                        "XZ stream padding is not a multiple of four bytes"
+                    )
+                }
+            }
+            if buf[..4] != [0x00; 4] {
+                break;
+            }
+        }
+        self.inner_mut().read_exact(&mut buf[4..])?;
+        self.stream_flags = decode_stream_header(&buf)?;
+        self.index = XZIndex::new();
+        Ok(true)
+    }
+
+    fn read_decode(&mut self, buf: &mut [u8]) -> Result<usize> {
+        while !self.end_reached {
+            match self.block.as_mut() {
+                None => self.start_block()?,
+                Some(block) => {
+                    let size = block.read(buf)?;
+                    if size > 0 {
+                        return Ok(size);
+                    }
+                    let (unpadded_size, uncompressed_size) = block.finish()?;
+                    self.inner = Some(self.block.take().unwrap().into_inner());
+                    self.index.add(unpadded_size, uncompressed_size)?;
+                }
+            }
+        }
+        Ok(0)
+    }
+}
+
+impl<R: Read> Read for XZReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if let Some(e) = &self.error {
+            return error!(e.0, e.1.clone());
+        }
+        match self.read_decode(buf) {
+            Ok(size) => Ok(size),
+            Err(e) => {
+                self.error = Some((e.kind(), e.to_string()));
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Decodes the data of a single block and validates its sizes and check.
+pub(crate) struct BlockReader<R> {
+    chain: FilterChainReader<CountingReader<R>>,
+    header: BlockHeader,
+    check: BlockCheck,
+    uncompressed_size: u64,
+}
+
+impl<R: Read> BlockReader<R> {
+    /// Checks that the block can be decoded within the memory limit and returns its
+    /// filter chain.
+    pub(crate) fn filter_chain(header: &BlockHeader, mem_limit_kb: u64) -> Result<FilterChain> {
+        let chain = FilterChain::from_filter_flags(&header.filters)?;
+        let need_mem = lzma2_get_memory_usage(chain.dict_size());
+        if mem_limit_kb < need_mem {
+            return error!(
+                ErrorKind::OutOfMemory,
+                format!(
+                    "{}kb memory needed,but limit was {}kb",
+                    need_mem, mem_limit_kb
+                )
+            );
+        }
+        Ok(chain)
+    }
+
+    /// Creates the decoder of a block with the chain returned by
+    /// [filter_chain](Self::filter_chain).
+    pub(crate) fn new(
+        inner: R,
+        header: BlockHeader,
+        check: CheckType,
+        chain: &FilterChain,
+    ) -> Self {
+        Self {
+            chain: chain.reader(CountingReader::new(inner)),
+            header,
+            check: BlockCheck::new(check),
+            uncompressed_size: 0,
+        }
+    }
+
+    pub(crate) fn into_inner(self) -> R {
+        self.chain.into_inner().inner
+    }
+
+    /// Verifies the block sizes, padding and check once all data has been read.
+    /// Returns the unpadded and uncompressed sizes of the block. The underlying reader stays
+    /// in the block reader, also when the block is corrupt.
+    pub(crate) fn finish(&mut self) -> Result<(u64, u64)> {
+        let compressed_size = self.chain.get_ref().count;
+        if self.header.compressed_size.unwrap_or(compressed_size) != compressed_size
+            || self
+                .header
+                .uncompressed_size
+                .unwrap_or(self.uncompressed_size)
+                != self.uncompressed_size
+        {
+            return error!(
+                ErrorKind::InvalidData,
+                "XZ block sizes do not match the block header"
+            );
+        }
+        let check = self.check.encode();
+        let uncompressed_size = self.uncompressed_size;
+        let unpadded_size = self.header.header_size as u64 + compressed_size + check.len() as u64;
+        let inner = self.chain.get_mut();
+
+        let padding_size = block_padding_size(compressed_size);
+        let mut buf = [0u8; 3 + 32];
+        inner.read_exact(&mut buf[..padding_size + check.len()])?;
+        if buf[..padding_size].iter().any(|&b| b != 0x00) {
+            return error!(ErrorKind::InvalidData, "XZ block padding is corrupt");
+        }
+        if buf[padding_size..padding_size + check.len()] != check[..] {
+            return error!(ErrorKind::InvalidData, "Integrity check does not match");
+        }
+        Ok((unpadded_size, uncompressed_size))
+    }
+}
+
+impl<R: Read> Read for BlockReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let size = self.chain.read(buf)?;
+        self.uncompressed_size += size as u64;
+        self.check.update(&buf[..size]);
+        let compressed_size = self.chain.get_ref().count;
+        if self
+            .header
+            .uncompressed_size
+            .map_or(false, |s| self.uncompressed_size > s)
+            || self
+                .header
+                .compressed_size
+                .map_or(false, |s| compressed_size > s)
+        {
+            return error!(
+                ErrorKind::InvalidData,
+                "XZ block sizes do not match the block header"
+            );
+        }
+        Ok(size)
+    }
+}
@@ -0,0 +1,44 @@
+//! The LZMA compression method (14) of the ZIP format.
+//!
+//! See section 5.8 of the [ZIP APPNOTE](https://pkware.cachefly.net/webdocs/casestudies/APPNOTE.TXT).
+//! This module only handles the compressed data of an entry; the ZIP headers are left to
+//! a ZIP crate, which has to set [FLAG_EOS_MARKER] in the general purpose bit flag of entries
+//! written with an end of stream marker.
+
+mod zip_lzma_reader;
+pub use zip_lzma_reader::*;
+
+use crate::io::{error, ErrorKind, Result};
+
+/// The compression method number of LZMA.
+pub const METHOD_LZMA: u16 = 14;
+/// General purpose bit 1, set if the LZMA data is terminated by an end of stream marker.
+pub const FLAG_EOS_MARKER: u16 = 0x0002;
+/// Size of the header preceding the LZMA data: the LZMA SDK version, the size of the
+/// properties and the properties (the props byte and the dictionary size).
+pub const HEADER_SIZE: usize = 9;
+const PROPS_SIZE: u16 = 5;
+/// LZMA SDK version written to the header. Readers ignore it.
+pub const SDK_VERSION: [u8; 2] = [9, 20];
+
+/// Decodes the header, returning the props byte and the dictionary size.
+pub fn decode_header(buf: &[u8; HEADER_SIZE]) -> Result<(u8, u32)> {
+    let props_size = u16::from_le_bytes([buf[2], buf[3]]);
+    if props_size != PROPS_SIZE {
+        return error!(
+            ErrorKind::InvalidData,
+            format!("Invalid ZIP LZMA properties size {}", props_size)
+        );
+    }
+    let dict_size = u32::from_le_bytes([buf[5], buf[6], buf[7], buf[8]]);
+    Ok((buf[4], dict_size))
+}
+
+pub fn encode_header(props: u8, dict_size: u32) -> [u8; HEADER_SIZE] {
+    let mut buf = [0u8; HEADER_SIZE];
+    buf[..2].copy_from_slice(&SDK_VERSION);
+    buf[2..4].copy_from_slice(&PROPS_SIZE.to_le_bytes());
+    buf[4] = props;
+    buf[5..].copy_from_slice(&dict_size.to_le_bytes());
+    buf
+}
@@ -0,0 +1,75 @@
+use super::{decode_header, HEADER_SIZE};
+use crate::io::{error, ErrorKind, Read, Result};
+use crate::{lzma_get_memory_usage_by_props, LZMAReader};
+
+/// Decompresses the data of a ZIP entry stored with the LZMA compression method (14).
+///
+/// # Examples
+/// ```
+/// use std::io::{Read, Write};
+/// use lzma_rust::{LZMA2Options, ZipLzmaReader, ZipLzmaWriter};
+/// let mut writer = ZipLzmaWriter::new(Vec::new(), &LZMA2Options::with_preset(6), true).unwrap();
+/// writer.write_all(b"Hello, world!").unwrap();
+/// writer.finish().unwrap();
+/// let compressed = writer.into_inner();
+///
+/// let mut reader = ZipLzmaReader::new(&compressed[..], 13, true).unwrap();
+/// let mut decompressed = Vec::new();
+/// reader.read_to_end(&mut decompressed).unwrap();
+/// assert_eq!(&decompressed[..], b"Hello, world!");
+/// ```
+pub struct ZipLzmaReader<R> {
+    lzma: LZMAReader<R>,
+}
+
+impl<R: Read> ZipLzmaReader<R> {
+    /// Creates a new decompressor without a memory usage limit, reading the header immediately.
+    /// - [uncompressed_size] - the uncompressed size from the ZIP headers. u64::MAX means unknown,
+    ///   which requires an end of stream marker.
+    /// - [eos_marker] - whether [FLAG_EOS_MARKER](super::FLAG_EOS_MARKER) is set for the entry.
+    pub fn new(inner: R, uncompressed_size: u64, eos_marker: bool) -> Result<Self> {
+        Self::new_mem_limit(inner, uncompressed_size, eos_marker, u64::MAX)
+    }
+
+    /// Creates a new decompressor with a memory usage limit.
+    /// - [mem_limit_kb] - memory usage limit in kibibytes (KiB). u64::MAX means no limit.
+    pub fn new_mem_limit(
+        mut inner: R,
+        uncompressed_size: u64,
+        eos_marker: bool,
+        mem_limit_kb: u64,
+    ) -> Result<Self> {
+        if !eos_marker && uncompressed_size == u64::MAX {
+            return error!(
+                ErrorKind::InvalidInput,
+                "ZIP LZMA data without an end marker needs the uncompressed size"
+            );
+        }
+        let mut buf = [0u8; HEADER_SIZE];
+        inner.read_exact(&mut buf)?;
+        let (props, dict_size) = decode_header(&buf)?;
+        let need_mem = lzma_get_memory_usage_by_props(dict_size as u64, props)?;
+        if mem_limit_kb < need_mem {
+            return error!(
+                ErrorKind::OutOfMemory,
+                format!(
+                    "{}kb memory needed,but limit was {}kb",
+                    need_mem, mem_limit_kb
+                )
+            );
+        }
+        let lzma =
+            LZMAReader::new_with_props(inner, uncompressed_size, props, dict_size as u64, None)?;
+        Ok(Self { lzma })
+    }
+
+    pub fn into_inner(self) -> R {
+        self.lzma.into_inner()
+    }
+}
+
+impl<R: Read> Read for ZipLzmaReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        self.lzma.read(buf)
+    }
+}
@@ -0,0 +1,132 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{Read, Write};
+
+use lzma_rust::filter::BCJFilter;
+use lzma_rust::{BCJReader, BCJWriter, CountingWriter, LZMA2Options, LZMA2Reader, LZMA2Writer};
+
+/// Random bytes with many x86 call and jump instructions, some of them overlapping.
+const X86_BIN: &[u8] = include_bytes!("fixtures/x86.bin");
+/// [X86_BIN] converted by liblzma's x86 filter with the default start offset, taken from a
+/// raw `x86,lzma2` stream written by Python's `lzma` module.
+const X86_BCJ: &[u8] = include_bytes!("fixtures/x86.bcj");
+/// The same with a start offset of 4096.
+const X86_4096_BCJ: &[u8] = include_bytes!("fixtures/x86-4096.bcj");
+
+type NewFilter = fn(bool, u32) -> BCJFilter;
+
+/// Random bytes with branch instructions of the other architectures, 512 bytes each.
+const BCJ_BIN: &[u8] = include_bytes!("fixtures/bcj.bin");
+/// [BCJ_BIN] converted by `xz --format=raw --<arch>` (xz 5.8.2) for every architecture.
+const BCJ_CONVERTED: [(NewFilter, &[u8]); 7] = [
+    (BCJFilter::new_arm, include_bytes!("fixtures/bcj-arm.bcj")),
+    (
+        BCJFilter::new_arm_thumb,
+        include_bytes!("fixtures/bcj-armthumb.bcj"),
+    ),
+    (
+        BCJFilter::new_arm64,
+        include_bytes!("fixtures/bcj-arm64.bcj"),
+    ),
+    (
+        BCJFilter::new_ppc,
+        include_bytes!("fixtures/bcj-powerpc.bcj"),
+    ),
+    (
+        BCJFilter::new_sparc,
+        include_bytes!("fixtures/bcj-sparc.bcj"),
+    ),
+    (BCJFilter::new_ia64, include_bytes!("fixtures/bcj-ia64.bcj")),
+    (
+        BCJFilter::new_riscv,
+        include_bytes!("fixtures/bcj-riscv.bcj"),
+    ),
+];
+
+fn encode(data: &[u8], filter: BCJFilter, chunk_size: usize) -> Vec<u8> {
+    let mut writer = BCJWriter::new(Vec::new(), filter);
+    for chunk in data.chunks(chunk_size) {
+        writer.write_all(chunk).unwrap();
+    }
+    writer.finish().unwrap();
+    writer.into_inner()
+}
+
+fn decode(data: &[u8], filter: BCJFilter, chunk_size: usize) -> Vec<u8> {
+    let mut reader = BCJReader::new(data, filter);
+    let mut output = Vec::new();
+    let mut buf = vec![0u8; chunk_size];
+    loop {
+        let size = reader.read(&mut buf).unwrap();
+        if size == 0 {
+            return output;
+        }
+        output.extend_from_slice(&buf[..size]);
+    }
+}
+
+#[test]
+fn x86_matches_liblzma() {
+    for (start_pos, converted) in [(0, X86_BCJ), (4096, X86_4096_BCJ)] {
+        for chunk_size in [1, 7, 4096, 100000] {
+            let encoded = encode(X86_BIN, BCJFilter::new_x86(true, start_pos), chunk_size);
+            assert!(encoded == converted);
+            let decoded = decode(converted, BCJFilter::new_x86(false, start_pos), chunk_size);
+            assert!(decoded == X86_BIN);
+        }
+    }
+}
+
+#[test]
+fn other_architectures_match_xz() {
+    for (new_filter, converted) in BCJ_CONVERTED {
+        assert!(BCJ_BIN != converted);
+        for chunk_size in [1, 6, 4096] {
+            let encoded = encode(BCJ_BIN, new_filter(true, 0), chunk_size);
+            assert!(encoded == converted);
+            let decoded = decode(converted, new_filter(false, 0), chunk_size);
+            assert!(decoded == BCJ_BIN);
+        }
+    }
+}
+
+#[test]
+fn x86_in_place() {
+    let mut data = X86_BIN.to_vec();
+    let converted = BCJFilter::new_x86(true, 0).code(&mut data);
+    assert!(converted > X86_BIN.len() - 5);
+    assert!(data[..converted] == X86_BCJ[..converted]);
+}
+
+#[test]
+fn x86_lzma2_round_trip() {
+    let options = LZMA2Options::with_preset(6);
+    let lzma2 = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    let mut writer = BCJWriter::new_x86(lzma2, 0);
+    writer.write_all(X86_BIN).unwrap();
+    assert_eq!(writer.write(&[]).unwrap(), 0);
+    let compressed = writer.into_inner().inner.inner;
+
+    // Without the BCJ reader, the converted data is read.
+    let mut converted = Vec::new();
+    LZMA2Reader::new(&compressed[..], options.dict_size, None)
+        .read_to_end(&mut converted)
+        .unwrap();
+    assert!(converted == X86_BCJ);
+
+    let lzma2 = LZMA2Reader::new(&compressed[..], options.dict_size, None);
+    let mut decompressed = Vec::new();
+    BCJReader::new_x86(lzma2, 0)
+        .read_to_end(&mut decompressed)
+        .unwrap();
+    assert!(decompressed == X86_BIN);
+}
+
+#[test]
+fn write_after_finish() {
+    let mut writer = BCJWriter::new_x86(Vec::new(), 0);
+    writer.write_all(&[0xE8, 0x00]).unwrap();
+    assert_eq!(writer.write(&[]).unwrap(), 0);
+    assert_eq!(writer.get_ref(), &[0xE8, 0x00]);
+    assert!(writer.write(&[0x00]).is_err());
+}
@@ -0,0 +1,54 @@
+use lzma_rust::check::{Check, Crc32, Crc64, Sha256};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+
+/// Feeds `data` in chunks of varying sizes to exercise the incremental paths.
+fn chunked<C: Check>(data: &[u8]) -> C::Output {
+    let mut check = C::default();
+    let mut pos = 0;
+    let mut len = 1;
+    while pos < data.len() {
+        let end = (pos + len).min(data.len());
+        check.update(&data[pos..end]);
+        pos = end;
+        len = len * 3 % 200 + 1;
+    }
+    check.value()
+}
+
+#[test]
+fn crc32() {
+    assert_eq!(Crc32::checksum(b""), 0);
+    assert_eq!(Crc32::checksum(TEXT), 0xa0247e6d);
+    assert_eq!(chunked::<Crc32>(TEXT), 0xa0247e6d);
+}
+
+#[test]
+fn crc64() {
+    assert_eq!(Crc64::checksum(b""), 0);
+    // as listed by `xz -lvv fixtures/text-crc64.xz`
+    assert_eq!(Crc64::checksum(TEXT), 0x1ccc7d6e07354a74);
+    assert_eq!(chunked::<Crc64>(TEXT), 0x1ccc7d6e07354a74);
+}
+
+#[test]
+fn sha256() {
+    let empty = [
+        0xe3, 0xb0, 0xc4, 0x42, 0x98, 0xfc, 0x1c, 0x14, 0x9a, 0xfb, 0xf4, 0xc8, 0x99, 0x6f, 0xb9,
+        0x24, 0x27, 0xae, 0x41, 0xe4, 0x64, 0x9b, 0x93, 0x4c, 0xa4, 0x95, 0x99, 0x1b, 0x78, 0x52,
+        0xb8, 0x55,
+    ];
+    let text = [
+        0x07, 0xe0, 0x38, 0x6b, 0x23, 0x17, 0x98, 0x73, 0xf3, 0x26, 0x80, 0xcc, 0x78, 0xe7, 0xbd,
+        0xdf, 0x79, 0x49, 0x52, 0x58, 0x33, 0x85, 0xb7, 0x1b, 0x49, 0x24, 0xe1, 0xa8, 0x5c, 0xd1,
+        0x7b, 0xb1,
+    ];
+    assert_eq!(Sha256::checksum(b""), empty);
+    assert_eq!(Sha256::checksum(TEXT), text);
+    assert_eq!(chunked::<Sha256>(TEXT), text);
+    // message lengths around the padding boundary
+    for len in 54..=66 {
+        let data = &TEXT[..len];
+        assert_eq!(chunked::<Sha256>(data), Sha256::checksum(data));
+    }
+}
@@ -0,0 +1,79 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{Read, Write};
+
+use lzma_rust::filter::DeltaFilter;
+use lzma_rust::{CountingWriter, DeltaReader, DeltaWriter, LZMA2Options, LZMA2Reader, LZMA2Writer};
+
+/// [pcm] compressed by Python's `lzma` module as a raw stream with the filters
+/// `delta(dist=4),lzma2(preset=6)`.
+const PCM_DELTA4_LZMA2: &[u8] = include_bytes!("fixtures/pcm-delta4.lzma2");
+
+/// 16-bit stereo sawtooth waves.
+fn pcm() -> Vec<u8> {
+    (0..4096)
+        .flat_map(|i| [(i * 3 * 37) % 2000 - 1000, (i * 4 * 37) % 2000 - 1000])
+        .flat_map(|s: i32| (s as i16).to_le_bytes())
+        .collect()
+}
+
+fn lzma2_decompress(data: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    LZMA2Reader::new(data, LZMA2Options::DICT_SIZE_DEFAULT, None)
+        .read_to_end(&mut output)
+        .unwrap();
+    output
+}
+
+#[test]
+fn read_fixture() {
+    let lzma2 = LZMA2Reader::new(PCM_DELTA4_LZMA2, LZMA2Options::DICT_SIZE_DEFAULT, None);
+    let mut output = Vec::new();
+    DeltaReader::new(lzma2, 4).read_to_end(&mut output).unwrap();
+    assert_eq!(output, pcm());
+}
+
+#[test]
+fn write_matches_liblzma() {
+    let expected = lzma2_decompress(PCM_DELTA4_LZMA2);
+    let pcm = pcm();
+    for chunk_size in [1, 3, 4096, 5000] {
+        let mut writer = DeltaWriter::new(Vec::new(), 4);
+        for chunk in pcm.chunks(chunk_size) {
+            writer.write_all(chunk).unwrap();
+        }
+        assert_eq!(writer.into_inner(), expected);
+    }
+}
+
+#[test]
+fn lzma2_round_trip() {
+    let pcm = pcm();
+    for distance in [1, 2, 4, 7, 256] {
+        let options = LZMA2Options::with_preset(6);
+        let lzma2 = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+        let mut writer = DeltaWriter::new(lzma2, distance);
+        writer.write_all(&pcm).unwrap();
+        assert_eq!(writer.write(&[]).unwrap(), 0);
+        let compressed = writer.into_inner().inner.inner;
+
+        let lzma2 = LZMA2Reader::new(&compressed[..], options.dict_size, None);
+        let mut reader = DeltaReader::new(lzma2, distance);
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).unwrap();
+        assert_eq!(output, pcm);
+    }
+}
+
+#[test]
+fn props() {
+    assert_eq!(DeltaFilter::new(4).props(), 3);
+    assert_eq!(DeltaFilter::from_props(255).distance(), 256);
+    assert_eq!(DeltaFilter::from_props(0).distance(), 1);
+}
+
+#[test]
+#[should_panic]
+fn distance_out_of_range() {
+    DeltaFilter::new(257);
+}
@@ -0,0 +1,166 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{ErrorKind, Read, Write};
+
+use lzma_rust::filter::{BCJArch, BCJOptions, DeltaFilter, DeltaOptions, Filter, FilterDecoder};
+use lzma_rust::xz::{BlockHeader, FilterFlags, STREAM_HEADER_SIZE};
+use lzma_rust::{FilterChain, LZMA2Options, XZReader};
+
+const X86_BIN: &[u8] = include_bytes!("fixtures/x86.bin");
+/// [X86_BIN] compressed by `xz --x86=start=4096 --delta=dist=4 --lzma2=preset=6` (xz 5.8.2).
+const X86_DELTA_XZ: &[u8] = include_bytes!("fixtures/x86-delta.xz");
+
+fn x86_delta_chain() -> FilterChain {
+    let mut bcj = BCJOptions::new(BCJArch::X86);
+    bcj.start_pos = 4096;
+    FilterChain::builder()
+        .filter(bcj)
+        .filter(DeltaOptions::new(4))
+        .lzma2(LZMA2Options::with_preset(6))
+        .build()
+        .unwrap()
+}
+
+fn block_header() -> BlockHeader {
+    BlockHeader::decode(&X86_DELTA_XZ[STREAM_HEADER_SIZE..]).unwrap()
+}
+
+#[test]
+fn filter_flags_match_xz() {
+    assert_eq!(x86_delta_chain().filter_flags(), block_header().filters);
+}
+
+#[test]
+fn read_xz_block() {
+    let header = block_header();
+    let chain = FilterChain::from_filter_flags(&header.filters).unwrap();
+    assert_eq!(chain.filters().len(), 2);
+    assert_eq!(chain.dict_size(), LZMA2Options::DICT_SIZE_DEFAULT);
+
+    let block = &X86_DELTA_XZ[STREAM_HEADER_SIZE + header.header_size..];
+    let mut output = Vec::new();
+    chain.reader(block).read_to_end(&mut output).unwrap();
+    assert_eq!(output, X86_BIN);
+}
+
+#[test]
+fn read_xz_file() {
+    let mut reader = XZReader::new(X86_DELTA_XZ).unwrap();
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).unwrap();
+    assert_eq!(output, X86_BIN);
+    assert!(reader.into_inner().is_empty());
+}
+
+#[test]
+fn round_trip() {
+    let chain = x86_delta_chain();
+    let mut compressed = Vec::new();
+    let mut writer = chain.writer(&mut compressed).unwrap();
+    for chunk in X86_BIN.chunks(1000) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert_eq!(writer.write(&[]).unwrap(), 0);
+    drop(writer);
+
+    let mut output = Vec::new();
+    chain
+        .reader(&compressed[..])
+        .read_to_end(&mut output)
+        .unwrap();
+    assert_eq!(output, X86_BIN);
+}
+
+#[test]
+fn lzma2_only() {
+    let chain = FilterChain::builder().build().unwrap();
+    assert_eq!(
+        chain.filter_flags(),
+        [FilterFlags {
+            id: 0x21,
+            props: vec![LZMA2Options::default().dict_size_prop()],
+        }]
+    );
+}
+
+/// A filter that claims the ID of LZMA2.
+struct FakeLZMA2;
+
+impl Filter for FakeLZMA2 {
+    fn id(&self) -> u64 {
+        0x21
+    }
+
+    fn props(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn decoder(&self) -> Box<dyn FilterDecoder> {
+        Box::new(DeltaFilter::new(1))
+    }
+
+    fn writer<'a>(&self, inner: Box<dyn Write + 'a>) -> std::io::Result<Box<dyn Write + 'a>> {
+        Ok(inner)
+    }
+}
+
+#[test]
+fn build_errors() {
+    let filters = |count| {
+        (0..count).fold(FilterChain::builder(), |builder, _| {
+            builder.filter(DeltaOptions::new(1))
+        })
+    };
+    assert!(filters(3).build().is_ok());
+    assert_eq!(
+        filters(4).build().err().unwrap().kind(),
+        ErrorKind::InvalidInput
+    );
+
+    let bad_distance = FilterChain::builder().filter(DeltaOptions::new(257));
+    assert_eq!(
+        bad_distance.build().err().unwrap().kind(),
+        ErrorKind::InvalidInput
+    );
+
+    let lzma2_first = FilterChain::builder().filter(FakeLZMA2);
+    assert_eq!(
+        lzma2_first.build().err().unwrap().kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn from_filter_flags_errors() {
+    let lzma2 = FilterFlags {
+        id: 0x21,
+        props: vec![0],
+    };
+    let flags = |id: u64, props: &[u8]| FilterFlags {
+        id,
+        props: props.to_vec(),
+    };
+    let cases = [
+        (vec![], ErrorKind::InvalidData),
+        (vec![lzma2.clone(); 5], ErrorKind::InvalidData),
+        (vec![flags(0x04, &[])], ErrorKind::Unsupported),
+        (vec![lzma2.clone(), lzma2.clone()], ErrorKind::Unsupported),
+        (
+            vec![flags(0x20, &[]), lzma2.clone()],
+            ErrorKind::Unsupported,
+        ),
+        (
+            vec![flags(0x04, &[0; 2]), lzma2.clone()],
+            ErrorKind::Unsupported,
+        ),
+        (
+            vec![flags(0x03, &[]), lzma2.clone()],
+            ErrorKind::Unsupported,
+        ),
+        (vec![flags(0x21, &[])], ErrorKind::Unsupported),
+    ];
+    for (filters, kind) in cases {
+        let err = FilterChain::from_filter_flags(&filters).err().unwrap();
+        assert_eq!(err.kind(), kind, "{:?}", filters);
+    }
+}
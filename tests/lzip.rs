@@ -0,0 +1,128 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{Read, Write};
+
+use lzma_rust::lzip::{decode_dict_size, encode_dict_size};
+use lzma_rust::{LzipOptions, LzipReader, LzipWriter};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+
+fn compress(data: &[u8], options: &LzipOptions) -> Vec<u8> {
+    let mut writer = LzipWriter::new(Vec::new(), options).unwrap();
+    writer.write_all(data).unwrap();
+    writer.finish().unwrap();
+    writer.into_inner()
+}
+
+/// `TEXT` compressed with preset 6 and 16 KiB members.
+fn text_lz() -> Vec<u8> {
+    let mut options = LzipOptions::with_preset(6);
+    options.member_data_size = Some(16 * 1024);
+    compress(TEXT, &options)
+}
+
+fn decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut reader = LzipReader::new(data)?;
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn members_round_trip() {
+    let compressed = text_lz();
+    assert_eq!(decompress(&compressed).unwrap(), TEXT);
+    let members = compressed.windows(4).filter(|w| w == b"LZIP").count();
+    assert_eq!(members, 3);
+}
+
+#[test]
+fn round_trip() {
+    for preset in [0, 3, 9] {
+        let options = LzipOptions::with_preset(preset);
+        assert_eq!(decompress(&compress(TEXT, &options)).unwrap(), TEXT);
+    }
+    let options = LzipOptions::with_preset(1);
+    let empty = compress(&[], &options);
+    assert_eq!(empty.len(), 36);
+    assert_eq!(decompress(&empty).unwrap(), b"");
+
+    let mut options = LzipOptions::with_preset(1);
+    options.lzma.dict_size = 100000;
+    let mut data = compress(TEXT, &options);
+    assert_eq!(decode_dict_size(data[5]).unwrap(), 104 << 10);
+    data.extend_from_slice(&empty);
+    assert_eq!(decompress(&data).unwrap(), TEXT);
+}
+
+#[test]
+fn dict_size_coding() {
+    assert_eq!(decode_dict_size(0x0C).unwrap(), 4 << 10);
+    assert_eq!(decode_dict_size(0x17).unwrap(), 8 << 20);
+    assert_eq!(decode_dict_size(0xD3).unwrap(), 320 << 10);
+    assert_eq!(decode_dict_size(0x94).unwrap(), 3 << 18);
+    assert_eq!(decode_dict_size(0x1D).unwrap(), 512 << 20);
+    assert!(decode_dict_size(0x0B).is_err());
+    assert!(decode_dict_size(0x1E).is_err());
+    assert!(decode_dict_size(0x2C).is_err());
+
+    for dict_size in [
+        1,
+        4096,
+        4097,
+        65536,
+        100000,
+        3 << 20,
+        (3 << 20) + 1,
+        512 << 20,
+    ] {
+        let coded = encode_dict_size(dict_size).unwrap();
+        let decoded = decode_dict_size(coded).unwrap() as u64;
+        assert!(decoded >= dict_size);
+        assert!(decoded - dict_size.max(4096) <= dict_size / 8);
+    }
+    assert_eq!(encode_dict_size(3 << 20).unwrap(), 0x96);
+    assert!(encode_dict_size((512 << 20) + 1).is_err());
+}
+
+#[test]
+fn corrupt_trailer() {
+    let text_lz = text_lz();
+    for pos in [text_lz.len() - 20, text_lz.len() - 16, text_lz.len() - 8] {
+        let mut data = text_lz.clone();
+        data[pos] ^= 0x01;
+        assert!(decompress(&data).is_err());
+    }
+    assert!(decompress(&text_lz[..text_lz.len() - 1]).is_err());
+
+    let mut data = text_lz.clone();
+    data.extend_from_slice(b"garbage");
+    assert!(decompress(&data).is_err());
+}
+
+#[test]
+fn into_inner_after_error() {
+    let text_lz = text_lz();
+    let mut reader = LzipReader::new_mem_limit(&text_lz[..], 1024).unwrap();
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    assert_eq!(reader.into_inner().len(), text_lz.len() - 6);
+
+    // The first byte of the LZMA data must be zero.
+    let mut data = text_lz.clone();
+    data[6] = 1;
+    let mut reader = LzipReader::new(&data[..]).unwrap();
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    reader.into_inner();
+
+    let mut reader = LzipReader::new(&text_lz[..8]).unwrap();
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    reader.into_inner();
+
+    // The CRC32 of the uncompressed data at the start of the trailer.
+    let compressed = compress(TEXT, &LzipOptions::with_preset(6));
+    let mut data = compressed.clone();
+    data[compressed.len() - 20] ^= 0x01;
+    let mut reader = LzipReader::new(&data[..]).unwrap();
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    assert_eq!(reader.into_inner().len(), 0);
+}
@@ -0,0 +1,206 @@
+#![cfg(all(feature = "encoder", feature = "alloc", not(feature = "no_std")))]
+
+use std::io::{Read, Write};
+
+use lzma_rust::lz::MFType;
+use lzma_rust::{CountingWriter, EncodeMode, LZMA2Options, LZMAHeader, LZMAReader, LZMAWriter};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+/// Compressed by `xz --format=lzma -6`.
+const TEXT_LZMA: &[u8] = include_bytes!("fixtures/text.lzma");
+
+fn compress(data: &[u8], options: &LZMA2Options, input_size: Option<u64>) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut writer =
+        LZMAWriter::new_use_header(CountingWriter::new(&mut out), options, input_size).unwrap();
+    writer.write_all(data).unwrap();
+    writer.finish().unwrap();
+    drop(writer);
+    out
+}
+
+fn decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut reader = LZMAReader::new_mem_limit(data, u64::MAX, None)?;
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn read_lzma_fixture() {
+    let header = LZMAHeader::parse(TEXT_LZMA[..13].try_into().unwrap()).unwrap();
+    assert_eq!((header.lc, header.lp, header.pb), (3, 0, 2));
+    assert_eq!(header.dict_size, 8 << 20);
+    assert_eq!(header.uncompressed_size, None);
+    assert_eq!(header.to_bytes(), TEXT_LZMA[..13]);
+    assert_eq!(header.memory_usage(), 8 * 1024 + 10 + 12);
+
+    assert_eq!(decompress(TEXT_LZMA).unwrap(), TEXT);
+    assert!(LZMAReader::new_mem_limit(TEXT_LZMA, 8 * 1024, None).is_err());
+}
+
+#[test]
+fn read_end_marker() {
+    // xz writes an unknown size and terminates the data with an end marker.
+    let mut reader =
+        LZMAReader::new_with_props(&TEXT_LZMA[13..], u64::MAX, TEXT_LZMA[0], 8 << 20, None)
+            .unwrap();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, TEXT);
+}
+
+#[test]
+fn write_in_pieces() {
+    let options = LZMA2Options::with_preset(6);
+    let mut out = Vec::new();
+    let mut writer =
+        LZMAWriter::new_no_header(CountingWriter::new(&mut out), &options, true).unwrap();
+    for piece in TEXT.chunks(1000) {
+        writer.write_all(piece).unwrap();
+    }
+    writer.finish().unwrap();
+    drop(writer);
+
+    let mut reader = LZMAReader::new_with_props(
+        &out[..],
+        u64::MAX,
+        options.get_props(),
+        options.dict_size,
+        None,
+    )
+    .unwrap();
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, TEXT);
+}
+
+#[test]
+fn round_trip() {
+    for preset in [0, 6, 9] {
+        let options = LZMA2Options::with_preset(preset);
+        for input_size in [None, Some(TEXT.len() as u64)] {
+            let compressed = compress(TEXT, &options, input_size);
+            let header = LZMAHeader::read_from(&mut &compressed[..]).unwrap();
+            assert_eq!(header.props(), options.get_props());
+            let mut expected = options.clone();
+            if let Some(input_size) = input_size {
+                expected.shrink_dict_size(input_size);
+            }
+            assert_eq!(header.dict_size as u64, expected.dict_size);
+            assert_eq!(header.uncompressed_size, input_size);
+            assert_eq!(decompress(&compressed).unwrap(), TEXT);
+        }
+    }
+    let options = LZMA2Options::with_preset(1);
+    assert_eq!(decompress(&compress(&[], &options, None)).unwrap(), b"");
+    assert_eq!(decompress(&compress(&[], &options, Some(0))).unwrap(), b"");
+}
+
+#[test]
+fn shrink_dict_size() {
+    let options = LZMA2Options::with_preset(9);
+    for (input_size, dict_size) in [
+        (0, 4096),
+        (4097, 6 << 10),
+        (40031, 48 << 10),
+        (1 << 30, 64 << 20),
+    ] {
+        let mut shrunk = options.clone();
+        shrunk.shrink_dict_size(input_size);
+        assert_eq!(shrunk.dict_size, dict_size);
+    }
+
+    let compressed = compress(TEXT, &options, Some(TEXT.len() as u64));
+    let header = LZMAHeader::read_from(&mut &compressed[..]).unwrap();
+    assert_eq!(header.dict_size, 48 << 10);
+    assert_eq!(decompress(&compressed).unwrap(), TEXT);
+
+    let mut with_preset_dict = options.clone();
+    with_preset_dict.preset_dict = Some(vec![0; 1 << 20]);
+    with_preset_dict.shrink_dict_size(40031);
+    assert_eq!(with_preset_dict.dict_size, 3 << 19);
+}
+
+#[test]
+fn match_finders() {
+    for mf in [
+        MFType::HC3,
+        MFType::HC4,
+        MFType::BT2,
+        MFType::BT3,
+        MFType::BT4,
+    ] {
+        for mode in [EncodeMode::Fast, EncodeMode::Normal] {
+            let mut options = LZMA2Options::with_preset(6);
+            options.mf = mf;
+            options.mode = mode;
+            let compressed = compress(TEXT, &options, None);
+            assert_eq!(
+                decompress(&compressed).unwrap(),
+                TEXT,
+                "{:?} {:?}",
+                mf,
+                mode
+            );
+        }
+    }
+
+    let memory_usage = |mf| {
+        let mut options = LZMA2Options::with_preset(6);
+        options.mf = mf;
+        options.get_memory_usage()
+    };
+    assert!(memory_usage(MFType::HC3) < memory_usage(MFType::BT3));
+    assert!(memory_usage(MFType::BT2) < memory_usage(MFType::BT3));
+    assert!(memory_usage(MFType::HC3) < memory_usage(MFType::HC4));
+}
+
+#[test]
+fn extreme_presets() {
+    // The settings that `xz -vv -<preset>e` prints.
+    for preset in 0..=9 {
+        let options = LZMA2Options::with_preset_extreme(preset);
+        let (nice_len, depth_limit) = if preset == 3 || preset == 5 {
+            (192, 0)
+        } else {
+            (273, 512)
+        };
+        assert_eq!(options.mode, EncodeMode::Normal);
+        assert_eq!(options.mf, MFType::BT4);
+        assert_eq!(options.nice_len, nice_len);
+        assert_eq!(options.depth_limit, depth_limit);
+        assert_eq!(
+            options.dict_size,
+            LZMA2Options::with_preset(preset).dict_size
+        );
+    }
+
+    let compressed = compress(TEXT, &LZMA2Options::with_preset_extreme(6), None);
+    assert_eq!(decompress(&compressed).unwrap(), TEXT);
+    assert!(compressed.len() <= compress(TEXT, &LZMA2Options::with_preset(6), None).len());
+}
+
+#[test]
+fn options() {
+    // Unlike LZMA2, .lzma allows lc + lp > 4.
+    let mut options = LZMA2Options::with_preset(6);
+    options.lc = 8;
+    options.lp = 4;
+    let compressed = compress(TEXT, &options, None);
+    assert_eq!(decompress(&compressed).unwrap(), TEXT);
+
+    options.nice_len = LZMA2Options::NICE_LEN_MAX + 1;
+    let err = LZMAWriter::new_use_header(CountingWriter::new(Vec::new()), &options, None)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn invalid_header() {
+    let mut header = [0u8; 13];
+    header[0] = 225;
+    assert!(LZMAHeader::parse(&header).is_err());
+    assert!(LZMAHeader::read_from(&mut &header[..5]).is_err());
+}
@@ -0,0 +1,352 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{ErrorKind, Read, Write};
+
+use lzma_rust::lz::MFType;
+use lzma_rust::{
+    CountingWriter, EncodeMode, FilterChain, FlushMode, LZMA2MTOptions, LZMA2MTWriter,
+    LZMA2Options, LZMA2OptionsBuilder, LZMA2Reader, LZMA2Writer, XZOptions, XZWriter,
+};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+/// Compressed by `xz --format=raw --lzma2=preset=6`.
+const TEXT_LZMA2: &[u8] = include_bytes!("fixtures/text.lzma2");
+
+fn decompress(data: &[u8], options: &LZMA2Options) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    LZMA2Reader::new(data, options.dict_size, options.preset_dict.as_deref())
+        .read_to_end(&mut output)?;
+    Ok(output)
+}
+
+#[test]
+fn read_raw_fixture() {
+    let options = LZMA2Options::with_preset(6);
+    assert_eq!(decompress(TEXT_LZMA2, &options).unwrap(), TEXT);
+}
+
+/// Compresses `TEXT`, flushing with `mode` at every boundary. Returns the stream and the
+/// offsets returned by the flushes.
+fn compress_flushed(
+    options: &LZMA2Options,
+    mode: FlushMode,
+    boundaries: &[usize],
+) -> (Vec<u8>, Vec<usize>) {
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), options).unwrap();
+    let mut offsets = Vec::new();
+    let mut start = 0;
+    for &end in boundaries {
+        writer.write_all(&TEXT[start..end]).unwrap();
+        offsets.push(writer.flush_mode(mode).unwrap() as usize);
+        start = end;
+    }
+    writer.write_all(&TEXT[start..]).unwrap();
+    writer.finish().unwrap();
+    (writer.inner.inner, offsets)
+}
+
+#[test]
+fn sync_flush() {
+    let options = LZMA2Options::with_preset(6);
+    let boundaries = [1000, 1001, 30000];
+    let (compressed, offsets) = compress_flushed(&options, FlushMode::Sync, &boundaries);
+    assert_eq!(decompress(&compressed, &options).unwrap(), TEXT);
+
+    for (&offset, &end) in offsets.iter().zip(&boundaries) {
+        // The chunk after a sync flush continues with the same dictionary.
+        assert!(compressed[offset] == 0x02 || (0x80..0xE0).contains(&compressed[offset]));
+        assert_eq!(
+            decompress(&[&compressed[..offset], &[0x00]].concat(), &options).unwrap(),
+            &TEXT[..end]
+        );
+    }
+}
+
+#[test]
+fn full_flush() {
+    let boundaries = [1, 5000, 5001, 40000];
+    for mf in [
+        MFType::HC3,
+        MFType::HC4,
+        MFType::BT2,
+        MFType::BT3,
+        MFType::BT4,
+    ] {
+        for mode in [EncodeMode::Fast, EncodeMode::Normal] {
+            let mut options = LZMA2Options::with_preset(6);
+            options.mf = mf;
+            options.mode = mode;
+            let (compressed, offsets) = compress_flushed(&options, FlushMode::Full, &boundaries);
+            assert_eq!(decompress(&compressed, &options).unwrap(), TEXT);
+
+            for (&offset, &end) in offsets.iter().zip(&boundaries) {
+                assert!(compressed[offset] == 0x01 || compressed[offset] >= 0xE0);
+                let output = decompress(&compressed[offset..], &options).unwrap();
+                assert_eq!(output, &TEXT[end..], "{mf:?} {mode:?} {end}");
+            }
+        }
+    }
+}
+
+#[test]
+fn full_flush_drops_preset_dict() {
+    let mut options = LZMA2Options::with_preset(6);
+    options.preset_dict = Some(TEXT[..20000].to_vec());
+    let (compressed, offsets) = compress_flushed(&options, FlushMode::Full, &[0, 3000]);
+    assert_eq!(decompress(&compressed, &options).unwrap(), TEXT);
+    let output = decompress(&compressed[offsets[0]..], &LZMA2Options::with_preset(6)).unwrap();
+    assert_eq!(output, TEXT);
+}
+
+#[test]
+fn flush_after_finish() {
+    let mut writer =
+        LZMA2Writer::new(CountingWriter::new(Vec::new()), &LZMA2Options::default()).unwrap();
+    writer.write_all(TEXT).unwrap();
+    writer.finish().unwrap();
+    assert!(writer.flush_mode(FlushMode::Full).is_err());
+    assert!(writer.flush().is_err());
+}
+
+#[test]
+fn wrapping_match() {
+    // Matches at nearly the full dictionary distance make the decoder copy across the end of
+    // its cyclic buffer with overlapping source and destination ranges.
+    let mut options = LZMA2Options::with_preset(6);
+    options.dict_size = lzma_rust::DICT_SIZE_MIN;
+    let mut seed = 1u32;
+    let block: Vec<u8> = (0..options.dict_size - 2)
+        .map(|_| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            (seed >> 16) as u8
+        })
+        .collect();
+    let data = block.repeat(8);
+
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(decompress(&writer.inner.inner, &options).unwrap(), data);
+}
+
+#[test]
+fn long_run() {
+    // A run much longer than the dictionary makes the encoder move more data to the start of
+    // its buffer than it moves it by.
+    let mut options = LZMA2Options::with_preset(0);
+    options.dict_size = 1 << 20;
+    let data = vec![0; 8 << 20];
+
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    writer.write_all(&data).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(decompress(&writer.inner.inner, &options).unwrap(), data);
+}
+
+#[test]
+fn memory_usage() {
+    // The value is in KiB. xz needs about 94 MiB for preset 6, the match finders here store
+    // 8 byte positions instead of 4.
+    let usage = LZMA2Options::with_preset(6).get_memory_usage();
+    assert!((96 << 10..256 << 10).contains(&usage), "{usage}");
+    let mut previous = 0;
+    for preset in 0..=9 {
+        let usage = LZMA2Options::with_preset(preset).get_memory_usage();
+        assert!(usage >= previous, "{preset}");
+        previous = usage;
+    }
+}
+
+#[test]
+fn fit_memory_limit() {
+    let mut options = LZMA2Options::with_preset(6);
+    let usage = options.get_memory_usage();
+    options.fit_memory_limit(usage).unwrap();
+    assert_eq!(options.dict_size, 8 << 20);
+
+    options.fit_memory_limit(usage / 4).unwrap();
+    assert!(options.get_memory_usage() <= usage / 4);
+    assert_eq!(options.mf, MFType::BT4);
+    assert_eq!(options.dict_size, 3 << 19);
+
+    let mut min_bt4 = options.clone();
+    min_bt4.dict_size = lzma_rust::DICT_SIZE_MIN;
+    let limit = min_bt4.get_memory_usage() - 1;
+    options.fit_memory_limit(limit).unwrap();
+    assert!(options.get_memory_usage() <= limit);
+    assert_eq!(options.mf, MFType::HC4);
+
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    writer.write_all(TEXT).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(decompress(&writer.inner.inner, &options).unwrap(), TEXT);
+
+    let mut options = LZMA2Options::with_preset(6);
+    let err = options.fit_memory_limit(1024).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+    assert_eq!(options.dict_size, 8 << 20);
+    assert_eq!(options.mf, MFType::BT4);
+}
+
+#[test]
+fn builder() {
+    let options = LZMA2Options::builder()
+        .dict_size(1 << 16)
+        .lc(0)
+        .lp(4)
+        .pb(0)
+        .mode(EncodeMode::Fast)
+        .nice_len(LZMA2Options::NICE_LEN_MIN)
+        .mf(MFType::HC3)
+        .depth_limit(100)
+        .preset_dict(TEXT[..100].to_vec())
+        .build()
+        .unwrap();
+    assert_eq!(options.dict_size, 1 << 16);
+    assert_eq!((options.lc, options.lp, options.pb), (0, 4, 0));
+    assert_eq!(options.mode, EncodeMode::Fast);
+    assert_eq!(options.nice_len, 8);
+    assert_eq!(options.mf, MFType::HC3);
+    assert_eq!(options.depth_limit, 100);
+    assert_eq!(options.preset_dict.as_deref(), Some(&TEXT[..100]));
+
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    writer.write_all(TEXT).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(decompress(&writer.inner.inner, &options).unwrap(), TEXT);
+}
+
+#[test]
+fn invalid_options() {
+    let preset = || LZMA2OptionsBuilder::from(LZMA2Options::with_preset(6));
+    assert!(preset().dict_size(lzma_rust::DICT_SIZE_MIN).build().is_ok());
+    let invalid = [
+        preset().lc(3).lp(2),
+        preset().lc(9).lp(0),
+        preset().lc(0).lp(5),
+        preset().pb(5),
+        preset().nice_len(LZMA2Options::NICE_LEN_MIN - 1),
+        preset().nice_len(LZMA2Options::NICE_LEN_MAX + 1),
+        preset().dict_size(lzma_rust::DICT_SIZE_MIN - 1),
+        preset().dict_size(LZMA2Options::DICT_SIZE_MAX + 1),
+    ];
+    for builder in invalid {
+        assert_eq!(builder.build().unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+
+    // The writers check the options before compressing anything.
+    let mut options = LZMA2Options::with_preset(6);
+    options.lc = 4;
+    options.lp = 4;
+    let err = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options)
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let mut xz = XZOptions::with_preset(6);
+    xz.lzma2 = options.clone();
+    let err = XZWriter::new(Vec::new(), &xz).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let mt = LZMA2MTOptions::new(options.clone(), 2);
+    let err = LZMA2MTWriter::new(Vec::new(), &mt).err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+
+    let err = FilterChain::builder().lzma2(options).build().err().unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+}
+
+#[test]
+fn option_strings() {
+    // The filter chains that `xz -vv` prints for these options.
+    for (input, printed) in [
+        (
+            "",
+            "dict=8MiB,lc=3,lp=0,pb=2,mode=normal,nice=64,mf=bt4,depth=0",
+        ),
+        (
+            "preset=6e,dict=3MiB",
+            "dict=3MiB,lc=3,lp=0,pb=2,mode=normal,nice=273,mf=bt4,depth=512",
+        ),
+        (
+            "dict=65537,lc=4,lp=0,pb=0,mf=hc3,mode=fast,nice=128,depth=7",
+            "dict=65537,lc=4,lp=0,pb=0,mode=fast,nice=128,mf=hc3,depth=7",
+        ),
+        (
+            "preset=6,preset=3",
+            "dict=4MiB,lc=3,lp=0,pb=2,mode=fast,nice=273,mf=hc4,depth=48",
+        ),
+        (
+            "lc=1,preset=9e",
+            "dict=64MiB,lc=3,lp=0,pb=2,mode=normal,nice=273,mf=bt4,depth=512",
+        ),
+        (
+            " lc = 1 ,, lp=0,",
+            "dict=8MiB,lc=1,lp=0,pb=2,mode=normal,nice=64,mf=bt4,depth=0",
+        ),
+    ] {
+        let options: LZMA2Options = input.parse().unwrap();
+        assert_eq!(options.to_string(), printed, "{input}");
+    }
+
+    for (dict, size) in [
+        ("64M", 64 << 20),
+        ("64MB", 64 << 20),
+        ("64Mi", 64 << 20),
+        ("64MiB", 64 << 20),
+        ("64k", 64 << 10),
+        ("64KiB", 64 << 10),
+        ("1g", 1 << 30),
+        ("4096", 4096),
+    ] {
+        let options: LZMA2Options = format!("dict={dict}").parse().unwrap();
+        assert_eq!(options.dict_size, size, "{dict}");
+    }
+
+    for preset in 0..=9 {
+        for options in [
+            LZMA2Options::with_preset(preset),
+            LZMA2Options::with_preset_extreme(preset),
+        ] {
+            let parsed: LZMA2Options = options.to_string().parse().unwrap();
+            assert_eq!(parsed.to_string(), options.to_string());
+            assert_eq!(parsed.dict_size, options.dict_size);
+            assert_eq!(parsed.mf, options.mf);
+        }
+    }
+
+    let options = LZMA2Options {
+        depth_limit: -1,
+        ..LZMA2Options::with_preset(1)
+    };
+    options.validate().unwrap();
+    let printed = options.to_string();
+    assert!(printed.ends_with(",depth=0"), "{printed}");
+    let parsed: LZMA2Options = printed.parse().unwrap();
+    assert_eq!(parsed.depth_limit, 0);
+    assert_eq!(parsed.to_string(), printed);
+
+    for invalid in [
+        "lc",
+        "LC=1",
+        "foo=1",
+        "mode=Fast",
+        "mf=bt5",
+        "preset=6E",
+        "preset=10",
+        "preset=e",
+        "lc=+1",
+        "dict=0x1000",
+        "dict=64MiBx",
+        "dict=",
+        "nice=abc",
+        "depth=-1",
+        "dict=99999999999999999999",
+        "dict=17179869184G",
+        "lc=3,lp=2",
+        "nice=300",
+    ] {
+        let err = invalid.parse::<LZMA2Options>().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput, "{invalid}");
+    }
+}
@@ -0,0 +1,229 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{ErrorKind, Read, Write};
+
+use lzma_rust::{
+    CountingWriter, LZMA2MTOptions, LZMA2MTReader, LZMA2MTWriter, LZMA2Options, LZMA2Reader,
+    LZMA2Writer,
+};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+
+fn options(threads: usize, block_size: u64) -> LZMA2MTOptions {
+    let mut options = LZMA2MTOptions::new(LZMA2Options::with_preset(6), threads);
+    options.block_size = block_size;
+    options
+}
+
+fn compress(data: &[u8], options: &LZMA2MTOptions, chunk_size: usize) -> Vec<u8> {
+    let mut writer = LZMA2MTWriter::new(Vec::new(), options).unwrap();
+    for chunk in data.chunks(chunk_size) {
+        writer.write_all(chunk).unwrap();
+    }
+    assert_eq!(writer.write(&[]).unwrap(), 0);
+    writer.into_inner()
+}
+
+fn decompress(data: &[u8], options: &LZMA2Options) -> Vec<u8> {
+    let mut output = Vec::new();
+    LZMA2Reader::new(data, options.dict_size, options.preset_dict.as_deref())
+        .read_to_end(&mut output)
+        .unwrap();
+    output
+}
+
+#[test]
+fn round_trip() {
+    for (threads, block_size) in [(1, 4096), (3, 5000), (4, 1 << 20)] {
+        let options = options(threads, block_size);
+        for chunk_size in [1000, 4096, TEXT.len()] {
+            let compressed = compress(TEXT, &options, chunk_size);
+            assert_eq!(decompress(&compressed, &options.lzma2), TEXT);
+        }
+    }
+}
+
+#[test]
+fn output_is_independent_of_threads() {
+    let expected = compress(TEXT, &options(1, 8192), TEXT.len());
+    for threads in [2, 4, 8] {
+        let mut options = options(threads, 8192);
+        for max_in_flight in [1, 2, 16] {
+            options.max_in_flight = max_in_flight;
+            assert_eq!(compress(TEXT, &options, 3000), expected);
+        }
+    }
+}
+
+#[test]
+fn blocks_reset_dictionary() {
+    // Every block is compressed on its own, so it compresses into the same chunks as the
+    // start of a new stream.
+    let options = options(2, 8192);
+    let compressed = compress(TEXT, &options, TEXT.len());
+    let second_block = compress(&TEXT[8192..16384], &options, TEXT.len());
+    let second_block = &second_block[..second_block.len() - 1];
+    let first_block = compress(&TEXT[..8192], &options, TEXT.len());
+    let first_block = &first_block[..first_block.len() - 1];
+    assert!(compressed.starts_with(first_block));
+    assert!(compressed[first_block.len()..].starts_with(second_block));
+}
+
+#[test]
+fn preset_dict() {
+    let mut options = options(2, 4096);
+    options.lzma2.preset_dict = Some(TEXT[..10000].to_vec());
+    let compressed = compress(&TEXT[10000..], &options, 1000);
+    assert_eq!(decompress(&compressed, &options.lzma2), &TEXT[10000..]);
+}
+
+#[test]
+fn flush() {
+    let options = options(2, 1 << 20);
+    let mut writer = LZMA2MTWriter::new(Vec::new(), &options).unwrap();
+    writer.write_all(&TEXT[..1000]).unwrap();
+    writer.flush().unwrap();
+    let flushed = writer.get_ref().len();
+    assert!(flushed > 0);
+
+    let mut reader = LZMA2Reader::new(&writer.get_ref()[..], options.lzma2.dict_size, None);
+    let mut output = vec![0; 1000];
+    reader.read_exact(&mut output).unwrap();
+    assert_eq!(output, &TEXT[..1000]);
+
+    writer.write_all(&TEXT[1000..]).unwrap();
+    writer.finish().unwrap();
+    assert!(writer.write(b"x").is_err());
+    assert_eq!(decompress(&writer.into_inner(), &options.lzma2), TEXT);
+}
+
+#[test]
+fn empty() {
+    let compressed = compress(&[], &options(2, 4096), 1);
+    assert_eq!(compressed, [0x00]);
+}
+
+#[test]
+fn invalid_options() {
+    let mut invalid = [options(0, 4096), options(2, 0), options(2, 4096)];
+    invalid[2].max_in_flight = 0;
+    for options in invalid {
+        let err = LZMA2MTWriter::new(Vec::new(), &options).err().unwrap();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+}
+
+fn decompress_mt(data: &[u8], options: &LZMA2Options, threads: usize) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    let preset_dict = options.preset_dict.as_deref();
+    LZMA2MTReader::new(data, options.dict_size, preset_dict, threads).read_to_end(&mut output)?;
+    Ok(output)
+}
+
+#[test]
+fn read_parallel() {
+    for block_size in [4096, 5000, 1 << 20] {
+        let options = options(4, block_size);
+        let compressed = compress(TEXT, &options, TEXT.len());
+        for threads in [0, 1, 2, 8] {
+            let output = decompress_mt(&compressed, &options.lzma2, threads).unwrap();
+            assert_eq!(output, TEXT);
+        }
+    }
+}
+
+#[test]
+fn read_parallel_preset_dict() {
+    let mut options = options(2, 4096);
+    options.lzma2.preset_dict = Some(TEXT[..10000].to_vec());
+    let compressed = compress(&TEXT[10000..], &options, TEXT.len());
+    let output = decompress_mt(&compressed, &options.lzma2, 4).unwrap();
+    assert_eq!(output, &TEXT[10000..]);
+}
+
+#[test]
+fn read_single_reset() {
+    let options = LZMA2Options::with_preset(6);
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    writer.write_all(TEXT).unwrap();
+    writer.finish().unwrap();
+    let compressed = writer.inner.inner;
+    assert_eq!(decompress_mt(&compressed, &options, 4).unwrap(), TEXT);
+}
+
+#[test]
+fn read_stops_at_end_marker() {
+    let options = options(2, 4096);
+    let mut compressed = compress(TEXT, &options, TEXT.len());
+    compressed.extend_from_slice(b"trailing");
+    let mut reader = LZMA2MTReader::new(&compressed[..], options.lzma2.dict_size, None, 2);
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).unwrap();
+    assert_eq!(output, TEXT);
+    assert_eq!(reader.into_inner(), b"trailing");
+}
+
+#[test]
+fn read_corrupt() {
+    let options = options(2, 4096);
+    let compressed = compress(TEXT, &options, TEXT.len());
+    let truncated = &compressed[..compressed.len() / 2];
+    assert!(decompress_mt(truncated, &options.lzma2, 2).is_err());
+
+    let mut corrupt = compressed.clone();
+    let middle = corrupt.len() / 2;
+    corrupt[middle] ^= 0xFF;
+    let mut reader = LZMA2MTReader::new(&corrupt[..], options.lzma2.dict_size, None, 2);
+    let mut output = Vec::new();
+    let err = reader.read_to_end(&mut output).err().unwrap();
+    assert!(reader.read(&mut [0; 16]).is_err());
+    assert_ne!(err.kind(), ErrorKind::Other);
+
+    assert_eq!(
+        decompress_mt(&[0x03], &options.lzma2, 2)
+            .err()
+            .unwrap()
+            .kind(),
+        ErrorKind::InvalidInput
+    );
+}
+
+#[test]
+fn read_large_segments() {
+    let single = LZMA2Options::with_preset(6);
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &single).unwrap();
+    writer.write_all(TEXT).unwrap();
+    writer.finish().unwrap();
+    let mut single_reset = writer.inner.inner;
+    single_reset.extend_from_slice(b"trailing");
+
+    let mut options = options(2, 16384);
+    options.lzma2.preset_dict = Some(TEXT[..10000].to_vec());
+    let mut multi_reset = compress(TEXT, &options, TEXT.len());
+    multi_reset.extend_from_slice(b"trailing");
+
+    for (compressed, lzma2) in [(single_reset, single), (multi_reset, options.lzma2)] {
+        for segment_size_max in [0, 4096, 1 << 20] {
+            let preset_dict = lzma2.preset_dict.as_deref();
+            let mut reader = LZMA2MTReader::new(&compressed[..], lzma2.dict_size, preset_dict, 4);
+            reader.set_segment_size_max(segment_size_max);
+            let mut output = Vec::new();
+            reader.read_to_end(&mut output).unwrap();
+            assert_eq!(output, TEXT);
+            assert_eq!(reader.into_inner(), b"trailing");
+        }
+    }
+}
+
+#[test]
+fn read_huge_chunk_sizes() {
+    // Chunks that claim 2 MiB each, about 400 GB in total, but hold garbage.
+    let mut compressed = vec![0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+    for _ in 0..200000 {
+        compressed.extend_from_slice(&[0xDF, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00]);
+    }
+    compressed.push(0x00);
+    for threads in [1, 4] {
+        assert!(decompress_mt(&compressed, &LZMA2Options::default(), threads).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+// Kept apart from the other tests, which would add to the memory use of the process.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::io::{Read, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use lzma_rust::{CountingWriter, LZMA2MTReader, LZMA2Options, LZMA2Writer};
+
+struct PeakAlloc {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let size = self.current.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        self.peak.fetch_max(size, Ordering::SeqCst);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.current.fetch_sub(layout.size(), Ordering::SeqCst);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOC: PeakAlloc = PeakAlloc {
+    current: AtomicUsize::new(0),
+    peak: AtomicUsize::new(0),
+};
+
+#[test]
+fn read_single_reset_in_bounded_memory() {
+    const SIZE: usize = 64 << 20;
+    let mut options = LZMA2Options::with_preset(0);
+    options.dict_size = 1 << 20;
+    let zeros = vec![0; 1 << 20];
+    let mut writer = LZMA2Writer::new(CountingWriter::new(Vec::new()), &options).unwrap();
+    for _ in 0..SIZE / zeros.len() {
+        writer.write_all(&zeros).unwrap();
+    }
+    writer.finish().unwrap();
+    let compressed = writer.inner.inner;
+    drop(zeros);
+
+    let before = ALLOC.current.load(Ordering::SeqCst);
+    ALLOC.peak.store(before, Ordering::SeqCst);
+    let mut reader = LZMA2MTReader::new(&compressed[..], options.dict_size, None, 4);
+    let mut buf = [0u8; 4096];
+    let mut total = 0;
+    loop {
+        let size = reader.read(&mut buf).unwrap();
+        if size == 0 {
+            break;
+        }
+        assert!(buf[..size].iter().all(|&b| b == 0));
+        total += size;
+    }
+    assert_eq!(total, SIZE);
+    // The dictionary and at most a few buffered segments of 3 MiB, instead of all the output.
+    let peak = ALLOC.peak.load(Ordering::SeqCst) - before;
+    assert!(peak < 16 << 20, "peak allocation of {peak} bytes");
+}
@@ -0,0 +1,68 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::Read;
+
+use lzma_rust::{microlzma_compress, LZMA2Options, MicroLZMAReader};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+/// Created by liblzma's `lzma_microlzma_encoder` (xz 5.8) with preset 6 and a 1000 byte
+/// output buffer, which holds the first 4278 bytes of the text.
+const TEXT_1000_MICROLZMA: &[u8] = include_bytes!("fixtures/text-1000.microlzma");
+
+fn decompress(data: &[u8], uncompressed_size: usize, dict_size: u64) -> Vec<u8> {
+    let mut reader =
+        MicroLZMAReader::new(data, data.len() as u64, uncompressed_size as u64, dict_size).unwrap();
+    let mut output = Vec::new();
+    reader.read_to_end(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn read_fixture() {
+    let dict_size = LZMA2Options::with_preset(6).dict_size;
+    assert_eq!(
+        decompress(TEXT_1000_MICROLZMA, 4278, dict_size),
+        &TEXT[..4278]
+    );
+}
+
+#[test]
+fn round_trip() {
+    for preset in [0, 6] {
+        let options = LZMA2Options::with_preset(preset);
+        let mut previous = 0;
+        for size in [5, 6, 20, 100, 1000, 4096] {
+            let mut output = vec![0u8; size];
+            let (consumed, written) = microlzma_compress(TEXT, &mut output, &options).unwrap();
+            assert!(written <= size && consumed >= previous);
+            let output = &output[..written];
+            assert_eq!(
+                decompress(output, consumed, options.dict_size),
+                &TEXT[..consumed]
+            );
+            previous = consumed;
+        }
+    }
+}
+
+#[test]
+fn whole_input() {
+    let options = LZMA2Options::with_preset(6);
+    let mut output = vec![0u8; TEXT.len()];
+    let (consumed, written) = microlzma_compress(TEXT, &mut output, &options).unwrap();
+    assert_eq!(consumed, TEXT.len());
+    assert!(written < TEXT.len() / 2);
+    assert_eq!(
+        decompress(&output[..written], consumed, options.dict_size),
+        TEXT
+    );
+
+    let (consumed, written) = microlzma_compress(&[], &mut output, &options).unwrap();
+    assert_eq!((consumed, written), (0, 5));
+}
+
+#[test]
+fn output_too_small() {
+    let mut output = [0u8; 4];
+    assert!(microlzma_compress(TEXT, &mut output, &LZMA2Options::default()).is_err());
+}
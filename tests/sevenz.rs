@@ -0,0 +1,230 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{Cursor, ErrorKind, Read};
+
+use lzma_rust::check::{Check, Crc32};
+use lzma_rust::sevenz::method_id;
+use lzma_rust::{SevenZEntry, SevenZOptions, SevenZReader, SevenZWriter};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+/// Created by `bsdtar --format 7zip --options 7zip:compression=<coder>` (libarchive 3.8.2) from
+/// `a.txt` ("hello\n"), an empty file `empty` and `dir/text.txt`, all with the mtime
+/// 2024-01-02 03:04:05 UTC. The headers of all but the Copy archive are compressed.
+///
+/// None of the fixtures were made by 7-Zip itself, and neither has the output of
+/// [SevenZWriter] been checked with `7z t` yet: 7-Zip wasn't available where these tests were
+/// written. The written archives are checked against the format in [write_structure] and
+/// were extracted with `bsdtar -xf`.
+const TEXT_LZMA2_7Z: &[u8] = include_bytes!("fixtures/text-lzma2.7z");
+const TEXT_LZMA_7Z: &[u8] = include_bytes!("fixtures/text-lzma.7z");
+const TEXT_COPY_7Z: &[u8] = include_bytes!("fixtures/text-copy.7z");
+const TEXT_BZIP2_7Z: &[u8] = include_bytes!("fixtures/text-bzip2.7z");
+/// A Copy-coded encoded header whose packed stream is the encoded header itself.
+const ENCODED_HEADER_LOOP_7Z: &[u8] = include_bytes!("fixtures/encoded-header-loop.7z");
+
+/// Windows FILETIME of 2024-01-02 03:04:05 UTC.
+const MTIME: u64 = (1704164645 + 11644473600) * 10_000_000;
+
+fn extract(data: &[u8]) -> std::io::Result<Vec<(String, bool, Vec<u8>)>> {
+    let mut reader = SevenZReader::new(Cursor::new(data))?;
+    let mut files = Vec::new();
+    reader.for_each_entry(|entry, data| {
+        let mut content = Vec::new();
+        data.read_to_end(&mut content)?;
+        files.push((entry.name.clone(), entry.is_directory, content));
+        Ok(true)
+    })?;
+    files.sort();
+    Ok(files)
+}
+
+#[test]
+fn read_fixtures() {
+    for data in [TEXT_LZMA2_7Z, TEXT_LZMA_7Z, TEXT_COPY_7Z] {
+        let files = extract(data).unwrap();
+        let expected = vec![
+            ("a.txt".to_string(), false, b"hello\n".to_vec()),
+            ("dir".to_string(), true, Vec::new()),
+            ("dir/text.txt".to_string(), false, TEXT.to_vec()),
+            ("empty".to_string(), false, Vec::new()),
+        ];
+        assert_eq!(files, expected);
+    }
+}
+
+#[test]
+fn entries() {
+    let reader = SevenZReader::new(Cursor::new(TEXT_LZMA2_7Z)).unwrap();
+    let entry = reader
+        .entries()
+        .iter()
+        .find(|e| e.name == "dir/text.txt")
+        .unwrap();
+    assert!(entry.has_stream);
+    assert_eq!(entry.size, TEXT.len() as u64);
+    assert!(entry.crc.is_some());
+    assert_eq!(entry.mtime, Some(MTIME));
+
+    let empty = reader.entries().iter().find(|e| e.name == "empty").unwrap();
+    assert!(!empty.has_stream && !empty.is_directory);
+}
+
+#[test]
+fn stop_and_skip() {
+    let mut reader = SevenZReader::new(Cursor::new(TEXT_LZMA_7Z)).unwrap();
+    let mut names = Vec::new();
+    reader
+        .for_each_entry(|entry, _| {
+            names.push(entry.name.clone());
+            Ok(names.len() < 3)
+        })
+        .unwrap();
+    assert_eq!(names.len(), 3);
+}
+
+#[test]
+fn unsupported_coder() {
+    let err = extract(TEXT_BZIP2_7Z).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    assert!(err.to_string().contains("Unsupported 7z coder"));
+}
+
+#[test]
+fn corrupt_data() {
+    let mut data = TEXT_COPY_7Z.to_vec();
+    // The packed streams start right after the signature header.
+    data[40] ^= 1;
+    let err = extract(&data).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+    let mut data = TEXT_LZMA2_7Z.to_vec();
+    data[20] ^= 1;
+    assert!(SevenZReader::new(Cursor::new(data)).is_err());
+}
+
+#[test]
+fn nested_encoded_header() {
+    let err = SevenZReader::new(Cursor::new(ENCODED_HEADER_LOOP_7Z))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+}
+
+fn unix_attributes(mode: u32) -> Option<u32> {
+    let directory = if mode & 0o040000 != 0 { 0x10 } else { 0x20 };
+    Some(directory | 0x8000 | mode << 16)
+}
+
+fn archive(options: &SevenZOptions) -> Vec<u8> {
+    let mut writer = SevenZWriter::new(Cursor::new(Vec::new()), options).unwrap();
+    let file = |name: &str| SevenZEntry {
+        name: name.to_string(),
+        mtime: Some(MTIME),
+        attributes: unix_attributes(0o100644),
+        ..Default::default()
+    };
+    writer
+        .push_entry(file("a.txt"), Some(&b"hello\n"[..]))
+        .unwrap();
+    let dir = SevenZEntry {
+        is_directory: true,
+        attributes: unix_attributes(0o040755),
+        ..file("dir")
+    };
+    writer.push_entry(dir, None::<&[u8]>).unwrap();
+    writer.push_entry(file("dir/text.txt"), Some(TEXT)).unwrap();
+    writer.push_entry(file("empty"), Some(&b""[..])).unwrap();
+    writer.finish().unwrap();
+    writer.into_inner().into_inner()
+}
+
+#[test]
+fn write_structure() {
+    let data = archive(&SevenZOptions::with_preset(6));
+
+    // The signature header, checked byte by byte instead of with the decoder of the reader.
+    assert_eq!(data[..8], [b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C, 0, 4]);
+    let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+    let u64_at = |pos: usize| u64::from_le_bytes(data[pos..pos + 8].try_into().unwrap());
+    assert_eq!(u32_at(8), Crc32::checksum(&data[12..32]));
+    let next_header = 32 + u64_at(12) as usize;
+    let next_header_end = next_header + u64_at(20) as usize;
+    assert_eq!(next_header_end, data.len());
+    assert_eq!(u32_at(28), Crc32::checksum(&data[next_header..]));
+
+    let reader = SevenZReader::new(Cursor::new(&data)).unwrap();
+    let archive = reader.archive();
+    assert_eq!(archive.folders.len(), 1);
+    let folder = &archive.folders[0];
+    assert_eq!(folder.coders.len(), 1);
+    assert_eq!(folder.coders[0].method_id, method_id::LZMA2);
+    assert_eq!(folder.num_unpack_streams, 2);
+    assert_eq!(folder.unpack_size(), 6 + TEXT.len() as u64);
+    assert_eq!(archive.pack_sizes.len(), 1);
+    assert!(32 + archive.pack_pos + archive.pack_sizes[0] <= next_header as u64);
+
+    let names: Vec<_> = reader.entries().iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["a.txt", "dir", "dir/text.txt", "empty"]);
+    for (entry, content) in reader
+        .entries()
+        .iter()
+        .zip([&b"hello\n"[..], b"", TEXT, b""])
+    {
+        assert_eq!(entry.has_stream, !content.is_empty());
+        assert_eq!(entry.size, content.len() as u64);
+        let crc = Some(Crc32::checksum(content)).filter(|_| entry.has_stream);
+        assert_eq!(entry.crc, crc, "{}", entry.name);
+    }
+}
+
+#[test]
+fn round_trip() {
+    let expected = extract(TEXT_LZMA2_7Z).unwrap();
+    for solid in [true, false] {
+        let mut options = SevenZOptions::with_preset(1);
+        options.solid = solid;
+        let data = archive(&options);
+        assert_eq!(extract(&data).unwrap(), expected);
+
+        let reader = SevenZReader::new(Cursor::new(&data)).unwrap();
+        assert_eq!(reader.archive().folders.len(), if solid { 1 } else { 2 });
+        let dir = &reader.entries()[1];
+        assert!(dir.is_directory);
+        assert_eq!(dir.mtime, Some(MTIME));
+        assert_eq!(dir.attributes, unix_attributes(0o040755));
+    }
+}
+
+#[test]
+fn empty_archive() {
+    let mut writer = SevenZWriter::new(Cursor::new(Vec::new()), &SevenZOptions::default()).unwrap();
+    writer.finish().unwrap();
+    let data = writer.into_inner().into_inner();
+    assert_eq!(data.len(), 32);
+    assert!(extract(&data).unwrap().is_empty());
+}
+
+/// Returns an error after the data of `inner`.
+struct FailingReader<R>(R);
+
+impl<R: Read> Read for FailingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.read(buf)? {
+            0 => Err(std::io::Error::new(ErrorKind::Other, "read failed")),
+            size => Ok(size),
+        }
+    }
+}
+
+#[test]
+fn failed_entry() {
+    let mut writer = SevenZWriter::new(Cursor::new(Vec::new()), &SevenZOptions::default()).unwrap();
+    let entry = SevenZEntry {
+        name: "text.txt".to_string(),
+        ..Default::default()
+    };
+    let data = FailingReader(&TEXT[..1000]);
+    assert!(writer.push_entry(entry.clone(), Some(data)).is_err());
+    assert!(writer.push_entry(entry, Some(TEXT)).is_err());
+    assert!(writer.finish().is_err());
+}
@@ -0,0 +1,227 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{Cursor, ErrorKind, Read, Seek, SeekFrom, Write};
+
+use lzma_rust::xz::CheckType;
+use lzma_rust::{SeekableXZReader, XZOptions, XZReader, XZWriter};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+/// Compressed by `xz -6 --check=crc32`.
+const TEXT_XZ: &[u8] = include_bytes!("fixtures/text.xz");
+/// Compressed by `xz -6 --check=crc64`.
+const TEXT_CRC64_XZ: &[u8] = include_bytes!("fixtures/text-crc64.xz");
+/// Compressed by `xz -6 --check=sha256`.
+const TEXT_SHA256_XZ: &[u8] = include_bytes!("fixtures/text-sha256.xz");
+/// Compressed by `xz -0 --check=none`.
+const TEXT_NONE_XZ: &[u8] = include_bytes!("fixtures/text-none.xz");
+/// Compressed by `xz -6 --check=crc32 --block-size=16KiB`.
+const TEXT_BLOCKS_XZ: &[u8] = include_bytes!("fixtures/text-blocks.xz");
+/// A stream without blocks whose index lists three blocks of the maximum unpadded size.
+const INDEX_OVERFLOW_XZ: &[u8] = include_bytes!("fixtures/index-overflow.xz");
+
+fn compress(data: &[u8], options: &XZOptions) -> Vec<u8> {
+    let mut writer = XZWriter::new(Vec::new(), options).unwrap();
+    writer.write_all(data).unwrap();
+    writer.finish().unwrap();
+    writer.into_inner()
+}
+
+fn decompress(data: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut reader = XZReader::new(data)?;
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn read_xz_fixture() {
+    assert_eq!(decompress(TEXT_XZ).unwrap(), TEXT);
+    assert_eq!(decompress(TEXT_CRC64_XZ).unwrap(), TEXT);
+    assert_eq!(decompress(TEXT_SHA256_XZ).unwrap(), TEXT);
+    assert_eq!(decompress(TEXT_NONE_XZ).unwrap(), TEXT);
+}
+
+#[test]
+fn read_blocks_fixture() {
+    assert_eq!(decompress(TEXT_BLOCKS_XZ).unwrap(), TEXT);
+    let reader = SeekableXZReader::new(Cursor::new(TEXT_BLOCKS_XZ)).unwrap();
+    assert_eq!(reader.block_count(), 3);
+}
+
+#[test]
+fn write_blocks_round_trip() {
+    let mut options = XZOptions::with_preset(6);
+    options.block_size = Some(16 * 1024);
+    let compressed = compress(TEXT, &options);
+    assert_eq!(decompress(&compressed).unwrap(), TEXT);
+    let reader = SeekableXZReader::new(Cursor::new(&compressed)).unwrap();
+    assert_eq!(reader.block_count(), 3);
+}
+
+#[test]
+fn round_trip() {
+    for preset in [0, 3, 6, 9] {
+        let options = XZOptions::with_preset(preset);
+        assert_eq!(decompress(&compress(TEXT, &options)).unwrap(), TEXT);
+    }
+    let options = XZOptions::with_preset(1);
+    assert_eq!(decompress(&compress(&[], &options)).unwrap(), b"");
+    for check in [CheckType::None, CheckType::CRC64, CheckType::SHA256] {
+        let mut options = XZOptions::with_preset(1);
+        options.check = check;
+        options.block_size = Some(10000);
+        assert_eq!(decompress(&compress(TEXT, &options)).unwrap(), TEXT);
+    }
+}
+
+#[test]
+fn concatenated_streams() {
+    let mut data = TEXT_XZ.to_vec();
+    data.extend_from_slice(&[0; 8]);
+    data.extend_from_slice(TEXT_SHA256_XZ);
+    data.extend_from_slice(&compress(&[], &XZOptions::with_preset(0)));
+    data.extend_from_slice(TEXT_NONE_XZ);
+    data.extend_from_slice(&[0; 4]);
+    assert_eq!(decompress(&data).unwrap(), TEXT.repeat(3));
+
+    data.extend_from_slice(&[0; 3]);
+    assert!(decompress(&data).is_err());
+}
+
+#[test]
+fn single_stream() {
+    let mut data = TEXT_XZ.to_vec();
+    data.extend_from_slice(b"trailing data");
+    assert!(decompress(&data).is_err());
+
+    let mut reader = XZReader::new(&data[..]).unwrap();
+    reader.set_single_stream(true);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).unwrap();
+    assert_eq!(buf, TEXT);
+    assert_eq!(reader.into_inner(), b"trailing data");
+}
+
+#[test]
+fn seekable() {
+    let mut options = XZOptions::with_preset(1);
+    options.block_size = Some(5000);
+    let mut data = compress(TEXT, &options);
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(TEXT_BLOCKS_XZ);
+    let expected = TEXT.repeat(2);
+
+    let mut reader = SeekableXZReader::new(Cursor::new(&data)).unwrap();
+    assert_eq!(reader.stream_count(), 2);
+    assert_eq!(reader.block_count(), 9 + 3);
+    assert_eq!(reader.uncompressed_size(), expected.len() as u64);
+
+    let mut buf = vec![0; 7000];
+    for pos in [
+        0, 79999, 4999, 5000, 5001, 40000, 42000, 1, 60000, 100, 12345,
+    ] {
+        let pos = reader.seek(SeekFrom::Start(pos)).unwrap() as usize;
+        let len = buf.len().min(expected.len() - pos);
+        reader.read_exact(&mut buf[..len]).unwrap();
+        assert_eq!(buf[..len], expected[pos..pos + len]);
+    }
+    reader.seek(SeekFrom::End(-10)).unwrap();
+    let mut tail = Vec::new();
+    reader.read_to_end(&mut tail).unwrap();
+    assert_eq!(tail, expected[expected.len() - 10..]);
+    reader.seek(SeekFrom::Current(100)).unwrap();
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    assert!(reader.seek(SeekFrom::Current(-100000)).is_err());
+
+    let mut data = data.clone();
+    data.truncate(data.len() - 4);
+    assert!(SeekableXZReader::new(Cursor::new(&data)).is_err());
+}
+
+#[test]
+fn corrupt_check() {
+    let mut data = TEXT_XZ.to_vec();
+    // last byte of the CRC32 of the only block, right before the index
+    let pos = data.len() - 12 - 12 - 1;
+    data[pos] ^= 0x01;
+    assert!(decompress(&data).is_err());
+
+    for (fixture, check_size) in [(TEXT_CRC64_XZ, 8), (TEXT_SHA256_XZ, 32)] {
+        let mut data = fixture.to_vec();
+        let pos = data.len() - 12 - 12 - check_size;
+        data[pos] ^= 0x01;
+        assert!(decompress(&data).is_err());
+    }
+}
+
+#[test]
+fn read_header() {
+    let reader = XZReader::new(TEXT_XZ).unwrap();
+    assert_eq!(reader.check_type(), CheckType::CRC32);
+
+    let mut data = TEXT_XZ.to_vec();
+    data[0] = 0;
+    assert_eq!(
+        XZReader::new(&data[..]).err().unwrap().kind(),
+        ErrorKind::InvalidData
+    );
+    assert!(XZReader::new(&TEXT_XZ[..8]).is_err());
+}
+
+#[test]
+fn memory_limit() {
+    let mut reader = XZReader::new_mem_limit(TEXT_XZ, 1024).unwrap();
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+}
+
+#[test]
+fn truncated() {
+    for len in [TEXT_XZ.len() / 2, TEXT_XZ.len() - 1] {
+        assert!(decompress(&TEXT_XZ[..len]).is_err());
+    }
+}
+
+#[test]
+fn into_inner_after_error() {
+    let mut reader = XZReader::new_mem_limit(TEXT_XZ, 1024).unwrap();
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+    let _: &[u8] = reader.into_inner();
+
+    let mut data = TEXT_XZ.to_vec();
+    let pos = data.len() - 12 - 12 - 1;
+    data[pos] ^= 0x01;
+    let mut reader = XZReader::new(&data[..]).unwrap();
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    // The reader stopped right after the corrupt check.
+    assert_eq!(reader.into_inner().len(), 12 + 12);
+}
+
+#[test]
+fn seekable_into_inner_after_error() {
+    let mut reader = SeekableXZReader::new_mem_limit(Cursor::new(TEXT_XZ), 1024).unwrap();
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfMemory);
+    reader.into_inner();
+
+    let mut data = TEXT_XZ.to_vec();
+    let pos = data.len() - 12 - 12 - 1;
+    data[pos] ^= 0x01;
+    let mut reader = SeekableXZReader::new(Cursor::new(&data)).unwrap();
+    let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(reader.into_inner().position(), pos as u64 + 1);
+}
+
+#[test]
+fn index_overflow() {
+    let err = SeekableXZReader::new(Cursor::new(INDEX_OVERFLOW_XZ))
+        .err()
+        .unwrap();
+    assert_eq!(err.kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        decompress(INDEX_OVERFLOW_XZ).unwrap_err().kind(),
+        ErrorKind::InvalidData
+    );
+}
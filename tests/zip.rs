@@ -0,0 +1,87 @@
+#![cfg(all(feature = "encoder", not(feature = "no_std")))]
+
+use std::io::{Read, Write};
+
+use lzma_rust::zip::{FLAG_EOS_MARKER, METHOD_LZMA};
+use lzma_rust::{LZMA2Options, ZipLzmaReader, ZipLzmaWriter};
+
+const TEXT: &[u8] = include_bytes!("fixtures/text.txt");
+/// Created by Python's `zipfile` with `ZIP_LZMA`, which writes an end of stream marker.
+const TEXT_ZIP: &[u8] = include_bytes!("fixtures/text.zip");
+
+/// The fields of the local file header needed to decode the only entry of a ZIP file.
+struct LocalEntry<'a> {
+    flags: u16,
+    method: u16,
+    uncompressed_size: u64,
+    data: &'a [u8],
+}
+
+fn local_entry(zip: &[u8]) -> LocalEntry<'_> {
+    let u16_at = |i: usize| u16::from_le_bytes([zip[i], zip[i + 1]]);
+    let u32_at = |i: usize| u32::from_le_bytes([zip[i], zip[i + 1], zip[i + 2], zip[i + 3]]);
+    assert_eq!(&zip[..4], b"PK\x03\x04");
+    let start = 30 + u16_at(26) as usize + u16_at(28) as usize;
+    LocalEntry {
+        flags: u16_at(6),
+        method: u16_at(8),
+        uncompressed_size: u32_at(22) as u64,
+        data: &zip[start..start + u32_at(18) as usize],
+    }
+}
+
+fn compress(data: &[u8], eos_marker: bool) -> Vec<u8> {
+    let mut writer =
+        ZipLzmaWriter::new(Vec::new(), &LZMA2Options::with_preset(6), eos_marker).unwrap();
+    writer.write_all(data).unwrap();
+    writer.finish().unwrap();
+    assert_eq!(writer.uncompressed_size(), data.len() as u64);
+    let compressed_size = writer.compressed_size();
+    let compressed = writer.into_inner();
+    assert_eq!(compressed.len() as u64, compressed_size);
+    compressed
+}
+
+fn decompress(data: &[u8], uncompressed_size: u64, eos_marker: bool) -> std::io::Result<Vec<u8>> {
+    let mut reader = ZipLzmaReader::new(data, uncompressed_size, eos_marker)?;
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+#[test]
+fn read_zip_fixture() {
+    let entry = local_entry(TEXT_ZIP);
+    assert_eq!(entry.method, METHOD_LZMA);
+    assert_eq!(entry.flags & FLAG_EOS_MARKER, FLAG_EOS_MARKER);
+    let decompressed = decompress(entry.data, entry.uncompressed_size, true).unwrap();
+    assert_eq!(decompressed, TEXT);
+    let decompressed = decompress(entry.data, u64::MAX, true).unwrap();
+    assert_eq!(decompressed, TEXT);
+}
+
+#[test]
+fn round_trip() {
+    for eos_marker in [true, false] {
+        let compressed = compress(TEXT, eos_marker);
+        assert_eq!(&compressed[2..4], &[5, 0]);
+        let decompressed = decompress(&compressed, TEXT.len() as u64, eos_marker).unwrap();
+        assert_eq!(decompressed, TEXT);
+    }
+    let compressed = compress(TEXT, true);
+    assert_eq!(decompress(&compressed, u64::MAX, true).unwrap(), TEXT);
+    // Without an end marker the uncompressed size is required.
+    let compressed = compress(TEXT, false);
+    assert!(decompress(&compressed, u64::MAX, false).is_err());
+
+    let compressed = compress(b"", true);
+    assert_eq!(decompress(&compressed, 0, true).unwrap(), b"");
+}
+
+#[test]
+fn invalid_header() {
+    let mut compressed = compress(TEXT, true);
+    compressed[2] = 4;
+    let err = decompress(&compressed, TEXT.len() as u64, true).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+}